@@ -0,0 +1,122 @@
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::vec3::Point3;
+
+// Axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl Aabb {
+    // Box around the two corner points, given in any order.
+    pub fn new(a: Point3, b: Point3) -> Self {
+        Self::empty().include(a).include(b)
+    }
+
+    #[inline]
+    pub fn empty() -> Self {
+        Self {
+            min: Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Point3::new(-f32::INFINITY, -f32::INFINITY, -f32::INFINITY),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|axis| self.min[axis] > self.max[axis])
+    }
+
+    pub fn include(mut self, p: Point3) -> Self {
+        for axis in 0..3 {
+            self.min[axis] = self.min[axis].min(p[axis]);
+            self.max[axis] = self.max[axis].max(p[axis]);
+        }
+        self
+    }
+
+    pub fn surrounding(a: &Aabb, b: &Aabb) -> Self {
+        if a.is_empty() {
+            return *b;
+        }
+        if b.is_empty() {
+            return *a;
+        }
+        a.include(b.min).include(b.max)
+    }
+
+    #[inline]
+    pub fn centroid(&self) -> Point3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        let extent = self.max - self.min;
+        if extent[0] > extent[1] && extent[0] > extent[2] {
+            0
+        } else if extent[1] > extent[2] {
+            1
+        } else {
+            2
+        }
+    }
+
+    // Slab test, whether the ray enters the box somewhere within `ray_t`.
+    pub fn hit(&self, r: &Ray, mut ray_t: Interval) -> bool {
+        let origin = r.origin();
+        let direction = r.direction();
+        for axis in 0..3 {
+            let inv_d = 1.0 / direction[axis];
+            let t0 = (self.min[axis] - origin[axis]) * inv_d;
+            let t1 = (self.max[axis] - origin[axis]) * inv_d;
+            let (t0, t1) = if inv_d < 0.0 { (t1, t0) } else { (t0, t1) };
+            // Written so that a NaN from 0 * inf leaves the interval untouched.
+            if t0 > ray_t.min {
+                ray_t.min = t0;
+            }
+            if t1 < ray_t.max {
+                ray_t.max = t1;
+            }
+            if ray_t.max < ray_t.min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn test_hit() {
+        let bbox = Aabb::new(Point3::new(1.0, 1.0, 1.0), Point3::new(-1.0, -1.0, -1.0));
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(bbox.hit(&ray, Interval::new(0.001, f32::INFINITY)));
+        assert!(!bbox.hit(&ray, Interval::new(0.001, 3.0)));
+
+        let ray = Ray::new(Point3::new(-5.0, 2.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(!bbox.hit(&ray, Interval::new(0.001, f32::INFINITY)));
+    }
+
+    #[test]
+    fn test_surrounding() {
+        let a = Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+        let b = Aabb::new(Point3::new(2.0, -1.0, 0.5), Point3::new(3.0, 0.0, 0.5));
+        let both = Aabb::surrounding(&a, &b);
+        assert_eq!(both.min, Point3::new(0.0, -1.0, 0.0));
+        assert_eq!(both.max, Point3::new(3.0, 1.0, 1.0));
+        assert_eq!(both.longest_axis(), 0);
+        assert_eq!(Aabb::surrounding(&Aabb::empty(), &a), a);
+        assert!(Aabb::empty().is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+// Keyframed values over time, for camera paths and animated scenes.
+
+use crate::vec3::Vec3;
+
+use std::str::FromStr;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    // Smooth spline through every key, with the tangents from the neighbouring keys.
+    #[default]
+    CatmullRom,
+}
+
+impl Interpolation {
+    pub const ALL: [Interpolation; 2] = [Interpolation::Linear, Interpolation::CatmullRom];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Interpolation::Linear => "linear",
+            Interpolation::CatmullRom => "catmull-rom",
+        }
+    }
+}
+
+impl FromStr for Interpolation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Interpolation::ALL
+            .into_iter()
+            .find(|interpolation| interpolation.name() == s)
+            .ok_or_else(|| format!("unknown interpolation `{}`", s))
+    }
+}
+
+// Values which can be blended, as weighted sums.
+pub trait Animatable: Copy {
+    fn add(self, other: Self) -> Self;
+    fn scale(self, factor: f32) -> Self;
+}
+
+impl Animatable for f32 {
+    fn add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn scale(self, factor: f32) -> Self {
+        self * factor
+    }
+}
+
+impl Animatable for Vec3 {
+    fn add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn scale(self, factor: f32) -> Self {
+        factor * self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    // Sorted by time.
+    keys: Vec<(f32, T)>,
+    interpolation: Interpolation,
+}
+
+impl<T: Animatable> Track<T> {
+    pub fn new(interpolation: Interpolation) -> Self {
+        Self {
+            keys: Vec::new(),
+            interpolation,
+        }
+    }
+
+    // Adds a key, replacing the one at the same time if there is any.
+    pub fn with_key(mut self, time: f32, value: T) -> Self {
+        let index = self.keys.partition_point(|(t, _)| *t < time);
+        match self.keys.get(index) {
+            Some((t, _)) if *t == time => self.keys[index].1 = value,
+            _ => self.keys.insert(index, (time, value)),
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn keys(&self) -> &[(f32, T)] {
+        &self.keys
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    // The value at `time`, held constant before the first and after the last key.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let (first, last) = (self.keys.first()?, self.keys.last()?);
+        if time <= first.0 {
+            return Some(first.1);
+        }
+        if time >= last.0 {
+            return Some(last.1);
+        }
+        let i = self.keys.partition_point(|(t, _)| *t <= time) - 1;
+        let (t1, p1) = self.keys[i];
+        let (t2, p2) = self.keys[i + 1];
+        let s = (time - t1) / (t2 - t1);
+        Some(match self.interpolation {
+            Interpolation::Linear => p1.scale(1.0 - s).add(p2.scale(s)),
+            Interpolation::CatmullRom => {
+                // The end keys are repeated, which leaves the spline with the slope of the chord.
+                let (t0, p0) = if i > 0 { self.keys[i - 1] } else { (t1, p1) };
+                let (t3, p3) = self.keys.get(i + 2).copied().unwrap_or((t2, p2));
+                // Tangents scaled to the length of the segment, for unevenly spaced keys.
+                let m1 = p2.add(p0.scale(-1.0)).scale((t2 - t1) / (t2 - t0));
+                let m2 = p3.add(p1.scale(-1.0)).scale((t2 - t1) / (t3 - t1));
+                let (s2, s3) = (s * s, s * s * s);
+                p1.scale(2.0 * s3 - 3.0 * s2 + 1.0)
+                    .add(m1.scale(s3 - 2.0 * s2 + s))
+                    .add(p2.scale(3.0 * s2 - 2.0 * s3))
+                    .add(m2.scale(s3 - s2))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track() {
+        let linear = Track::new(Interpolation::Linear)
+            .with_key(2.0, 4.0)
+            .with_key(0.0, 0.0)
+            .with_key(1.0, 1.0);
+        assert_eq!(linear.sample(-1.0), Some(0.0));
+        assert_eq!(linear.sample(0.5), Some(0.5));
+        assert_eq!(linear.sample(1.5), Some(2.5));
+        assert_eq!(linear.sample(3.0), Some(4.0));
+        assert_eq!(Track::<f32>::new(Interpolation::Linear).sample(0.0), None);
+
+        // Evenly spaced keys on a line stay on it, the spline passes through every key.
+        let spline = Track::new(Interpolation::CatmullRom)
+            .with_key(0.0, Vec3::new(0.0, 0.0, 0.0))
+            .with_key(1.0, Vec3::new(1.0, 2.0, 0.0))
+            .with_key(2.0, Vec3::new(2.0, 4.0, 0.0))
+            .with_key(3.0, Vec3::new(3.0, 6.0, 0.0));
+        let p = spline.sample(1.25).unwrap();
+        assert!((p - Vec3::new(1.25, 2.5, 0.0)).length() < 1e-5);
+        assert!((spline.sample(2.0).unwrap() - Vec3::new(2.0, 4.0, 0.0)).length() < 1e-6);
+    }
+}
@@ -1,37 +1,884 @@
-use ray_tracing_in_one_weekend::{camera, generate_scene, render, vec3::Point3};
+use clap::{value_parser, Arg, ArgAction, Command};
+use ray_tracing_in_one_weekend::{
+    camera::{self, Eye, StereoLayout},
+    environment,
+    filter::{Filter, FilterKind},
+    framebuffer::Framebuffer,
+    generate_scene,
+    gltf::{self, GltfCamera},
+    hittable::Hittable,
+    ply,
+    progress::Progress,
+    render, rtweekend,
+    sampler::SamplerKind,
+    scene, sky, stl,
+    vec3::{self, Point3},
+    world::World,
+    writer,
+};
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+fn draw_progress_bar(progress: &Progress) {
+    const WIDTH: usize = 40;
+    let fraction = progress.fraction().clamp(0.0, 1.0);
+    let filled = (fraction * WIDTH as f32) as usize;
+    let eta = match progress.eta {
+        Some(eta) => {
+            let seconds = eta.as_secs();
+            format!(
+                "{}:{:02}:{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            )
+        }
+        None => "--:--:--".to_string(),
+    };
+    let mut stderr = std::io::stderr().lock();
+    let _ = write!(
+        stderr,
+        "\r[{}{}] {:5.1}% pass {}/{} tile {}/{} ETA {}",
+        "#".repeat(filled),
+        " ".repeat(WIDTH - filled),
+        fraction * 100.0,
+        progress.pass,
+        progress.passes,
+        progress.tiles_done,
+        progress.tiles_total,
+        eta
+    );
+    let _ = stderr.flush();
+}
+
+// A loaded scene with the camera and the camera path it brings along, if any.
+type Input = (World, Option<GltfCamera>, Option<camera::CameraPath>);
+
+// Loads a scene file by its extension, only glTF and JSON scenes can bring a camera along.
+fn load_input(path: &Path) -> io::Result<Input> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let mesh: Arc<dyn Hittable> = match extension.as_str() {
+        "gltf" | "glb" => {
+            let scene = gltf::load(path)?;
+            return Ok((
+                World::new(scene.world.objects().to_vec()),
+                scene.camera,
+                None,
+            ));
+        }
+        "json" => {
+            let scene = scene::load(path)?;
+            return Ok((scene.world, scene.camera, scene.camera_path));
+        }
+        "ply" => Arc::new(ply::load(path)?),
+        "stl" => Arc::new(stl::load(path)?),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown scene format `{extension}`"),
+            ))
+        }
+    };
+    Ok((World::new(vec![mesh]), None, None))
+}
 
 fn main() {
     env_logger::init();
 
+    let matches = Command::new("sansara")
+        .about("Renders the final scene of \"Ray Tracing in One Weekend\"")
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .value_name("FILE")
+                .help("Write the image to FILE (.ppm or .pfm) instead of PPM to stdout")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("scene")
+                .long("scene")
+                .help("Built-in scene to render")
+                .value_parser(["random-spheres", "microfacet-spheres", "principled-spheres"])
+                .default_value("random-spheres"),
+        )
+        .arg(
+            Arg::new("input")
+                .long("input")
+                .short('i')
+                .value_name("FILE")
+                .help("Render a JSON scene (.json), a glTF scene (.gltf, .glb) or a mesh (.ply, .stl) instead of a built-in scene")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("projection")
+                .long("projection")
+                .help("Camera projection, ignored when the loaded scene has a camera")
+                .value_parser(camera::Projection::NAMES)
+                .default_value("perspective"),
+        )
+        .arg(
+            Arg::new("view-height")
+                .long("view-height")
+                .value_name("UNITS")
+                .help("World units visible vertically with the orthographic projection, the height of the perspective view at the focus distance by default")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("fov")
+                .long("fov")
+                .value_name("DEGREES")
+                .help("Field of view of the fisheye lenses across the image circle")
+                .value_parser(value_parser!(f32))
+                .default_value("180"),
+        )
+        .arg(
+            Arg::new("focal-length")
+                .long("focal-length")
+                .value_name("MM")
+                .help("Use a physical camera with this lens, the scene is then measured in meters")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("f-number")
+                .long("f-number")
+                .value_name("N")
+                .help("Aperture of the physical camera")
+                .value_parser(value_parser!(f32))
+                .default_value("2.8"),
+        )
+        .arg(
+            Arg::new("sensor-width")
+                .long("sensor-width")
+                .value_name("MM")
+                .help("Sensor width of the physical camera")
+                .value_parser(value_parser!(f32))
+                .default_value("36"),
+        )
+        .arg(
+            Arg::new("sensor-height")
+                .long("sensor-height")
+                .value_name("MM")
+                .help("Sensor height of the physical camera")
+                .value_parser(value_parser!(f32))
+                .default_value("24"),
+        )
+        .arg(
+            Arg::new("shutter")
+                .long("shutter")
+                .value_name("SECONDS")
+                .help("Shutter time of the physical camera and of motion blur")
+                .value_parser(value_parser!(f32))
+                .default_value("0.008"),
+        )
+        .arg(
+            Arg::new("iso")
+                .long("iso")
+                .help("Sensitivity of the physical camera")
+                .value_parser(value_parser!(f32))
+                .default_value("100"),
+        )
+        .arg(
+            Arg::new("aperture-blades")
+                .long("aperture-blades")
+                .value_name("COUNT")
+                .help("Polygonal lens aperture with COUNT blades instead of a circle")
+                .value_parser(value_parser!(u32).range(3..)),
+        )
+        .arg(
+            Arg::new("aperture-rotation")
+                .long("aperture-rotation")
+                .value_name("DEGREES")
+                .help("Rotation of the polygonal aperture")
+                .value_parser(value_parser!(f32))
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("aperture-image")
+                .long("aperture-image")
+                .value_name("FILE")
+                .help("Grayscale image of the lens aperture, for custom bokeh shapes")
+                .value_parser(value_parser!(PathBuf))
+                .conflicts_with("aperture-blades"),
+        )
+        .arg(
+            Arg::new("camera-path")
+                .long("camera-path")
+                .value_name("FILE")
+                .help("Move the camera along the keyframes of a JSON path")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("turntable")
+                .long("turntable")
+                .value_name("SECONDS")
+                .help("Orbit the camera once around its target")
+                .value_parser(value_parser!(f32))
+                .conflicts_with("camera-path"),
+        )
+        .arg(
+            Arg::new("frames")
+                .long("frames")
+                .value_name("RANGE")
+                .help("Render the frames FIRST-LAST into numbered files, the whole camera path by default")
+        )
+        .arg(
+            Arg::new("fps")
+                .long("fps")
+                .help("Frames per second of the animation")
+                .value_parser(value_parser!(f32))
+                .default_value("24"),
+        )
+        .arg(
+            Arg::new("sky")
+                .long("sky")
+                .help("Light the scene with a physical sky and sun instead of the gradient")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("sun-elevation")
+                .long("sun-elevation")
+                .value_name("DEGREES")
+                .help("Height of the sun above the horizon")
+                .value_parser(value_parser!(f32))
+                .default_value("45"),
+        )
+        .arg(
+            Arg::new("sun-azimuth")
+                .long("sun-azimuth")
+                .value_name("DEGREES")
+                .help("Direction of the sun, turned from +z towards +x")
+                .value_parser(value_parser!(f32))
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("turbidity")
+                .long("turbidity")
+                .help("Haziness of the sky, from 2 for clear air to 10")
+                .value_parser(value_parser!(f32))
+                .default_value("3"),
+        )
+        .arg(
+            Arg::new("sky-intensity")
+                .long("sky-intensity")
+                .help(
+                    "Scale of the sky and the sun from kcd/m², 1 makes a unit of radiance \
+                     1 kcd/m² [default: 0.05, a unit is 20 kcd/m², or 1000 for the cd/m² of \
+                     --focal-length]",
+                )
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("environment")
+                .long("environment")
+                .value_name("FILE")
+                .help("Light the scene with an equirectangular HDR image (.hdr)")
+                .value_parser(value_parser!(PathBuf))
+                .conflicts_with("sky"),
+        )
+        .arg(
+            Arg::new("environment-rotation")
+                .long("environment-rotation")
+                .value_name("DEGREES")
+                .help("Turn the environment around the up axis")
+                .value_parser(value_parser!(f32))
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("environment-intensity")
+                .long("environment-intensity")
+                .help("Brightness of the environment")
+                .value_parser(value_parser!(f32))
+                .default_value("1"),
+        )
+        .arg(
+            Arg::new("spectral")
+                .long("spectral")
+                .help("Trace light at sampled wavelengths instead of red, green and blue")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("motion-blur")
+                .long("motion-blur")
+                .help("Keep the shutter open while animated objects move")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("stereo")
+                .long("stereo")
+                .value_name("LAYOUT")
+                .help("Render both eyes, next to each other, above each other or into two files")
+                .value_parser(StereoLayout::ALL.map(|layout| layout.name())),
+        )
+        .arg(
+            Arg::new("interocular")
+                .long("interocular")
+                .value_name("DISTANCE")
+                .help("Distance between the stereo eyes, 1/30 of the convergence distance by default")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("convergence")
+                .long("convergence")
+                .value_name("DISTANCE")
+                .help("Distance of the stereo screen plane, the focus distance by default")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .help("Seed for the scene layout and the sampling")
+                .value_parser(value_parser!(u64))
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("samples")
+                .long("samples")
+                .help("Samples per pixel, the upper bound when a budget is given")
+                .value_parser(value_parser!(u32))
+                .default_value("500"),
+        )
+        .arg(
+            Arg::new("sampler")
+                .long("sampler")
+                .help("Sample pattern for pixels, lens and scattering")
+                .value_parser(SamplerKind::ALL.map(|kind| kind.name()))
+                .default_value("independent"),
+        )
+        .arg(
+            Arg::new("filter")
+                .long("filter")
+                .help("Pixel reconstruction filter")
+                .value_parser(FilterKind::ALL.map(|kind| kind.name()))
+                .default_value("box"),
+        )
+        .arg(
+            Arg::new("filter-radius")
+                .long("filter-radius")
+                .value_name("PIXELS")
+                .help("Radius of the reconstruction filter, depends on the filter by default")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("time-limit")
+                .long("time-limit")
+                .value_name("SECONDS")
+                .help("Stop sampling before the render takes longer than SECONDS")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("noise-threshold")
+                .long("noise-threshold")
+                .value_name("ERROR")
+                .help("Stop sampling once the relative pixel error drops below ERROR")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("checkpoint")
+                .long("checkpoint")
+                .value_name("FILE")
+                .help("Periodically save the render progress to FILE")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("checkpoint-interval")
+                .long("checkpoint-interval")
+                .value_name("SECONDS")
+                .help("Minimal time between two checkpoints")
+                .value_parser(value_parser!(u64))
+                .default_value("60"),
+        )
+        .arg(
+            Arg::new("resume")
+                .long("resume")
+                .value_name("FILE")
+                .help("Continue the render saved in the checkpoint FILE")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("quiet")
+                .long("quiet")
+                .short('q')
+                .help("Don't draw a progress bar")
+                .action(ArgAction::SetTrue),
+        )
+        .get_matches();
+
+    let seed = *matches.get_one::<u64>("seed").unwrap();
+    // Count of random samples for each pixel
+    let sample_per_pixel = *matches.get_one::<u32>("samples").unwrap();
+    let sampler: SamplerKind = matches
+        .get_one::<String>("sampler")
+        .unwrap()
+        .parse()
+        .unwrap();
+    let filter_kind: FilterKind = matches
+        .get_one::<String>("filter")
+        .unwrap()
+        .parse()
+        .unwrap();
+    let filter = match matches.get_one::<f32>("filter-radius") {
+        Some(radius) => Filter::with_radius(filter_kind, *radius),
+        None => Filter::new(filter_kind),
+    };
+    let resume = matches.get_one::<PathBuf>("resume");
+    // Keep checkpointing into the file we resume from, unless told otherwise.
+    let checkpoint = matches.get_one::<PathBuf>("checkpoint").or(resume);
+    let checkpoint_interval = *matches.get_one::<u64>("checkpoint-interval").unwrap();
+
     // World
-    let world = generate_scene::random_sphere();
+    rtweekend::seed(seed);
+    let mut scene_camera = None;
+    let mut scene_camera_path = None;
+    let mut world = match matches.get_one::<PathBuf>("input") {
+        Some(path) => match load_input(path) {
+            Ok((world, camera, camera_path)) => {
+                scene_camera = camera;
+                scene_camera_path = camera_path;
+                world
+            }
+            Err(err) => {
+                eprintln!("Can't load {}: {}", path.display(), err);
+                std::process::exit(1);
+            }
+        },
+        None => {
+            let world = match matches.get_one::<String>("scene").unwrap().as_str() {
+                "microfacet-spheres" => generate_scene::microfacet_spheres(),
+                "principled-spheres" => generate_scene::principled_spheres(),
+                _ => generate_scene::random_sphere(),
+            };
+            World::new(world.objects().to_vec())
+        }
+    };
+    if matches.get_flag("sky") {
+        let sky = sky::Sky::new(
+            *matches.get_one::<f32>("sun-elevation").unwrap(),
+            *matches.get_one::<f32>("sun-azimuth").unwrap(),
+            *matches.get_one::<f32>("turbidity").unwrap(),
+        )
+        // A physical camera exposes for radiance in cd/m², the sky comes in kcd/m².
+        .with_intensity(matches.get_one::<f32>("sky-intensity").copied().unwrap_or(
+            if matches.contains_id("focal-length") {
+                1000.0
+            } else {
+                0.05
+            },
+        ));
+        world = world
+            .with_light(sky.sun())
+            .with_background(environment::Background::Sky(sky));
+    }
+    if let Some(path) = matches.get_one::<PathBuf>("environment") {
+        match environment::Environment::load(path) {
+            Ok(map) => {
+                let map = map
+                    .with_rotation(*matches.get_one::<f32>("environment-rotation").unwrap())
+                    .with_intensity(*matches.get_one::<f32>("environment-intensity").unwrap());
+                world = world.with_background(environment::Background::Environment(Arc::new(map)));
+            }
+            Err(err) => {
+                eprintln!("Can't load {}: {}", path.display(), err);
+                std::process::exit(1);
+            }
+        }
+    }
 
     // Image
     // Ratio of image width over height
     const ASPECT_RATIO: f32 = 16.0 / 9.0;
     // Rendered image width in pixel count
     const IMAGE_WIDTH: i32 = 1280;
-    // Count of random samples for each pixel
-    const SAMPLE_PER_PIXEL: u32 = 500;
     // Maximum number of ray bounces into scene
     const MAX_DEPTH: u32 = 50;
 
-    const VFOV: f32 = 20.0;
-    let look_from = Point3::new(13.0, 2.0, 3.0);
-    let look_at = Point3::new(0.0, 0.0, 0.0);
+    // A physical lens replaces the default field of view, depth of field and exposure.
+    let physical = matches.get_one::<f32>("focal-length").map(|focal_length| {
+        camera::PhysicalCamera::new(*focal_length, *matches.get_one::<f32>("f-number").unwrap())
+            .with_sensor(
+                *matches.get_one::<f32>("sensor-width").unwrap(),
+                *matches.get_one::<f32>("sensor-height").unwrap(),
+            )
+            .with_shutter(*matches.get_one::<f32>("shutter").unwrap())
+            .with_iso(*matches.get_one::<f32>("iso").unwrap())
+    });
+    let vfov = physical.map_or(20.0, |physical| physical.vfov());
+    let mut look_from = Point3::new(13.0, 2.0, 3.0);
+    let mut look_at = Point3::new(0.0, 0.0, 0.0);
     let vup = Point3::new(0.0, 1.0, 0.0);
-    let defocus_angle = 0.6;
-    let focus_distance = 10.0;
+    let mut defocus_angle = 0.6;
+    let mut focus_distance = 10.0;
+    if matches.contains_id("input") {
+        // Frame the whole loaded scene from the same direction, without depth of field.
+        let bbox = world.bounding_box();
+        let radius = (bbox.max - bbox.min).length() / 2.0;
+        focus_distance = radius / (rtweekend::degrees_to_radians(vfov) / 2.0).sin();
+        look_at = bbox.centroid();
+        look_from = look_at + focus_distance * vec3::unit_vector(look_from);
+        defocus_angle = 0.0;
+    }
 
     // Camera
     let image = camera::Image::with_width(IMAGE_WIDTH);
-    let camera_settings =
-        camera::CameraSettings::new(ASPECT_RATIO, VFOV, defocus_angle, focus_distance);
-    let transform = camera::Xform::new(look_from, look_at, vup);
-    let camera = camera::Camera::new(image, transform, camera_settings);
+    let mut camera = match scene_camera {
+        Some(scene_camera) => {
+            let mut camera_settings = scene_camera.settings;
+            if let Some(physical) = physical {
+                camera_settings = camera_settings.with_exposure(physical.exposure());
+            }
+            camera::Camera::new(image, scene_camera.transform, camera_settings)
+        }
+        None => {
+            let camera_settings = match physical {
+                Some(physical) => physical.settings(focus_distance),
+                None => {
+                    camera::CameraSettings::new(ASPECT_RATIO, vfov, defocus_angle, focus_distance)
+                }
+            };
+            let fov = *matches.get_one::<f32>("fov").unwrap();
+            let (aspect_ratio, projection) =
+                match matches.get_one::<String>("projection").unwrap().as_str() {
+                    "orthographic" => {
+                        // Keep the framing of the perspective view in the plane of focus.
+                        let height = match matches.get_one::<f32>("view-height") {
+                            Some(height) => *height,
+                            None => {
+                                2.0 * (rtweekend::degrees_to_radians(vfov) / 2.0).tan()
+                                    * focus_distance
+                            }
+                        };
+                        (
+                            camera_settings.aspect_ratio,
+                            camera::Projection::Orthographic { height },
+                        )
+                    }
+                    "equirectangular" => (2.0, camera::Projection::Equirectangular),
+                    "fisheye-equidistant" => (
+                        1.0,
+                        camera::Projection::Fisheye {
+                            mapping: camera::FisheyeMapping::Equidistant,
+                            fov,
+                        },
+                    ),
+                    "fisheye-equisolid" => (
+                        1.0,
+                        camera::Projection::Fisheye {
+                            mapping: camera::FisheyeMapping::Equisolid,
+                            fov,
+                        },
+                    ),
+                    _ => (
+                        camera_settings.aspect_ratio,
+                        camera::Projection::Perspective,
+                    ),
+                };
+            let camera_settings = camera::CameraSettings {
+                aspect_ratio,
+                ..camera_settings
+            }
+            .with_projection(projection);
+            let transform = camera::Xform::new(look_from, look_at, vup);
+            camera::Camera::new(image, transform, camera_settings)
+        }
+    };
+
+    // Aperture, the shape of the bokeh
+    if let Some(path) = matches.get_one::<PathBuf>("aperture-image") {
+        match camera::ApertureImage::load(path) {
+            Ok(image) => {
+                camera.settings.aperture = camera::Aperture::Image(Arc::new(image));
+            }
+            Err(err) => {
+                eprintln!("Can't load {}: {}", path.display(), err);
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(blades) = matches.get_one::<u32>("aperture-blades") {
+        camera.settings.aperture = camera::Aperture::Polygon {
+            blades: *blades,
+            rotation: *matches.get_one::<f32>("aperture-rotation").unwrap(),
+        };
+    }
+
+    // Stereo
+    let stereo_layout: Option<StereoLayout> = matches
+        .get_one::<String>("stereo")
+        .map(|layout| layout.parse().unwrap());
+    if stereo_layout == Some(StereoLayout::Separate) && !matches.contains_id("output") {
+        eprintln!("Separate stereo images need an --output file");
+        std::process::exit(1);
+    }
+    if stereo_layout.is_some() {
+        let convergence = matches
+            .get_one::<f32>("convergence")
+            .copied()
+            .unwrap_or(camera.settings.focus_distance);
+        // The 1/30 rule of thumb, with the eyes apart by a thirtieth of the screen distance.
+        let interocular = matches
+            .get_one::<f32>("interocular")
+            .copied()
+            .unwrap_or(convergence / 30.0);
+        camera.settings = camera
+            .settings
+            .with_stereo(camera::Stereo::new(interocular, convergence));
+    }
 
     // Render
-    let render_settings = render::RenderSettings::new(SAMPLE_PER_PIXEL, MAX_DEPTH);
+    let mut render_settings = render::RenderSettings::new(sample_per_pixel, MAX_DEPTH)
+        .with_seed(seed)
+        .with_sampler(sampler)
+        .with_filter(filter)
+        .with_spectral(matches.get_flag("spectral"));
+    if let Some(seconds) = matches.get_one::<f32>("time-limit") {
+        render_settings = render_settings.with_time_limit(Duration::from_secs_f32(*seconds));
+    }
+    if let Some(noise_threshold) = matches.get_one::<f32>("noise-threshold") {
+        render_settings = render_settings.with_noise_threshold(*noise_threshold);
+    }
+    let quiet = matches.get_flag("quiet");
+    let checkpoint_interval = Duration::from_secs(checkpoint_interval);
+    let output = matches.get_one::<PathBuf>("output");
+
+    // Animation
+    let fps = *matches.get_one::<f32>("fps").unwrap();
+    let camera_path = if let Some(path) = matches.get_one::<PathBuf>("camera-path") {
+        match camera::CameraPath::load(path, &camera) {
+            Ok(camera_path) => Some(camera_path),
+            Err(err) => {
+                eprintln!("Can't load {}: {}", path.display(), err);
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(seconds) = matches.get_one::<f32>("turntable") {
+        Some(camera::CameraPath::orbit(&camera, *seconds))
+    } else {
+        scene_camera_path
+    };
+    // Objects are placed at the time of the frame, and move while the shutter is open.
+    let shutter = if matches.get_flag("motion-blur") {
+        *matches.get_one::<f32>("shutter").unwrap()
+    } else {
+        0.0
+    };
+    camera.settings = camera.settings.with_shutter(0.0, shutter);
+    let frames = match (matches.get_one::<String>("frames"), &camera_path) {
+        (Some(range), _) => match parse_frames(range) {
+            Some(frames) => Some(frames),
+            None => {
+                eprintln!("Invalid frame range `{range}`, expected FIRST-LAST");
+                std::process::exit(1);
+            }
+        },
+        // The whole path by default, without repeating the first frame of a closed loop.
+        (None, Some(camera_path)) => {
+            let (start, end) = camera_path.duration();
+            let (first, last) = ((start * fps).round() as i32, (end * fps).round() as i32);
+            Some((first, (last - 1).max(first)))
+        }
+        (None, None) => None,
+    };
+
+    let job = Job {
+        render_settings,
+        stereo_layout,
+        checkpoint_interval,
+        quiet,
+    };
+    let written = match frames {
+        None => job.render(
+            &camera,
+            output.cloned(),
+            checkpoint.cloned(),
+            resume.cloned(),
+            &world,
+        ),
+        Some((first, last)) => {
+            let Some(output) = output else {
+                eprintln!("Rendering frames needs an --output file");
+                std::process::exit(1);
+            };
+            (first..=last).try_for_each(|frame| {
+                if !quiet {
+                    eprintln!("Rendering frame {frame}");
+                }
+                let time = frame as f32 / fps;
+                let mut camera = match &camera_path {
+                    Some(camera_path) => camera_path.camera(&camera, time),
+                    None => camera.clone(),
+                };
+                camera.settings = camera.settings.with_shutter(time, time + shutter);
+                job.render(
+                    &camera,
+                    Some(frame_path(output, frame)),
+                    checkpoint.map(|path| frame_path(path, frame)),
+                    resume.map(|path| frame_path(path, frame)),
+                    &world,
+                )
+            })
+        }
+    };
+    if let Err(err) = written {
+        eprintln!("Can't write the image: {}", err);
+        std::process::exit(1);
+    }
+}
+
+// Everything to render one image from a camera, the same for every frame of a sequence.
+struct Job {
+    render_settings: render::RenderSettings,
+    stereo_layout: Option<StereoLayout>,
+    checkpoint_interval: Duration,
+    quiet: bool,
+}
+
+impl Job {
+    fn render(
+        &self,
+        camera: &camera::Camera,
+        output: Option<PathBuf>,
+        checkpoint: Option<PathBuf>,
+        resume: Option<PathBuf>,
+        world: &World,
+    ) -> io::Result<()> {
+        let Some(layout) = self.stereo_layout else {
+            let mut render_settings = self.render_settings.clone();
+            if let Some(path) = checkpoint {
+                render_settings = render_settings.with_checkpoint(path, self.checkpoint_interval);
+            }
+            let framebuffer = render_view(
+                render_settings,
+                camera.clone(),
+                resume.as_ref(),
+                self.quiet,
+                world,
+            );
+            return write_image(output.as_deref(), &framebuffer);
+        };
+
+        // Every eye is a render of its own, checkpointed into its own file.
+        let [left, right] = [Eye::Left, Eye::Right].map(|eye| {
+            if !self.quiet {
+                eprintln!("Rendering the {} eye", eye.name());
+            }
+            let mut render_settings = self.render_settings.clone();
+            if let Some(path) = &checkpoint {
+                render_settings =
+                    render_settings.with_checkpoint(eye_path(path, eye), self.checkpoint_interval);
+            }
+            let resume = resume.as_ref().map(|path| eye_path(path, eye));
+            let camera = camera.clone().with_eye(eye);
+            render_view(render_settings, camera, resume.as_ref(), self.quiet, world)
+        });
+        match layout {
+            StereoLayout::SideBySide => {
+                write_image(output.as_deref(), &Framebuffer::side_by_side(&left, &right))
+            }
+            StereoLayout::TopBottom => {
+                write_image(output.as_deref(), &Framebuffer::top_bottom(&left, &right))
+            }
+            StereoLayout::Separate => {
+                let path = output.unwrap();
+                writer::save(&eye_path(&path, Eye::Left), &left)
+                    .and_then(|_| writer::save(&eye_path(&path, Eye::Right), &right))
+            }
+        }
+    }
+}
+
+fn render_view(
+    render_settings: render::RenderSettings,
+    camera: camera::Camera,
+    resume: Option<&PathBuf>,
+    quiet: bool,
+    world: &World,
+) -> Framebuffer {
     let mut tracing = render::Render::new(render_settings, camera);
-    tracing.render(&world);
+    if let Some(path) = resume {
+        if let Err(err) = tracing.resume(path, world) {
+            eprintln!("Can't resume from {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    }
+    if !quiet {
+        // Redraw a few times per second at most, the callback fires for every tile.
+        let last_draw = Mutex::new(Instant::now());
+        tracing.set_progress_callback(move |progress| {
+            let mut last_draw = last_draw.lock().unwrap();
+            let pass_done = progress.tiles_done == progress.tiles_total;
+            if pass_done || last_draw.elapsed() >= Duration::from_millis(100) {
+                draw_progress_bar(progress);
+                *last_draw = Instant::now();
+            }
+        });
+    }
+    let framebuffer = tracing.render(world);
+    if !quiet {
+        eprintln!();
+    }
+    framebuffer
+}
+
+// Writes to the file, or as PPM to stdout without one.
+fn write_image(path: Option<&Path>, framebuffer: &Framebuffer) -> io::Result<()> {
+    match path {
+        Some(path) => writer::save(path, framebuffer),
+        None => {
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            writer::write_ppm(&mut out, framebuffer).and_then(|_| out.flush())
+        }
+    }
+}
+
+// `image.ppm` becomes `image.left.ppm` for the left eye.
+fn eye_path(path: &Path, eye: Eye) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(eye.name());
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    path.with_file_name(name)
+}
+
+// Either a single frame or an inclusive range like `1-120`.
+fn parse_frames(range: &str) -> Option<(i32, i32)> {
+    let (first, last) = match range.split_once('-') {
+        Some((first, last)) => (first.trim().parse().ok()?, last.trim().parse().ok()?),
+        None => {
+            let frame = range.trim().parse().ok()?;
+            (frame, frame)
+        }
+    };
+    (first <= last).then_some((first, last))
+}
+
+// Numbers the file of a frame, a run of `#` in the name is replaced by the zero padded frame
+// number, otherwise `image.ppm` becomes `image.0001.ppm`.
+fn frame_path(path: &Path, frame: i32) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if let Some(start) = name.find('#') {
+        let width = name[start..].chars().take_while(|c| *c == '#').count();
+        let number = format!("{frame:0width$}");
+        return path.with_file_name(format!(
+            "{}{}{}",
+            &name[..start],
+            number,
+            &name[start + width..]
+        ));
+    }
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(format!(".{frame:04}"));
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    path.with_file_name(name)
 }
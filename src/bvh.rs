@@ -0,0 +1,183 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+use std::hash::Hasher;
+use std::sync::Arc;
+
+// Leaves with at most this many objects aren't split any further.
+const LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    bbox: Aabb,
+    // Leaves cover `count` objects of `order` starting at `offset`. Interior nodes have no objects,
+    // their first child follows right after them and `offset` is the index of the second one.
+    offset: u32,
+    count: u32,
+    axis: u8,
+}
+
+// Bounding volume hierarchy, a flattened binary tree of bounding boxes around the objects that
+// lets a ray skip most of them.
+#[derive(Default)]
+pub struct Bvh {
+    objects: Vec<Arc<dyn Hittable>>,
+    // Object indices, ordered so that every leaf covers a contiguous range.
+    order: Vec<usize>,
+    nodes: Vec<Node>,
+}
+
+impl Bvh {
+    pub fn new(objects: Vec<Arc<dyn Hittable>>) -> Self {
+        let boxes: Vec<_> = objects.iter().map(|object| object.bounding_box()).collect();
+        let mut bvh = Self {
+            order: (0..objects.len()).collect(),
+            objects,
+            nodes: vec![],
+        };
+        if !bvh.objects.is_empty() {
+            bvh.build(&boxes, 0, boxes.len());
+        }
+        bvh
+    }
+
+    #[inline]
+    pub fn objects(&self) -> &[Arc<dyn Hittable>] {
+        &self.objects
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    // Splits the objects in the middle along the longest axis of their centroids.
+    fn build(&mut self, boxes: &[Aabb], start: usize, end: usize) -> usize {
+        let index = self.nodes.len();
+        let range = &mut self.order[start..end];
+        let bbox = range.iter().fold(Aabb::empty(), |bbox, &i| {
+            Aabb::surrounding(&bbox, &boxes[i])
+        });
+        let centroids = range
+            .iter()
+            .fold(Aabb::empty(), |bbox, &i| bbox.include(boxes[i].centroid()));
+        let axis = centroids.longest_axis();
+
+        let leaf = Node {
+            bbox,
+            offset: start as u32,
+            count: (end - start) as u32,
+            axis: axis as u8,
+        };
+        if end - start <= LEAF_SIZE || centroids.max[axis] <= centroids.min[axis] {
+            self.nodes.push(leaf);
+            return index;
+        }
+
+        let mid = (end - start) / 2;
+        range.select_nth_unstable_by(mid, |&a, &b| {
+            boxes[a].centroid()[axis].total_cmp(&boxes[b].centroid()[axis])
+        });
+        self.nodes.push(Node { count: 0, ..leaf });
+        self.build(boxes, start, start + mid);
+        let second = self.build(boxes, start + mid, end);
+        self.nodes[index].offset = second as u32;
+        index
+    }
+}
+
+impl Hittable for Bvh {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let mut hit_anything = false;
+        let mut closest_so_far = ray_t.max;
+
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.bbox.hit(r, Interval::new(ray_t.min, closest_so_far)) {
+                continue;
+            }
+            if node.count > 0 {
+                let start = node.offset as usize;
+                for &i in &self.order[start..start + node.count as usize] {
+                    if self.objects[i].hit(r, Interval::new(ray_t.min, closest_so_far), rec) {
+                        hit_anything = true;
+                        closest_so_far = rec.t;
+                    }
+                }
+            } else if r.direction()[node.axis as usize] < 0.0 {
+                // Visit the nearer child first, so that the farther one can be culled.
+                stack.push(index + 1);
+                stack.push(node.offset as usize);
+            } else {
+                stack.push(node.offset as usize);
+                stack.push(index + 1);
+            }
+        }
+        hit_anything
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.nodes
+            .first()
+            .map_or_else(Aabb::empty, |node| node.bbox)
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        state.write_usize(self.objects.len());
+        for object in &self.objects {
+            object.fingerprint(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::Sphere;
+    use crate::hittable_list::HittableList;
+    use crate::material::{Lambertian, MaterialType};
+    use crate::rtweekend;
+    use crate::vec3::{Point3, Vec3};
+
+    #[test]
+    fn test_same_hits_as_list() {
+        rtweekend::seed(7);
+        let mut list = HittableList::new();
+        let mut objects: Vec<Arc<dyn Hittable>> = vec![];
+        for _ in 0..200 {
+            let center = Vec3::random_vector_range(-10.0, 10.0);
+            let radius = rtweekend::random_double_range(0.1, 1.0);
+            let material = || MaterialType::Lambertian(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+            list.add(Arc::new(Sphere::new(center, radius, material())));
+            objects.push(Arc::new(Sphere::new(center, radius, material())));
+        }
+        let bvh = Bvh::new(objects);
+
+        for _ in 0..500 {
+            let ray = Ray::new(
+                Point3::new(0.0, 0.0, -20.0),
+                Vec3::random_vector_range(-1.0, 1.0) + Vec3::new(0.0, 0.0, 1.0),
+            );
+            let (mut expected, mut actual) = (HitRecord::new(), HitRecord::new());
+            let interval = Interval::new(0.001, f32::INFINITY);
+            assert_eq!(
+                list.hit(&ray, interval, &mut expected),
+                bvh.hit(&ray, interval, &mut actual)
+            );
+            assert_eq!(expected.t, actual.t);
+        }
+        assert_eq!(bvh.bounding_box(), list.bounding_box());
+    }
+}
@@ -1,5 +1,19 @@
 #![allow(clippy::too_many_arguments)]
-use crate::vec3::{Point3, Vec3};
+use crate::animation::{Interpolation, Track};
+use crate::color::luminance;
+use crate::distribution::Distribution2D;
+use crate::rtweekend::{degrees_to_radians, invalid_data, json_vec3};
+use crate::texture::ImageTexture;
+use crate::vec3::{self, Point3, Vec3};
+
+use std::f32::consts::PI;
+use std::hash::Hasher;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use serde_json::Value;
 
 #[derive(Clone, Copy)]
 pub struct Image {
@@ -25,7 +39,7 @@ impl Image {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Copy)]
 pub struct Xform {
     pub center: Point3,
     pub pixel00_loc: Point3,
@@ -36,6 +50,10 @@ pub struct Xform {
     pub vup: Vec3,
     pub defocus_disk_u: Vec3,
     pub defocus_disk_v: Vec3,
+    // Orthonormal basis of the camera, `w` points opposite the view direction.
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
 }
 
 impl Xform {
@@ -49,12 +67,177 @@ impl Xform {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Projection {
+    // Pinhole or thin lens, with the field of view given by `CameraSettings::vfov`.
+    #[default]
+    Perspective,
+    // Parallel rays along the view direction, `height` world units fit the image vertically.
+    Orthographic {
+        height: f32,
+    },
+    // Full sphere around the camera, longitude across the width and latitude down the height,
+    // with the poles along the up vector.
+    Equirectangular,
+    // Circular image fitting the image height, `fov` degrees across its diameter.
+    Fisheye {
+        mapping: FisheyeMapping,
+        fov: f32,
+    },
+}
+
+// How the angle from the lens axis maps to the distance from the image center.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FisheyeMapping {
+    // The distance grows linearly with the angle.
+    Equidistant,
+    // Equal solid angles cover equal image areas.
+    Equisolid,
+}
+
+impl Projection {
+    pub const NAMES: [&'static str; 5] = [
+        "perspective",
+        "orthographic",
+        "equirectangular",
+        "fisheye-equidistant",
+        "fisheye-equisolid",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Projection::Perspective => "perspective",
+            Projection::Orthographic { .. } => "orthographic",
+            Projection::Equirectangular => "equirectangular",
+            Projection::Fisheye {
+                mapping: FisheyeMapping::Equidistant,
+                ..
+            } => "fisheye-equidistant",
+            Projection::Fisheye {
+                mapping: FisheyeMapping::Equisolid,
+                ..
+            } => "fisheye-equisolid",
+        }
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        state.write(self.name().as_bytes());
+        match self {
+            Projection::Perspective | Projection::Equirectangular => {}
+            Projection::Orthographic { height } => state.write_u32(height.to_bits()),
+            Projection::Fisheye { fov, .. } => state.write_u32(fov.to_bits()),
+        }
+    }
+}
+
+// Shape of the lens opening, which out-of-focus highlights take on. Every shape fits the unit
+// disk scaled by the defocus angle.
+#[derive(Clone, Default)]
+pub enum Aperture {
+    #[default]
+    Circle,
+    // Regular polygon of straight blades, `rotation` in degrees turns it around the lens axis.
+    Polygon {
+        blades: u32,
+        rotation: f32,
+    },
+    // Grayscale mask, brighter pixels let through more light.
+    Image(Arc<ApertureImage>),
+}
+
+impl Aperture {
+    // Maps a uniform sample to a point of the aperture in [-1, 1]².
+    pub fn sample(&self, u: (f32, f32)) -> (f32, f32) {
+        match self {
+            Aperture::Circle => {
+                let p = vec3::sample_unit_disk(u);
+                (p[0], p[1])
+            }
+            Aperture::Polygon { blades, rotation } => {
+                // Pick one of the equal triangles around the center, then a point inside it.
+                let n = (*blades).max(3);
+                let x = u.0 * n as f32;
+                let k = (x as u32).min(n - 1);
+                let s = (x - k as f32).sqrt();
+                let angle = |k: u32| degrees_to_radians(*rotation) + 2.0 * PI * k as f32 / n as f32;
+                let (a, b) = (angle(k), angle(k + 1));
+                (
+                    s * ((1.0 - u.1) * a.cos() + u.1 * b.cos()),
+                    s * ((1.0 - u.1) * a.sin() + u.1 * b.sin()),
+                )
+            }
+            Aperture::Image(image) => image.sample(u),
+        }
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        match self {
+            Aperture::Circle => state.write_u8(0),
+            Aperture::Polygon { blades, rotation } => {
+                state.write_u8(1);
+                state.write_u32(*blades);
+                state.write_u32(rotation.to_bits());
+            }
+            Aperture::Image(image) => {
+                state.write_u8(2);
+                state.write_usize(image.width);
+                state.write_usize(image.height);
+                for value in &image.values {
+                    state.write_u32(value.to_bits());
+                }
+            }
+        }
+    }
+}
+
+pub struct ApertureImage {
+    width: usize,
+    height: usize,
+    values: Vec<f32>,
+    distribution: Distribution2D,
+}
+
+impl ApertureImage {
+    pub fn new(image: &ImageTexture) -> Self {
+        let values: Vec<f32> = image.pixels().iter().map(|c| luminance(*c)).collect();
+        let distribution = Distribution2D::new(&values, image.width(), image.height());
+        Self {
+            width: image.width(),
+            height: image.height(),
+            values,
+            distribution,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(&ImageTexture::load(path, true)?))
+    }
+
+    // The longer side of the image spans the diameter of the lens.
+    fn sample(&self, u: (f32, f32)) -> (f32, f32) {
+        let ((x, y), _) = self.distribution.sample_continuous(u);
+        let size = self.width.max(self.height) as f32;
+        (
+            (2.0 * x - 1.0) * self.width as f32 / size,
+            (1.0 - 2.0 * y) * self.height as f32 / size,
+        )
+    }
+}
+
+#[derive(Clone)]
 pub struct CameraSettings {
     pub aspect_ratio: f32,
     pub vfov: f32,
     pub defocus_angle: f32,
     pub focus_distance: f32,
+    pub projection: Projection,
+    pub stereo: Option<Stereo>,
+    pub aperture: Aperture,
+    // Scale from scene radiance to the image values.
+    pub exposure: f32,
+    // Animation time interval the rays are spread over, for motion blur.
+    pub shutter_open: f32,
+    pub shutter_close: f32,
 }
 
 impl CameraSettings {
@@ -64,14 +247,206 @@ impl CameraSettings {
             vfov,
             defocus_angle,
             focus_distance,
+            projection: Projection::Perspective,
+            stereo: None,
+            aperture: Aperture::Circle,
+            exposure: 1.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        }
+    }
+
+    pub fn with_shutter(mut self, open: f32, close: f32) -> Self {
+        self.shutter_open = open;
+        self.shutter_close = close;
+        self
+    }
+
+    pub fn with_exposure(mut self, exposure: f32) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub fn with_aperture(mut self, aperture: Aperture) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    pub fn with_stereo(mut self, stereo: Stereo) -> Self {
+        self.stereo = Some(stereo);
+        self
+    }
+}
+
+// A camera described like a real one, with the sensor and lens in millimeters and the scene in
+// meters. The field of view, the aperture and the exposure are derived from it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalCamera {
+    pub sensor_width: f32,
+    pub sensor_height: f32,
+    pub focal_length: f32,
+    pub f_number: f32,
+    // In seconds.
+    pub shutter: f32,
+    pub iso: f32,
+}
+
+impl Default for PhysicalCamera {
+    // A full frame sensor with a normal lens.
+    fn default() -> Self {
+        Self::new(50.0, 2.8)
+    }
+}
+
+impl PhysicalCamera {
+    pub fn new(focal_length: f32, f_number: f32) -> Self {
+        Self {
+            sensor_width: 36.0,
+            sensor_height: 24.0,
+            focal_length,
+            f_number,
+            shutter: 1.0 / 125.0,
+            iso: 100.0,
         }
     }
+
+    pub fn with_sensor(mut self, width: f32, height: f32) -> Self {
+        self.sensor_width = width;
+        self.sensor_height = height;
+        self
+    }
+
+    pub fn with_shutter(mut self, shutter: f32) -> Self {
+        self.shutter = shutter;
+        self
+    }
+
+    pub fn with_iso(mut self, iso: f32) -> Self {
+        self.iso = iso;
+        self
+    }
+
+    // Vertical field of view in degrees, for a lens focused at infinity.
+    pub fn vfov(&self) -> f32 {
+        2.0 * (self.sensor_height / (2.0 * self.focal_length))
+            .atan()
+            .to_degrees()
+    }
+
+    // Radius of the entrance pupil in meters.
+    pub fn aperture_radius(&self) -> f32 {
+        self.focal_length / (2.0 * self.f_number) / 1000.0
+    }
+
+    // Exposure value at ISO 100, 0 for one second at f/1.
+    pub fn ev100(&self) -> f32 {
+        (self.f_number * self.f_number / self.shutter * 100.0 / self.iso).log2()
+    }
+
+    // Luminance in cd/m² mapped to 1, by the saturation based sensitivity of the sensor.
+    pub fn exposure(&self) -> f32 {
+        1.0 / (1.2 * self.ev100().exp2())
+    }
+
+    pub fn settings(&self, focus_distance: f32) -> CameraSettings {
+        // `CameraSettings` measures the lens by the angle it spans from the plane of focus.
+        let defocus_angle = 2.0
+            * (self.aperture_radius() / focus_distance)
+                .atan()
+                .to_degrees();
+        CameraSettings::new(
+            self.sensor_width / self.sensor_height,
+            self.vfov(),
+            defocus_angle,
+            focus_distance,
+        )
+        .with_exposure(self.exposure())
+    }
+}
+
+// Eye separation and the distance where both eyes see the same point, that is at the screen
+// depth. The images are shifted rather than rotated (off-axis, not toe-in), so that there is no
+// vertical parallax.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stereo {
+    pub interocular: f32,
+    pub convergence: f32,
 }
 
+impl Stereo {
+    pub fn new(interocular: f32, convergence: f32) -> Self {
+        Self {
+            interocular,
+            convergence,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Eye {
+    // The camera itself, also used without stereo settings.
+    #[default]
+    Center,
+    Left,
+    Right,
+}
+
+impl Eye {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Eye::Center => "center",
+            Eye::Left => "left",
+            Eye::Right => "right",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoLayout {
+    SideBySide,
+    TopBottom,
+    // Each eye goes into its own file.
+    Separate,
+}
+
+impl StereoLayout {
+    pub const ALL: [StereoLayout; 3] = [
+        StereoLayout::SideBySide,
+        StereoLayout::TopBottom,
+        StereoLayout::Separate,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StereoLayout::SideBySide => "side-by-side",
+            StereoLayout::TopBottom => "top-bottom",
+            StereoLayout::Separate => "separate",
+        }
+    }
+}
+
+impl FromStr for StereoLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StereoLayout::ALL
+            .into_iter()
+            .find(|layout| layout.name() == s)
+            .ok_or_else(|| format!("unknown stereo layout `{}`", s))
+    }
+}
+
+#[derive(Clone)]
 pub struct Camera {
     pub image: Image,
     pub transform: Xform,
     pub settings: CameraSettings,
+    pub eye: Eye,
 }
 
 impl Camera {
@@ -90,6 +465,295 @@ impl Camera {
             image,
             transform,
             settings,
+            eye: Eye::Center,
+        }
+    }
+
+    // The same camera seen from one eye of its stereo settings.
+    pub fn with_eye(mut self, eye: Eye) -> Self {
+        self.eye = eye;
+        self
+    }
+
+    // Signed distance of the eye from the center of the camera along its horizontal axis.
+    pub fn eye_offset(&self) -> f32 {
+        let half = self
+            .settings
+            .stereo
+            .map_or(0.0, |stereo| stereo.interocular / 2.0);
+        match self.eye {
+            Eye::Center => 0.0,
+            Eye::Left => -half,
+            Eye::Right => half,
+        }
+    }
+
+    pub fn fingerprint(&self, state: &mut dyn Hasher) {
+        state.write_i32(self.image.width);
+        state.write_i32(self.image.height);
+        vec3::fingerprint(&self.transform.look_from, state);
+        vec3::fingerprint(&self.transform.look_at, state);
+        vec3::fingerprint(&self.transform.vup, state);
+        state.write_u32(self.settings.aspect_ratio.to_bits());
+        state.write_u32(self.settings.vfov.to_bits());
+        state.write_u32(self.settings.defocus_angle.to_bits());
+        state.write_u32(self.settings.focus_distance.to_bits());
+        state.write_u32(self.settings.exposure.to_bits());
+        state.write_u32(self.settings.shutter_open.to_bits());
+        state.write_u32(self.settings.shutter_close.to_bits());
+        self.settings.projection.fingerprint(state);
+        if let Some(stereo) = self.settings.stereo {
+            state.write_u32(stereo.interocular.to_bits());
+            state.write_u32(stereo.convergence.to_bits());
+        }
+        state.write(self.eye.name().as_bytes());
+        self.settings.aperture.fingerprint(state);
+    }
+}
+
+// Key of a camera path, the values the camera passes through at `time`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraKeyframe {
+    pub time: f32,
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub vfov: f32,
+    pub focus_distance: f32,
+}
+
+impl CameraKeyframe {
+    pub fn new(
+        time: f32,
+        look_from: Point3,
+        look_at: Point3,
+        vfov: f32,
+        focus_distance: f32,
+    ) -> Self {
+        Self {
+            time,
+            look_from,
+            look_at,
+            vfov,
+            focus_distance,
+        }
+    }
+}
+
+// Animated camera position, target, field of view and focus, for turntables and fly-throughs.
+#[derive(Debug, Clone)]
+pub struct CameraPath {
+    look_from: Track<Point3>,
+    look_at: Track<Point3>,
+    vfov: Track<f32>,
+    focus_distance: Track<f32>,
+}
+
+impl CameraPath {
+    pub fn new(interpolation: Interpolation) -> Self {
+        Self {
+            look_from: Track::new(interpolation),
+            look_at: Track::new(interpolation),
+            vfov: Track::new(interpolation),
+            focus_distance: Track::new(interpolation),
+        }
+    }
+
+    pub fn with_keyframe(mut self, key: CameraKeyframe) -> Self {
+        self.look_from = self.look_from.with_key(key.time, key.look_from);
+        self.look_at = self.look_at.with_key(key.time, key.look_at);
+        self.vfov = self.vfov.with_key(key.time, key.vfov);
+        self.focus_distance = self.focus_distance.with_key(key.time, key.focus_distance);
+        self
+    }
+
+    // One turn around the vertical axis through the target of `camera` in `duration` seconds.
+    pub fn orbit(camera: &Camera, duration: f32) -> Self {
+        // Enough keys for the spline to stay close to the circle.
+        const KEYS: u32 = 24;
+        let transform = &camera.transform;
+        let axis = vec3::unit_vector(transform.vup);
+        let offset = transform.look_from - transform.look_at;
+        let mut path = Self::new(Interpolation::CatmullRom);
+        for k in 0..=KEYS {
+            // Rodrigues' rotation of the offset around the axis.
+            let angle = 2.0 * PI * k as f32 / KEYS as f32;
+            let rotated = angle.cos() * offset
+                + angle.sin() * vec3::cross(axis, offset)
+                + (1.0 - angle.cos()) * vec3::dot(axis, offset) * axis;
+            path = path.with_keyframe(CameraKeyframe::new(
+                duration * k as f32 / KEYS as f32,
+                transform.look_at + rotated,
+                transform.look_at,
+                camera.settings.vfov,
+                camera.settings.focus_distance,
+            ));
+        }
+        path
+    }
+
+    // Reads a JSON path like `{"interpolation": "linear", "keyframes": [{"time": 0,
+    // "look_from": [13, 2, 3], "look_at": [0, 0, 0], "vfov": 20, "focus_distance": 10}]}`. The
+    // field of view and the focus distance of `camera` are used where a key has none.
+    pub fn load(path: &Path, camera: &Camera) -> io::Result<Self> {
+        let document: Value = serde_json::from_slice(&std::fs::read(path)?)
+            .map_err(|err| invalid_data(&format!("invalid camera path JSON: {err}")))?;
+        Self::from_json(&document, &camera.settings)
+    }
+
+    // The same as `load`, from an already parsed document.
+    pub fn from_json(document: &Value, settings: &CameraSettings) -> io::Result<Self> {
+        let interpolation = match document["interpolation"].as_str() {
+            Some(name) => name.parse().map_err(|err: String| invalid_data(&err))?,
+            None => Interpolation::default(),
+        };
+        let point = |value: &Value| {
+            json_vec3(value).ok_or_else(|| invalid_data("camera path points need three numbers"))
+        };
+        let keyframes = document["keyframes"]
+            .as_array()
+            .filter(|keyframes| !keyframes.is_empty())
+            .ok_or_else(|| invalid_data("camera path without keyframes"))?;
+        let mut path = Self::new(interpolation);
+        for key in keyframes {
+            let number =
+                |name: &str, default: f32| key[name].as_f64().map_or(default, |x| x as f32);
+            path = path.with_keyframe(CameraKeyframe::new(
+                key["time"]
+                    .as_f64()
+                    .ok_or_else(|| invalid_data("camera keyframe without time"))?
+                    as f32,
+                point(&key["look_from"])?,
+                point(&key["look_at"])?,
+                number("vfov", settings.vfov),
+                number("focus_distance", settings.focus_distance),
+            ));
+        }
+        Ok(path)
+    }
+
+    // Time of the first and the last key.
+    pub fn duration(&self) -> (f32, f32) {
+        let keys = self.look_from.keys();
+        match (keys.first(), keys.last()) {
+            (Some(first), Some(last)) => (first.0, last.0),
+            _ => (0.0, 0.0),
+        }
+    }
+
+    // `camera` moved along the path to `time`.
+    pub fn camera(&self, camera: &Camera, time: f32) -> Camera {
+        let mut camera = camera.clone();
+        let (Some(look_from), Some(look_at)) =
+            (self.look_from.sample(time), self.look_at.sample(time))
+        else {
+            return camera;
+        };
+        camera.transform = Xform::new(look_from, look_at, camera.transform.vup);
+        let settings = &mut camera.settings;
+        settings.vfov = self.vfov.sample(time).unwrap_or(settings.vfov);
+        // The lens keeps its size while the focus changes.
+        let lens_radius =
+            settings.focus_distance * (settings.defocus_angle.to_radians() / 2.0).tan();
+        settings.focus_distance = self
+            .focus_distance
+            .sample(time)
+            .unwrap_or(settings.focus_distance);
+        settings.defocus_angle = 2.0 * (lens_radius / settings.focus_distance).atan().to_degrees();
+        camera
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn test_physical_camera() {
+        let camera = PhysicalCamera::new(50.0, 16.0).with_shutter(1.0 / 100.0);
+        assert!((camera.vfov() - 26.99).abs() < 0.01);
+        assert!((camera.aperture_radius() - 0.0015625).abs() < 1e-7);
+        // Sunny 16: f/16 at 1/ISO seconds, close to the EV 15 of bright sun.
+        assert!((camera.ev100() - 14.64).abs() < 0.01);
+
+        let settings = camera.settings(10.0);
+        assert_eq!(settings.aspect_ratio, 1.5);
+        let radius = 10.0 * (settings.defocus_angle.to_radians() / 2.0).tan();
+        assert!((radius - camera.aperture_radius()).abs() < 1e-7);
+        // Doubling the shutter time doubles the exposure.
+        let longer = camera.with_shutter(1.0 / 50.0);
+        assert!((longer.exposure() / camera.exposure() - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_camera_path() {
+        let settings = CameraSettings::new(1.0, 20.0, 2.0, 10.0);
+        let transform = Xform::new(
+            Point3::new(10.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        let camera = Camera::new(Image::with_width(16), transform, settings);
+
+        // A quarter turn keeps the distance to the target.
+        let orbit = CameraPath::orbit(&camera, 4.0);
+        assert_eq!(orbit.duration(), (0.0, 4.0));
+        let moved = orbit.camera(&camera, 1.0);
+        assert!((moved.transform.look_from - Point3::new(0.0, 0.0, -10.0)).length() < 1e-4);
+        assert!((moved.transform.look_from.length() - 10.0).abs() < 0.02);
+
+        let zoom = CameraPath::new(Interpolation::Linear)
+            .with_keyframe(CameraKeyframe::new(
+                0.0,
+                Point3::new(0.0, 0.0, 5.0),
+                Point3::new(0.0, 0.0, 0.0),
+                40.0,
+                5.0,
+            ))
+            .with_keyframe(CameraKeyframe::new(
+                1.0,
+                Point3::new(0.0, 0.0, 10.0),
+                Point3::new(0.0, 0.0, 0.0),
+                20.0,
+                10.0,
+            ));
+        let moved = zoom.camera(&camera, 0.5);
+        assert_eq!(moved.transform.look_from, Point3::new(0.0, 0.0, 7.5));
+        assert_eq!(moved.settings.vfov, 30.0);
+        assert_eq!(moved.settings.focus_distance, 7.5);
+        let radius = |settings: &CameraSettings| {
+            settings.focus_distance * (settings.defocus_angle.to_radians() / 2.0).tan()
+        };
+        assert!((radius(&moved.settings) - radius(&camera.settings)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_aperture_shapes() {
+        // Every point of a hexagon is within its apothem along the normal of the nearest edge.
+        let hexagon = Aperture::Polygon {
+            blades: 6,
+            rotation: 0.0,
+        };
+        let apothem = (PI / 6.0).cos();
+        for i in 0..16 {
+            for j in 0..16 {
+                let (x, y) = hexagon.sample((i as f32 / 16.0, j as f32 / 16.0));
+                for k in 0..6 {
+                    let normal = (2.0 * k as f32 + 1.0) * PI / 6.0;
+                    assert!(x * normal.cos() + y * normal.sin() <= apothem + 1e-5);
+                }
+            }
+        }
+
+        // A mask lit only in its top right quarter.
+        let mut pixels = vec![Color::default(); 4];
+        pixels[1] = Color::new(1.0, 1.0, 1.0);
+        let mask = Aperture::Image(Arc::new(ApertureImage::new(&ImageTexture::new(
+            2, 2, pixels,
+        ))));
+        for u in [(0.0, 0.0), (0.3, 0.7), (0.99, 0.99)] {
+            let (x, y) = mask.sample(u);
+            assert!((0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y));
         }
     }
 }
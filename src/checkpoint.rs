@@ -0,0 +1,217 @@
+use crate::color::Color;
+use crate::framebuffer::{Framebuffer, Pixel};
+use crate::rtweekend::invalid_data;
+
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"SANSARA\0";
+const VERSION: u32 = 4;
+// Bytes of the header and of each pixel.
+const HEADER_SIZE: u64 = 40;
+const PIXEL_SIZE: u64 = 28;
+
+// Snapshot of an unfinished render. The random stream is fully determined by the seed, which
+// the settings hash covers, and the number of finished passes, so they stand in for the RNG
+// state.
+pub struct Checkpoint {
+    pub scene_hash: u64,
+    pub settings_hash: u64,
+    pub passes: u32,
+    pub framebuffer: Framebuffer,
+}
+
+impl Checkpoint {
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        // Write next to the target and rename, so that a kill during saving never leaves us
+        // with a truncated checkpoint.
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut f = BufWriter::new(File::create(&tmp_path)?);
+            self.write(&mut f)?;
+            f.flush()?;
+        }
+        fs::rename(tmp_path, path)
+    }
+
+    pub fn write(&self, f: &mut impl Write) -> io::Result<()> {
+        f.write_all(MAGIC)?;
+        f.write_all(&VERSION.to_le_bytes())?;
+        f.write_all(&self.scene_hash.to_le_bytes())?;
+        f.write_all(&self.settings_hash.to_le_bytes())?;
+        f.write_all(&self.passes.to_le_bytes())?;
+        f.write_all(&self.framebuffer.width().to_le_bytes())?;
+        f.write_all(&self.framebuffer.height().to_le_bytes())?;
+        for pixel in self.framebuffer.pixels() {
+            f.write_all(&pixel.sum[0].to_le_bytes())?;
+            f.write_all(&pixel.sum[1].to_le_bytes())?;
+            f.write_all(&pixel.sum[2].to_le_bytes())?;
+            f.write_all(&pixel.weight.to_le_bytes())?;
+            f.write_all(&pixel.luminance.to_le_bytes())?;
+            f.write_all(&pixel.square.to_le_bytes())?;
+            f.write_all(&pixel.samples.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len();
+        Self::read(&mut BufReader::new(file), size)
+    }
+
+    // The same as `load`, from `f` holding `size` bytes.
+    pub fn read(f: &mut impl Read, size: u64) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        f.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a sansara checkpoint"));
+        }
+        let version = read_u32(f)?;
+        if version != VERSION {
+            return Err(invalid_data(&format!(
+                "unsupported checkpoint version {version}"
+            )));
+        }
+
+        let scene_hash = read_u64(f)?;
+        let settings_hash = read_u64(f)?;
+        let passes = read_u32(f)?;
+        let width = read_u32(f)?;
+        let height = read_u32(f)?;
+        if width < 1 || height < 1 {
+            return Err(invalid_data("checkpoint has an empty image"));
+        }
+        // The pixels have to be in the file, a broken header mustn't make us allocate for more.
+        let remaining = size.saturating_sub(HEADER_SIZE);
+        let len = (width as u64) * (height as u64);
+        if width > i32::MAX as u32
+            || height > i32::MAX as u32
+            || len
+                .checked_mul(PIXEL_SIZE)
+                .is_none_or(|size| size > remaining)
+        {
+            return Err(invalid_data("checkpoint is truncated or too large"));
+        }
+        let len = len as usize;
+        let (width, height) = (width as i32, height as i32);
+
+        let mut pixels = Vec::with_capacity(len);
+        for _ in 0..len {
+            let r = read_f32(f)?;
+            let g = read_f32(f)?;
+            let b = read_f32(f)?;
+            pixels.push(Pixel {
+                sum: Color::new(r, g, b),
+                weight: read_f32(f)?,
+                luminance: read_f32(f)?,
+                square: read_f32(f)?,
+                samples: read_u32(f)?,
+            });
+        }
+
+        Ok(Self {
+            scene_hash,
+            settings_hash,
+            passes,
+            framebuffer: Framebuffer::with_data(width, height, pixels),
+        })
+    }
+}
+
+// FNV-1a, used instead of `DefaultHasher` because its output must stay stable between builds
+// for checkpoints to be resumable.
+pub struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+fn read_u32(f: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    f.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(f: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    f.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_f32(f: &mut impl Read) -> io::Result<f32> {
+    read_u32(f).map(f32::from_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    #[test]
+    fn test_save_and_load() {
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.add_samples(0, 0, Color::new(0.1, 0.2, 0.3), 0.5, 16);
+        framebuffer.add_samples(1, 0, Color::new(1.0, 2.0, 3.0), 4.0, 32);
+        let checkpoint = Checkpoint {
+            scene_hash: 1,
+            settings_hash: 2,
+            passes: 4,
+            framebuffer,
+        };
+        let mut bytes = vec![];
+        checkpoint.write(&mut bytes).unwrap();
+        let loaded = Checkpoint::read(&mut Cursor::new(&bytes), bytes.len() as u64).unwrap();
+
+        assert_eq!(loaded.scene_hash, 1);
+        assert_eq!(loaded.settings_hash, 2);
+        assert_eq!(loaded.passes, 4);
+        let (expected, framebuffer) = (&checkpoint.framebuffer, &loaded.framebuffer);
+        assert_eq!((framebuffer.width(), framebuffer.height()), (2, 1));
+        assert_eq!(framebuffer.pixels(), expected.pixels());
+    }
+
+    #[test]
+    fn test_reject_foreign_file() {
+        let bytes = b"P3\n1 1\n255\n0 0 0\n";
+        let error = Checkpoint::read(&mut Cursor::new(bytes), bytes.len() as u64)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_reject_oversized_header() {
+        for (width, height) in [(u32::MAX, u32::MAX), (65536, 65536), (2, 1)] {
+            let mut header = MAGIC.to_vec();
+            header.extend(VERSION.to_le_bytes());
+            header.extend([0u8; 20]);
+            header.extend(width.to_le_bytes());
+            header.extend(height.to_le_bytes());
+            // One pixel short even for the smallest.
+            header.extend([0u8; PIXEL_SIZE as usize]);
+            let error = Checkpoint::read(&mut Cursor::new(&header), header.len() as u64)
+                .err()
+                .unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+// Piecewise constant distributions to importance sample tabulated functions, like an aperture
+// image or an environment map.
+
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    func: Vec<f32>,
+    cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl Distribution1D {
+    // `func` holds the function values over equal steps of [0, 1), negative values count as 0.
+    pub fn new(func: &[f32]) -> Self {
+        let n = func.len().max(1);
+        let func: Vec<f32> = if func.is_empty() {
+            vec![0.0]
+        } else {
+            func.iter().map(|f| f.max(0.0)).collect()
+        };
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i] / n as f32;
+        }
+        let integral = cdf[n];
+        if integral == 0.0 {
+            // Nothing to importance sample, fall back to a uniform distribution.
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = i as f32 / n as f32;
+            }
+        } else {
+            for c in cdf.iter_mut() {
+                *c /= integral;
+            }
+        }
+        Self {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    #[inline]
+    pub fn integral(&self) -> f32 {
+        self.integral
+    }
+
+    // Returns the sampled position in [0, 1), its density and the index of its segment.
+    pub fn sample_continuous(&self, u: f32) -> (f32, f32, usize) {
+        // The last entry of the cdf not above `u`.
+        let offset = self
+            .cdf
+            .partition_point(|c| *c <= u)
+            .saturating_sub(1)
+            .min(self.func.len() - 1);
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+        let x = ((offset as f32 + du) / self.func.len() as f32).min(1.0 - f32::EPSILON);
+        (x, self.pdf(x), offset)
+    }
+
+    // Density of the position `x` in [0, 1).
+    pub fn pdf(&self, x: f32) -> f32 {
+        if self.integral == 0.0 {
+            return 1.0;
+        }
+        let offset = ((x * self.func.len() as f32) as usize).min(self.func.len() - 1);
+        self.func[offset] / self.integral
+    }
+}
+
+// Function over [0, 1)², sampled by the marginal density of the rows and then the conditional
+// density within the chosen row.
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    // `func` is row major with `width` values per row, the first row at y = 0.
+    pub fn new(func: &[f32], width: usize, height: usize) -> Self {
+        assert_eq!(func.len(), width * height);
+        let conditional: Vec<Distribution1D> =
+            func.chunks(width.max(1)).map(Distribution1D::new).collect();
+        let marginal: Vec<f32> = conditional.iter().map(|row| row.integral()).collect();
+        Self {
+            conditional,
+            marginal: Distribution1D::new(&marginal),
+        }
+    }
+
+    #[inline]
+    pub fn integral(&self) -> f32 {
+        self.marginal.integral()
+    }
+
+    // Returns the sampled point in [0, 1)² and its density.
+    pub fn sample_continuous(&self, u: (f32, f32)) -> ((f32, f32), f32) {
+        let (y, pdf_y, row) = self.marginal.sample_continuous(u.1);
+        let (x, pdf_x, _) = self.conditional[row].sample_continuous(u.0);
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    pub fn pdf(&self, p: (f32, f32)) -> f32 {
+        let rows = self.conditional.len();
+        let row = ((p.1 * rows as f32) as usize).min(rows - 1);
+        self.conditional[row].pdf(p.0) * self.marginal.pdf(p.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distribution_1d() {
+        let distribution = Distribution1D::new(&[0.0, 1.0, 3.0, 0.0]);
+        assert!((distribution.integral() - 1.0).abs() < 1e-6);
+
+        // A quarter of the mass is in the second segment, the rest in the third.
+        let (x, pdf, offset) = distribution.sample_continuous(0.125);
+        assert_eq!(offset, 1);
+        assert!((x - 0.375).abs() < 1e-6);
+        assert!((pdf - 1.0).abs() < 1e-6);
+        let (x, pdf, offset) = distribution.sample_continuous(0.625);
+        assert_eq!(offset, 2);
+        assert!((x - 0.625).abs() < 1e-6);
+        assert!((pdf - 3.0).abs() < 1e-6);
+
+        let uniform = Distribution1D::new(&[0.0, 0.0]);
+        assert_eq!(uniform.sample_continuous(0.75).0, 0.75);
+        assert_eq!(uniform.pdf(0.75), 1.0);
+    }
+
+    #[test]
+    fn test_distribution_2d() {
+        // Only the lower right cell has any weight.
+        let distribution = Distribution2D::new(&[0.0, 0.0, 0.0, 2.0], 2, 2);
+        for u in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.99)] {
+            let ((x, y), pdf) = distribution.sample_continuous(u);
+            assert!((0.5..1.0).contains(&x) && (0.5..1.0).contains(&y));
+            assert!((pdf - 4.0).abs() < 1e-5);
+            assert_eq!(distribution.pdf((x, y)), pdf);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+// What rays see when they leave the scene: the gradient of the book, a physical sky or an image
+// of the surroundings that lights the scene.
+
+use crate::color::{luminance, Color};
+use crate::distribution::Distribution2D;
+use crate::sky::Sky;
+use crate::texture::ImageTexture;
+use crate::vec3::{self, Vec3};
+
+use std::f32::consts::PI;
+use std::hash::Hasher;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Default, Clone)]
+pub enum Background {
+    // The white to blue gradient of the book.
+    #[default]
+    Gradient,
+    Sky(Sky),
+    Environment(Arc<Environment>),
+}
+
+impl Background {
+    pub fn radiance(&self, direction: Vec3) -> Color {
+        match self {
+            Background::Gradient => {
+                let unit_direction = vec3::unit_vector(direction);
+                let a = 0.5 * (unit_direction[1] + 1.0);
+                (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.5, 0.7, 1.0)
+            }
+            Background::Sky(sky) => sky.radiance(direction),
+            Background::Environment(environment) => environment.radiance(direction),
+        }
+    }
+
+    pub fn fingerprint(&self, state: &mut dyn Hasher) {
+        match self {
+            Background::Gradient => state.write(b"gradient"),
+            Background::Sky(sky) => {
+                state.write(b"sky");
+                sky.fingerprint(state);
+            }
+            Background::Environment(environment) => {
+                state.write(b"environment");
+                environment.fingerprint(state);
+            }
+        }
+    }
+}
+
+// Equirectangular image of the light arriving from every direction, +y up and the center of the
+// image towards -z. Directions are importance sampled by the luminance of the pixels.
+pub struct Environment {
+    image: ImageTexture,
+    // Turn around the up axis, in degrees.
+    rotation: f32,
+    intensity: f32,
+    distribution: Distribution2D,
+}
+
+impl Environment {
+    pub fn new(image: ImageTexture) -> Self {
+        let (width, height) = (image.width(), image.height());
+        // Rows near the poles cover a smaller solid angle.
+        let func: Vec<f32> = image
+            .pixels()
+            .iter()
+            .enumerate()
+            .map(|(index, color)| {
+                let theta = ((index / width) as f32 + 0.5) / height as f32 * PI;
+                luminance(*color).max(0.0) * theta.sin()
+            })
+            .collect();
+        Self {
+            distribution: Distribution2D::new(&func, width, height),
+            image,
+            rotation: 0.0,
+            intensity: 1.0,
+        }
+    }
+
+    // Reads a high dynamic range image, Radiance `.hdr` or any other format of the `image` crate.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let image = ImageTexture::load(path, false)?;
+        if image.width() == 0 || image.height() == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "empty environment image",
+            ));
+        }
+        Ok(Self::new(image))
+    }
+
+    pub fn with_rotation(mut self, degrees: f32) -> Self {
+        self.rotation = degrees;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    // From a world direction to the position on the image, both in [0, 1) from the top left.
+    fn position_of(&self, direction: Vec3) -> (f32, f32) {
+        let direction = vec3::unit_vector(direction);
+        let phi = direction[0].atan2(-direction[2]) - self.rotation.to_radians();
+        let theta = direction[1].clamp(-1.0, 1.0).acos();
+        ((phi / (2.0 * PI) + 0.5).rem_euclid(1.0), theta / PI)
+    }
+
+    fn direction_at(&self, (x, y): (f32, f32)) -> Vec3 {
+        let phi = (x - 0.5) * 2.0 * PI + self.rotation.to_radians();
+        let theta = y * PI;
+        Vec3::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        )
+    }
+
+    // The pixel under the position, the same piecewise constant function that is sampled.
+    fn lookup(&self, (x, y): (f32, f32)) -> Color {
+        let (width, height) = (self.image.width(), self.image.height());
+        let i = ((x * width as f32) as usize).min(width - 1);
+        let j = ((y * height as f32) as usize).min(height - 1);
+        self.image.pixels()[j * width + i] * self.intensity
+    }
+
+    pub fn radiance(&self, direction: Vec3) -> Color {
+        self.lookup(self.position_of(direction))
+    }
+
+    // Picks a direction by the brightness of the image. Returns the direction, the radiance from
+    // it and the density over solid angle, `None` for the poles.
+    pub fn sample(&self, u: (f32, f32)) -> Option<(Vec3, Color, f32)> {
+        let (position, pdf) = self.distribution.sample_continuous(u);
+        let sin_theta = (position.1 * PI).sin();
+        if pdf == 0.0 || sin_theta <= 0.0 {
+            return None;
+        }
+        Some((
+            self.direction_at(position),
+            self.lookup(position),
+            pdf / (2.0 * PI * PI * sin_theta),
+        ))
+    }
+
+    // Density of `sample` picking the direction, over solid angle.
+    pub fn pdf(&self, direction: Vec3) -> f32 {
+        let position = self.position_of(direction);
+        let sin_theta = (position.1 * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(position) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        state.write_usize(self.image.width());
+        state.write_usize(self.image.height());
+        for color in self.image.pixels() {
+            vec3::fingerprint(color, state);
+        }
+        state.write_u32(self.rotation.to_bits());
+        state.write_u32(self.intensity.to_bits());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_environment_sampling() {
+        // Dark, with a single bright pixel.
+        let (width, height) = (16, 8);
+        let mut pixels = vec![Color::new(0.01, 0.01, 0.01); width * height];
+        pixels[3 * width + 12] = Color::new(100.0, 100.0, 100.0);
+        let environment =
+            Environment::new(ImageTexture::new(width, height, pixels)).with_rotation(30.0);
+
+        // Directions map to the image and back.
+        let direction = vec3::unit_vector(Vec3::new(0.3, 0.4, -0.8));
+        let back = environment.direction_at(environment.position_of(direction));
+        assert!((back - direction).length() < 1e-5);
+
+        // The samples land on the bright pixel, and the density integrates to one.
+        let mut bright = 0;
+        for k in 0..64 {
+            let u = ((k as f32 + 0.5) / 64.0, ((k as f32 + 0.5) * 0.618).fract());
+            let (wi, radiance, pdf) = environment.sample(u).unwrap();
+            assert!((environment.pdf(wi) - pdf).abs() < 1e-3 * pdf);
+            if radiance[0] == 100.0 {
+                bright += 1;
+            }
+        }
+        assert!(bright > 60);
+
+        let steps = 256;
+        let mut integral = 0.0;
+        for i in 0..steps {
+            for j in 0..steps {
+                let position = (
+                    (i as f32 + 0.5) / steps as f32,
+                    (j as f32 + 0.5) / steps as f32,
+                );
+                let sin_theta = (position.1 * PI).sin();
+                // The image covers 2π by π, with sin θ from the area of the sphere.
+                integral +=
+                    environment.pdf(environment.direction_at(position)) * sin_theta * 2.0 * PI * PI
+                        / (steps * steps) as f32;
+            }
+        }
+        assert!((integral - 1.0).abs() < 0.01);
+    }
+}
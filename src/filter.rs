@@ -0,0 +1,189 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterKind {
+    #[default]
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+    Lanczos,
+}
+
+impl FilterKind {
+    pub const ALL: [FilterKind; 5] = [
+        FilterKind::Box,
+        FilterKind::Tent,
+        FilterKind::Gaussian,
+        FilterKind::Mitchell,
+        FilterKind::Lanczos,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterKind::Box => "box",
+            FilterKind::Tent => "tent",
+            FilterKind::Gaussian => "gaussian",
+            FilterKind::Mitchell => "mitchell",
+            FilterKind::Lanczos => "lanczos",
+        }
+    }
+
+    // Radius in pixels the filters are usually used with.
+    pub fn default_radius(&self) -> f32 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.0,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell => 2.0,
+            FilterKind::Lanczos => 3.0,
+        }
+    }
+}
+
+impl Display for FilterKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for FilterKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FilterKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| format!("unknown filter `{}`", s))
+    }
+}
+
+// Pixel reconstruction filter. Every sample is splatted into all pixels whose centers lie within
+// `radius` of it, weighted by the filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Filter {
+    pub kind: FilterKind,
+    pub radius: f32,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::new(FilterKind::Box)
+    }
+}
+
+impl Filter {
+    pub fn new(kind: FilterKind) -> Self {
+        Self::with_radius(kind, kind.default_radius())
+    }
+
+    pub fn with_radius(kind: FilterKind, radius: f32) -> Self {
+        Self {
+            kind,
+            radius: radius.max(0.5),
+        }
+    }
+
+    // Number of neighbouring pixels on each side a sample can reach.
+    #[inline]
+    pub fn margin(&self) -> i32 {
+        (self.radius - 0.5).ceil() as i32
+    }
+
+    pub fn evaluate(&self, x: f32, y: f32) -> f32 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    fn evaluate_1d(&self, x: f32) -> f32 {
+        let radius = self.radius;
+        let x = x.abs();
+        if x > radius {
+            return 0.0;
+        }
+        match self.kind {
+            FilterKind::Box => 1.0,
+            FilterKind::Tent => radius - x,
+            FilterKind::Gaussian => {
+                // Shifted down so that the filter goes smoothly to zero at its radius.
+                let sigma = radius / 3.0;
+                let gaussian = |d: f32| (-d * d / (2.0 * sigma * sigma)).exp();
+                gaussian(x) - gaussian(radius)
+            }
+            FilterKind::Mitchell => mitchell_1d(2.0 * x / radius, 1.0 / 3.0, 1.0 / 3.0),
+            FilterKind::Lanczos => sinc(x) * sinc(x / radius),
+        }
+    }
+}
+
+// Mitchell-Netravali cubic over [0, 2].
+fn mitchell_1d(x: f32, b: f32, c: f32) -> f32 {
+    let x = x.abs();
+    let value = if x <= 1.0 {
+        (12.0 - 9.0 * b - 6.0 * c) * x * x * x
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b)
+    } else if x <= 2.0 {
+        (-b - 6.0 * c) * x * x * x
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c)
+    } else {
+        0.0
+    };
+    value / 6.0
+}
+
+#[inline]
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+    let x = std::f32::consts::PI * x;
+    x.sin() / x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_vanish_outside_radius() {
+        for kind in FilterKind::ALL {
+            let filter = Filter::new(kind);
+            assert!(filter.evaluate(0.0, 0.0) > 0.0, "{}", kind);
+            assert_eq!(filter.evaluate(filter.radius + 0.01, 0.0), 0.0, "{}", kind);
+            assert_eq!(filter.evaluate(0.0, -filter.radius - 0.01), 0.0, "{}", kind);
+            assert_eq!(
+                filter.evaluate(0.3, 0.2),
+                filter.evaluate(-0.3, -0.2),
+                "{}",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_filter_shapes() {
+        let tent = Filter::with_radius(FilterKind::Tent, 1.0);
+        assert_eq!(tent.evaluate(0.5, 0.0), 0.5);
+
+        let gaussian = Filter::new(FilterKind::Gaussian);
+        assert!(gaussian.evaluate(gaussian.radius, 0.0).abs() < 1e-6);
+
+        // Mitchell and Lanczos have negative lobes, which is what keeps edges sharp.
+        let mitchell = Filter::new(FilterKind::Mitchell);
+        assert!(mitchell.evaluate(1.5, 0.0) < 0.0);
+        let lanczos = Filter::new(FilterKind::Lanczos);
+        assert!(lanczos.evaluate(1.5, 0.0) < 0.0);
+        assert!(lanczos.evaluate(1.0, 0.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_margin() {
+        assert_eq!(Filter::new(FilterKind::Box).margin(), 0);
+        assert_eq!(Filter::new(FilterKind::Tent).margin(), 1);
+        assert_eq!(Filter::new(FilterKind::Lanczos).margin(), 3);
+        assert_eq!(Filter::with_radius(FilterKind::Box, 0.1).radius, 0.5);
+    }
+}
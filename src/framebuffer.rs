@@ -0,0 +1,254 @@
+use crate::color::{luminance, Color};
+
+// Everything accumulated for one pixel. Colors are splatted with reconstruction filter weights,
+// while the luminance statistics only count the samples taken inside the pixel itself.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Pixel {
+    pub sum: Color,
+    pub weight: f32,
+    pub luminance: f32,
+    // Sum of the squared sample luminance, to estimate the noise.
+    pub square: f32,
+    pub samples: u32,
+}
+
+impl Pixel {
+    #[inline]
+    fn add(&mut self, other: &Pixel) {
+        self.sum += other.sum;
+        self.weight += other.weight;
+        self.luminance += other.luminance;
+        self.square += other.square;
+        self.samples += other.samples;
+    }
+}
+
+// Rendered image in linear RGB. Every pixel keeps the sums of its samples rather than the
+// average, so that more samples can be added later on.
+#[derive(Clone, Default)]
+pub struct Framebuffer {
+    width: i32,
+    height: i32,
+    pixels: Vec<Pixel>,
+}
+
+impl Framebuffer {
+    pub fn new(width: i32, height: i32) -> Self {
+        let len = (width.max(0) * height.max(0)) as usize;
+        Self {
+            width,
+            height,
+            pixels: vec![Pixel::default(); len],
+        }
+    }
+
+    pub fn with_data(width: i32, height: i32, pixels: Vec<Pixel>) -> Self {
+        assert_eq!(pixels.len(), (width * height) as usize);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    #[inline]
+    fn contains(&self, i: i32, j: i32) -> bool {
+        0 <= i && i < self.width && 0 <= j && j < self.height
+    }
+
+    #[inline]
+    fn index(&self, i: i32, j: i32) -> usize {
+        debug_assert!(self.contains(i, j));
+        (j * self.width + i) as usize
+    }
+
+    // Filtered color of the pixel, black if nothing was splatted into it yet. The negative lobes
+    // of filters like Mitchell's can leave a few samples with a total weight of about zero or
+    // less, those pixels stay black too instead of blowing up or turning their colors around.
+    #[inline]
+    pub fn pixel(&self, i: i32, j: i32) -> Color {
+        let pixel = &self.pixels[self.index(i, j)];
+        if pixel.weight <= f32::EPSILON {
+            return Color::default();
+        }
+        pixel.sum / pixel.weight
+    }
+
+    #[inline]
+    pub fn samples(&self, i: i32, j: i32) -> u32 {
+        self.pixels[self.index(i, j)].samples
+    }
+
+    // Adds a filter weighted sample color, pixels outside of the image are ignored.
+    #[inline]
+    pub fn splat(&mut self, i: i32, j: i32, color: Color, weight: f32) {
+        if self.contains(i, j) {
+            let index = self.index(i, j);
+            self.pixels[index].sum += weight * color;
+            self.pixels[index].weight += weight;
+        }
+    }
+
+    // Records a sample taken inside the pixel for the noise estimate.
+    #[inline]
+    pub fn add_statistics(&mut self, i: i32, j: i32, sample_luminance: f32) {
+        let index = self.index(i, j);
+        self.pixels[index].luminance += sample_luminance;
+        self.pixels[index].square += sample_luminance * sample_luminance;
+        self.pixels[index].samples += 1;
+    }
+
+    // Adds `samples` box filtered samples with the given color sum at once.
+    pub fn add_samples(&mut self, i: i32, j: i32, sum: Color, square: f32, samples: u32) {
+        let index = self.index(i, j);
+        self.pixels[index].add(&Pixel {
+            sum,
+            weight: samples as f32,
+            luminance: luminance(sum),
+            square,
+            samples,
+        });
+    }
+
+    // Accumulates `other` with its upper left corner at (`x`, `y`), clipped to this image.
+    pub fn merge(&mut self, other: &Framebuffer, x: i32, y: i32) {
+        for j in 0..other.height {
+            for i in 0..other.width {
+                if self.contains(x + i, y + j) {
+                    let index = self.index(x + i, y + j);
+                    self.pixels[index].add(&other.pixels[other.index(i, j)]);
+                }
+            }
+        }
+    }
+
+    // Both images next to each other, `left` first. Used for stereo pairs.
+    pub fn side_by_side(left: &Framebuffer, right: &Framebuffer) -> Self {
+        let mut framebuffer =
+            Framebuffer::new(left.width + right.width, left.height.max(right.height));
+        framebuffer.merge(left, 0, 0);
+        framebuffer.merge(right, left.width, 0);
+        framebuffer
+    }
+
+    // Both images above each other, `top` first.
+    pub fn top_bottom(top: &Framebuffer, bottom: &Framebuffer) -> Self {
+        let mut framebuffer =
+            Framebuffer::new(top.width.max(bottom.width), top.height + bottom.height);
+        framebuffer.merge(top, 0, 0);
+        framebuffer.merge(bottom, 0, top.height);
+        framebuffer
+    }
+
+    pub fn pixels(&self) -> &[Pixel] {
+        &self.pixels
+    }
+
+    pub fn total_samples(&self) -> u64 {
+        self.pixels.iter().map(|pixel| pixel.samples as u64).sum()
+    }
+
+    // Average relative standard error of the pixel luminance estimates.
+    pub fn noise_level(&self) -> f32 {
+        let mut total = 0.0;
+        for pixel in &self.pixels {
+            if pixel.samples < 2 {
+                return f32::INFINITY;
+            }
+            let n = pixel.samples as f32;
+            let mean = pixel.luminance / n;
+            let variance = ((pixel.square / n - mean * mean) * n / (n - 1.0)).max(0.0);
+            // Offset the mean so that black pixels don't dominate the metric.
+            total += (variance / n).sqrt() / (mean + 1e-2);
+        }
+        total / self.pixels.len().max(1) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_average_pixel() {
+        let mut framebuffer = Framebuffer::new(2, 2);
+        assert_eq!(framebuffer.pixel(1, 1), Color::default());
+
+        framebuffer.add_samples(1, 1, Color::new(2.0, 4.0, 6.0), 1.0, 2);
+        framebuffer.add_samples(1, 1, Color::new(2.0, 2.0, 2.0), 1.0, 2);
+        assert_eq!(framebuffer.pixel(1, 1), Color::new(1.0, 1.5, 2.0));
+        assert_eq!(framebuffer.samples(1, 1), 4);
+        assert_eq!(framebuffer.samples(0, 1), 0);
+        assert_eq!(framebuffer.total_samples(), 4);
+    }
+
+    #[test]
+    fn test_weighted_splats() {
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.splat(0, 0, Color::new(1.0, 1.0, 1.0), 3.0);
+        framebuffer.splat(0, 0, Color::new(5.0, 5.0, 5.0), 1.0);
+        framebuffer.splat(2, 0, Color::new(5.0, 5.0, 5.0), 1.0);
+        assert_eq!(framebuffer.pixel(0, 0), Color::new(2.0, 2.0, 2.0));
+        assert_eq!(framebuffer.pixel(1, 0), Color::default());
+        // Splats don't count as samples of the pixel.
+        assert_eq!(framebuffer.samples(0, 0), 0);
+
+        // Negative lobes summing to nothing, or less.
+        framebuffer.splat(1, 0, Color::new(1.0, 1.0, 1.0), 0.5);
+        framebuffer.splat(1, 0, Color::new(2.0, 2.0, 2.0), -0.5);
+        assert_eq!(framebuffer.pixel(1, 0), Color::default());
+        framebuffer.splat(1, 0, Color::new(1.0, 1.0, 1.0), -0.1);
+        assert_eq!(framebuffer.pixel(1, 0), Color::default());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut tile = Framebuffer::new(2, 2);
+        tile.add_samples(0, 0, Color::new(1.0, 1.0, 1.0), 1.0, 1);
+        tile.add_samples(1, 1, Color::new(2.0, 2.0, 2.0), 4.0, 1);
+
+        let mut framebuffer = Framebuffer::new(2, 2);
+        framebuffer.merge(&tile, -1, -1);
+        assert_eq!(framebuffer.pixel(0, 0), Color::new(2.0, 2.0, 2.0));
+        assert_eq!(framebuffer.total_samples(), 1);
+    }
+
+    #[test]
+    fn test_stereo_layouts() {
+        let mut left = Framebuffer::new(2, 1);
+        left.add_samples(0, 0, Color::new(1.0, 1.0, 1.0), 1.0, 1);
+        let mut right = Framebuffer::new(2, 1);
+        right.add_samples(1, 0, Color::new(2.0, 2.0, 2.0), 4.0, 1);
+
+        let framebuffer = Framebuffer::side_by_side(&left, &right);
+        assert_eq!((framebuffer.width(), framebuffer.height()), (4, 1));
+        assert_eq!(framebuffer.pixel(0, 0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(framebuffer.pixel(3, 0), Color::new(2.0, 2.0, 2.0));
+
+        let framebuffer = Framebuffer::top_bottom(&left, &right);
+        assert_eq!((framebuffer.width(), framebuffer.height()), (2, 2));
+        assert_eq!(framebuffer.pixel(1, 1), Color::new(2.0, 2.0, 2.0));
+        assert_eq!(framebuffer.total_samples(), 2);
+    }
+
+    #[test]
+    fn test_noise_level() {
+        let mut framebuffer = Framebuffer::new(1, 1);
+        assert_eq!(framebuffer.noise_level(), f32::INFINITY);
+
+        // Two identical samples, no variance at all.
+        framebuffer.add_statistics(0, 0, 1.0);
+        framebuffer.add_statistics(0, 0, 1.0);
+        assert!(framebuffer.noise_level().abs() < 1e-6);
+    }
+}
@@ -2,8 +2,9 @@ use std::sync::Arc;
 
 use crate::color;
 use crate::hittable;
-use crate::material::{Dielectric, Metal};
+use crate::material::{Conductor, Dielectric, Metal, Principled, RoughDielectric};
 use crate::rtweekend;
+use crate::texture::{Checker, TextureType};
 use crate::vec3::{Point3, Vec3};
 use crate::{
     hittable_list::HittableList,
@@ -86,3 +87,78 @@ pub fn random_sphere() -> HittableList {
 
     world
 }
+
+pub fn microfacet_spheres() -> HittableList {
+    let mut world = HittableList::new();
+
+    let ground_material =
+        MaterialType::Lambertian(Lambertian::new(color::Color::new(0.5, 0.5, 0.5)));
+    world.add(Arc::new(hittable::Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground_material,
+    )));
+
+    // A row across the view of the default camera, from rough glass to polished aluminum.
+    let materials = [
+        MaterialType::RoughDielectric(RoughDielectric::new(1.5, 0.2)),
+        MaterialType::Conductor(Conductor::gold(0.3)),
+        MaterialType::Conductor(Conductor::copper(0.5)),
+        MaterialType::Conductor(Conductor::aluminum(0.1)),
+    ];
+    let across = Vec3::new(0.225, 0.0, -0.974);
+    for (material, t) in materials.into_iter().zip([-3.0, -1.0, 1.0, 3.0]) {
+        world.add(Arc::new(hittable::Sphere::new(
+            Point3::new(0.0, 0.9, 0.0) + t * across,
+            0.9,
+            material,
+        )));
+    }
+
+    world
+}
+
+pub fn principled_spheres() -> HittableList {
+    let mut world = HittableList::new();
+
+    let checker = Checker::new(
+        1.0,
+        color::Color::new(0.2, 0.3, 0.1),
+        color::Color::new(0.9, 0.9, 0.9),
+    );
+    let ground_material = MaterialType::Principled(Box::new(
+        Principled::new(TextureType::Checker(checker)).with_roughness(0.8),
+    ));
+    world.add(Arc::new(hittable::Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground_material,
+    )));
+
+    // Tinted glass, car paint, velvet and brushed gold.
+    let materials = [
+        Principled::new(color::Color::new(0.7, 0.9, 0.8))
+            .with_roughness(0.05)
+            .with_transmission(1.0, 1.5),
+        Principled::new(color::Color::new(0.6, 0.05, 0.05))
+            .with_roughness(0.4)
+            .with_clearcoat(1.0, 0.03),
+        Principled::new(color::Color::new(0.1, 0.1, 0.4))
+            .with_roughness(1.0)
+            .with_specular(0.0)
+            .with_sheen(1.0, 0.5),
+        Principled::new(color::Color::new(1.0, 0.78, 0.34))
+            .with_metallic(1.0)
+            .with_roughness(0.35),
+    ];
+    let across = Vec3::new(0.225, 0.0, -0.974);
+    for (material, t) in materials.into_iter().zip([-3.0, -1.0, 1.0, 3.0]) {
+        world.add(Arc::new(hittable::Sphere::new(
+            Point3::new(0.0, 0.9, 0.0) + t * across,
+            0.9,
+            MaterialType::Principled(Box::new(material)),
+        )));
+    }
+
+    world
+}
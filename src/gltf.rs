@@ -0,0 +1,713 @@
+// Importer for glTF 2.0 scenes, both `.gltf` with external or embedded buffers and binary `.glb`.
+// Meshes, the node hierarchy, metallic-roughness materials with their textures and the first
+// camera are read; animations, skins and morph targets are ignored.
+
+use crate::camera::{CameraSettings, Projection, Xform};
+use crate::color::Color;
+use crate::hittable_list::HittableList;
+use crate::material::{MaterialType, Principled};
+use crate::mesh::{Mesh, TriangleMesh};
+use crate::rtweekend::invalid_data;
+use crate::texture::{ImageTexture, TextureType};
+use crate::transform::Transform;
+use crate::vec3::{Point3, Vec3};
+
+use log::warn;
+use serde_json::Value;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+// Used when a camera doesn't specify its aspect ratio, the same as the default camera.
+const DEFAULT_ASPECT_RATIO: f32 = 16.0 / 9.0;
+
+pub struct GltfScene {
+    pub world: HittableList,
+    pub camera: Option<GltfCamera>,
+}
+
+pub struct GltfCamera {
+    pub transform: Xform,
+    pub settings: CameraSettings,
+}
+
+pub fn load(path: &Path) -> io::Result<GltfScene> {
+    let bytes = fs::read(path)?;
+    let (json, bin) = if bytes.starts_with(GLB_MAGIC) {
+        parse_glb(&bytes)?
+    } else {
+        (bytes.as_slice(), None)
+    };
+    let document: Value = serde_json::from_slice(json)
+        .map_err(|err| invalid_data(&format!("invalid glTF JSON: {err}")))?;
+    let base = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    Importer::new(document, base, bin)?.import()
+}
+
+// Splits a binary glTF into its JSON and the optional binary buffer chunk.
+fn parse_glb(bytes: &[u8]) -> io::Result<(&[u8], Option<&[u8]>)> {
+    let read_u32 = |offset: usize| -> io::Result<u32> {
+        bytes
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| invalid_data("truncated glb file"))
+    };
+    let version = read_u32(4)?;
+    if version != 2 {
+        return Err(invalid_data(&format!("unsupported glb version {version}")));
+    }
+    let length = (read_u32(8)? as usize).min(bytes.len());
+
+    let (mut json, mut bin) = (None, None);
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let chunk_length = read_u32(offset)? as usize;
+        let chunk_type = read_u32(offset + 4)?;
+        let chunk = bytes
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or_else(|| invalid_data("truncated glb chunk"))?;
+        match chunk_type {
+            CHUNK_JSON if json.is_none() => json = Some(chunk),
+            CHUNK_BIN if bin.is_none() => bin = Some(chunk),
+            // Unknown chunks have to be skipped.
+            _ => {}
+        }
+        // Chunks are padded to 4 bytes.
+        offset += 8 + chunk_length.next_multiple_of(4);
+    }
+    let json = json.ok_or_else(|| invalid_data("glb file without JSON chunk"))?;
+    Ok((json, bin))
+}
+
+struct Importer {
+    document: Value,
+    base: PathBuf,
+    buffers: Vec<Vec<u8>>,
+    // Decoded images by index and whether they hold sRGB colors.
+    images: RefCell<HashMap<(usize, bool), ImageTexture>>,
+    materials: RefCell<HashMap<Option<usize>, Arc<MaterialType>>>,
+}
+
+impl Importer {
+    fn new(document: Value, base: PathBuf, bin: Option<&[u8]>) -> io::Result<Self> {
+        let version = document["asset"]["version"].as_str().unwrap_or_default();
+        if !version.starts_with("2.") {
+            return Err(invalid_data(&format!(
+                "unsupported glTF version `{version}`"
+            )));
+        }
+        let mut buffers = vec![];
+        for buffer in array(&document["buffers"]) {
+            let data = match buffer["uri"].as_str() {
+                Some(uri) => read_uri(&base, uri)?,
+                None => bin
+                    .ok_or_else(|| invalid_data("buffer without data"))?
+                    .to_vec(),
+            };
+            buffers.push(data);
+        }
+        Ok(Self {
+            document,
+            base,
+            buffers,
+            images: RefCell::new(HashMap::new()),
+            materials: RefCell::new(HashMap::new()),
+        })
+    }
+
+    fn import(&self) -> io::Result<GltfScene> {
+        let document = &self.document;
+        let nodes = array(&document["nodes"]);
+        let scene = document["scene"].as_u64().unwrap_or(0) as usize;
+        let roots: Vec<usize> = match document["scenes"][scene]["nodes"].as_array() {
+            Some(roots) => roots.iter().filter_map(index).collect(),
+            // Without scenes, every node that isn't a child of another one is a root.
+            None => {
+                let children: HashSet<usize> = nodes
+                    .iter()
+                    .flat_map(|node| array(&node["children"]).iter().filter_map(index))
+                    .collect();
+                (0..nodes.len())
+                    .filter(|node| !children.contains(node))
+                    .collect()
+            }
+        };
+
+        let mut world = HittableList::new();
+        let mut camera = None;
+        let mut visited = HashSet::new();
+        let mut stack: Vec<_> = roots
+            .into_iter()
+            .rev()
+            .map(|node| (node, Transform::identity()))
+            .collect();
+        while let Some((node_index, parent)) = stack.pop() {
+            if !visited.insert(node_index) {
+                return Err(invalid_data("node hierarchy isn't a tree"));
+            }
+            let node = nodes
+                .get(node_index)
+                .ok_or_else(|| invalid_data(&format!("missing node {node_index}")))?;
+            let transform = parent * local_transform(node);
+
+            if let Some(mesh) = index(&node["mesh"]) {
+                self.add_mesh(&mut world, mesh, &transform)?;
+            }
+            if camera.is_none() {
+                if let Some(index) = index(&node["camera"]) {
+                    camera = self.camera(index, &transform);
+                }
+            }
+            for child in array(&node["children"]).iter().rev().filter_map(index) {
+                stack.push((child, transform));
+            }
+        }
+
+        Ok(GltfScene { world, camera })
+    }
+
+    fn add_mesh(
+        &self,
+        world: &mut HittableList,
+        mesh: usize,
+        transform: &Transform,
+    ) -> io::Result<()> {
+        for primitive in array(&self.document["meshes"][mesh]["primitives"]) {
+            let attributes = &primitive["attributes"];
+            let Some(position) = index(&attributes["POSITION"]) else {
+                warn!("skipping a primitive of mesh {mesh} without positions");
+                continue;
+            };
+            let positions: Vec<Point3> = self
+                .read_vectors::<3>(position)?
+                .into_iter()
+                .map(|[x, y, z]| Point3::new(x, y, z))
+                .collect();
+
+            let vertices: Vec<u32> = match index(&primitive["indices"]) {
+                Some(indices) => {
+                    let (values, _) = self.read_accessor(indices)?;
+                    values.into_iter().map(|i| i as u32).collect()
+                }
+                None => (0..positions.len() as u32).collect(),
+            };
+            if vertices.iter().any(|&i| i as usize >= positions.len()) {
+                return Err(invalid_data(&format!(
+                    "mesh {mesh} has an index out of range"
+                )));
+            }
+            let indices = match primitive["mode"].as_u64().unwrap_or(4) {
+                // Triangle list, strip and fan.
+                4 => vertices
+                    .chunks_exact(3)
+                    .map(|t| [t[0], t[1], t[2]])
+                    .collect(),
+                5 => (0..vertices.len().saturating_sub(2))
+                    .map(|i| {
+                        let t = &vertices[i..i + 3];
+                        // Every other triangle of a strip has to be flipped to keep the winding.
+                        if i % 2 == 0 {
+                            [t[0], t[1], t[2]]
+                        } else {
+                            [t[1], t[0], t[2]]
+                        }
+                    })
+                    .collect(),
+                6 => (1..vertices.len().saturating_sub(1))
+                    .map(|i| [vertices[0], vertices[i], vertices[i + 1]])
+                    .collect(),
+                mode => {
+                    warn!("skipping a primitive of mesh {mesh} with mode {mode}, only triangles are supported");
+                    continue;
+                }
+            };
+
+            let mut geometry = Mesh::new(positions, indices);
+            if let Some(normals) = index(&attributes["NORMAL"]) {
+                let normals = self.read_vectors::<3>(normals)?;
+                geometry = geometry.with_normals(
+                    normals
+                        .into_iter()
+                        .map(|[x, y, z]| Vec3::new(x, y, z))
+                        .collect(),
+                );
+            }
+            if let Some(uvs) = index(&attributes["TEXCOORD_0"]) {
+                // glTF has v going down from the top of the image.
+                let uvs = self.read_vectors::<2>(uvs)?;
+                geometry = geometry.with_uvs(uvs.into_iter().map(|[u, v]| (u, 1.0 - v)).collect());
+            }
+
+            let material = self.material(index(&primitive["material"]))?;
+            world.add(Arc::new(TriangleMesh::new(
+                geometry.transform(transform),
+                material,
+            )));
+        }
+        Ok(())
+    }
+
+    fn camera(&self, index: usize, transform: &Transform) -> Option<GltfCamera> {
+        let camera = &self.document["cameras"][index];
+        // glTF cameras look down their -z axis with +y up.
+        let look_from = transform.point(Point3::new(0.0, 0.0, 0.0));
+        let look_at = transform.point(Point3::new(0.0, 0.0, -1.0));
+        let vup = transform.vector(Vec3::new(0.0, 1.0, 0.0));
+        let focus_distance = (look_at - look_from).length();
+
+        let settings = match camera["type"].as_str() {
+            Some("perspective") => {
+                let perspective = &camera["perspective"];
+                let yfov = perspective["yfov"].as_f64()? as f32;
+                let aspect_ratio = perspective["aspectRatio"]
+                    .as_f64()
+                    .map_or(DEFAULT_ASPECT_RATIO, |aspect| aspect as f32);
+                CameraSettings::new(aspect_ratio, yfov.to_degrees(), 0.0, focus_distance)
+            }
+            Some("orthographic") => {
+                // The magnifications are half the view size, in the units of the camera node.
+                let orthographic = &camera["orthographic"];
+                let xmag = orthographic["xmag"].as_f64()? as f32;
+                let ymag = orthographic["ymag"].as_f64()? as f32;
+                let height = 2.0 * ymag * vup.length();
+                // The field of view is meaningless for a parallel projection.
+                CameraSettings::new(xmag / ymag, 90.0, 0.0, focus_distance)
+                    .with_projection(Projection::Orthographic { height })
+            }
+            _ => {
+                warn!("skipping camera {index}, unknown camera type");
+                return None;
+            }
+        };
+        Some(GltfCamera {
+            transform: Xform::new(look_from, look_at, vup),
+            settings,
+        })
+    }
+
+    fn material(&self, index: Option<usize>) -> io::Result<Arc<MaterialType>> {
+        if let Some(material) = self.materials.borrow().get(&index) {
+            return Ok(material.clone());
+        }
+        // The default material of the specification when a primitive has none.
+        let material = match index {
+            Some(index) => self.principled(&self.document["materials"][index])?,
+            None => Principled::new(Color::new(1.0, 1.0, 1.0))
+                .with_metallic(1.0)
+                .with_roughness(1.0),
+        };
+        let material = Arc::new(MaterialType::Principled(Box::new(material)));
+        self.materials.borrow_mut().insert(index, material.clone());
+        Ok(material)
+    }
+
+    fn principled(&self, material: &Value) -> io::Result<Principled> {
+        let pbr = &material["pbrMetallicRoughness"];
+        let base_color_factor = floats::<4>(&pbr["baseColorFactor"]).unwrap_or([1.0; 4]);
+        let base_color_factor = Color::new(
+            base_color_factor[0],
+            base_color_factor[1],
+            base_color_factor[2],
+        );
+        let base_color = match self.texture(&pbr["baseColorTexture"], true)? {
+            Some(image) => TextureType::Image(image.scale(base_color_factor)),
+            None => base_color_factor.into(),
+        };
+
+        // Roughness is stored in the green channel and metalness in the blue one.
+        let metallic = pbr["metallicFactor"].as_f64().unwrap_or(1.0) as f32;
+        let roughness = pbr["roughnessFactor"].as_f64().unwrap_or(1.0) as f32;
+        let (metallic, roughness) = match self.texture(&pbr["metallicRoughnessTexture"], false)? {
+            Some(image) => (
+                TextureType::Image(image.channel(2).scale(gray(metallic))),
+                TextureType::Image(image.channel(1).scale(gray(roughness))),
+            ),
+            None => (metallic.into(), roughness.into()),
+        };
+
+        let mut principled = Principled::new(base_color)
+            .with_metallic(metallic)
+            .with_roughness(roughness);
+
+        let extensions = &material["extensions"];
+        let transmission = &extensions["KHR_materials_transmission"]["transmissionFactor"];
+        if let Some(transmission) = transmission.as_f64() {
+            let ior = extensions["KHR_materials_ior"]["ior"]
+                .as_f64()
+                .unwrap_or(1.5);
+            principled = principled.with_transmission(transmission as f32, ior as f32);
+        }
+        let clearcoat = &extensions["KHR_materials_clearcoat"];
+        if let Some(factor) = clearcoat["clearcoatFactor"].as_f64() {
+            let roughness = clearcoat["clearcoatRoughnessFactor"]
+                .as_f64()
+                .unwrap_or(0.0);
+            principled = principled.with_clearcoat(factor as f32, roughness as f32);
+        }
+        Ok(principled)
+    }
+
+    // Image of a texture info object, `None` if there is no texture.
+    fn texture(&self, info: &Value, srgb: bool) -> io::Result<Option<ImageTexture>> {
+        let Some(texture) = index(&info["index"]) else {
+            return Ok(None);
+        };
+        if info["texCoord"].as_u64().unwrap_or(0) != 0 {
+            warn!("texture {texture} uses a second set of texture coordinates, using the first");
+        }
+        let Some(source) = index(&self.document["textures"][texture]["source"]) else {
+            warn!("skipping texture {texture} without a supported image");
+            return Ok(None);
+        };
+
+        if let Some(image) = self.images.borrow().get(&(source, srgb)) {
+            return Ok(Some(image.clone()));
+        }
+        let image = &self.document["images"][source];
+        let bytes = match (image["uri"].as_str(), index(&image["bufferView"])) {
+            (Some(uri), _) => read_uri(&self.base, uri)?,
+            (None, Some(view)) => self.buffer_view(view)?.to_vec(),
+            (None, None) => return Err(invalid_data(&format!("image {source} has no data"))),
+        };
+        let decoded = ImageTexture::decode(&bytes, srgb)?;
+        self.images
+            .borrow_mut()
+            .insert((source, srgb), decoded.clone());
+        Ok(Some(decoded))
+    }
+
+    fn buffer_view(&self, index: usize) -> io::Result<&[u8]> {
+        let view = &self.document["bufferViews"][index];
+        let buffer = self
+            .buffers
+            .get(view["buffer"].as_u64().unwrap_or(u64::MAX) as usize)
+            .ok_or_else(|| invalid_data(&format!("buffer view {index} without buffer")))?;
+        let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let length = view["byteLength"].as_u64().unwrap_or(0) as usize;
+        buffer
+            .get(offset..offset + length)
+            .ok_or_else(|| invalid_data(&format!("buffer view {index} is out of bounds")))
+    }
+
+    fn read_vectors<const N: usize>(&self, accessor: usize) -> io::Result<Vec<[f32; N]>> {
+        let (values, components) = self.read_accessor(accessor)?;
+        if components != N {
+            return Err(invalid_data(&format!(
+                "accessor {accessor} has {components} components instead of {N}"
+            )));
+        }
+        Ok(values
+            .chunks_exact(N)
+            .map(|chunk| std::array::from_fn(|i| chunk[i] as f32))
+            .collect())
+    }
+
+    // Reads all elements of an accessor into a flat list, returns it with the number of
+    // components per element. Normalized integers are mapped to [0, 1] or [-1, 1].
+    fn read_accessor(&self, index: usize) -> io::Result<(Vec<f64>, usize)> {
+        let accessor = &self.document["accessors"][index];
+        let error = |message: &str| invalid_data(&format!("accessor {index}: {message}"));
+        let count = accessor["count"]
+            .as_u64()
+            .ok_or_else(|| error("missing count"))? as usize;
+        let components = match accessor["type"].as_str() {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            _ => return Err(error("unsupported type")),
+        };
+        let component_type = accessor["componentType"].as_u64().unwrap_or(0);
+        let size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => return Err(error("unsupported component type")),
+        };
+        let normalized = accessor["normalized"].as_bool().unwrap_or(false);
+        if accessor.get("sparse").is_some() {
+            warn!("ignoring the sparse values of accessor {index}");
+        }
+
+        // Without a buffer view all values are zero.
+        let mut values = vec![0.0; count * components];
+        let Some(view) = self::index(&accessor["bufferView"]) else {
+            return Ok((values, components));
+        };
+        let data = self.buffer_view(view)?;
+        let offset = accessor["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let stride = self.document["bufferViews"][view]["byteStride"]
+            .as_u64()
+            .map_or(components * size, |stride| stride as usize);
+        for (element, chunk) in values.chunks_exact_mut(components).enumerate() {
+            for (component, value) in chunk.iter_mut().enumerate() {
+                let start = offset + element * stride + component * size;
+                let bytes = data
+                    .get(start..start + size)
+                    .ok_or_else(|| error("out of bounds"))?;
+                *value = decode_component(component_type, bytes, normalized);
+            }
+        }
+        Ok((values, components))
+    }
+}
+
+fn decode_component(component_type: u64, bytes: &[u8], normalized: bool) -> f64 {
+    let (value, scale) = match component_type {
+        5120 => (bytes[0] as i8 as f64, 127.0),
+        5121 => (bytes[0] as f64, 255.0),
+        5122 => (i16::from_le_bytes([bytes[0], bytes[1]]) as f64, 32767.0),
+        5123 => (u16::from_le_bytes([bytes[0], bytes[1]]) as f64, 65535.0),
+        5125 => (
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            1.0,
+        ),
+        _ => (
+            f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            1.0,
+        ),
+    };
+    if normalized {
+        (value / scale).max(-1.0)
+    } else {
+        value
+    }
+}
+
+fn local_transform(node: &Value) -> Transform {
+    if let Some(matrix) = floats::<16>(&node["matrix"]) {
+        return Transform::from_columns(&matrix);
+    }
+    let [tx, ty, tz] = floats::<3>(&node["translation"]).unwrap_or([0.0; 3]);
+    let rotation = floats::<4>(&node["rotation"]).unwrap_or([0.0, 0.0, 0.0, 1.0]);
+    let [sx, sy, sz] = floats::<3>(&node["scale"]).unwrap_or([1.0; 3]);
+    Transform::from_trs(Vec3::new(tx, ty, tz), rotation, Vec3::new(sx, sy, sz))
+}
+
+// Contents of a buffer or image URI, either embedded base64 data or a file next to the scene.
+fn read_uri(base: &Path, uri: &str) -> io::Result<Vec<u8>> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (header, payload) = data
+            .split_once(',')
+            .ok_or_else(|| invalid_data("malformed data URI"))?;
+        if !header.ends_with(";base64") {
+            return Err(invalid_data("only base64 data URIs are supported"));
+        }
+        return decode_base64(payload).ok_or_else(|| invalid_data("malformed base64 data"));
+    }
+    fs::read(base.join(percent_decode(uri)))
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' | b'\n' | b'\r' | b' ' => continue,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[inline]
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map_or(&[], |array| array.as_slice())
+}
+
+#[inline]
+fn index(value: &Value) -> Option<usize> {
+    value.as_u64().map(|index| index as usize)
+}
+
+fn floats<const N: usize>(value: &Value) -> Option<[f32; N]> {
+    let array = value.as_array()?;
+    if array.len() != N {
+        return None;
+    }
+    let mut floats = [0.0; N];
+    for (float, value) in floats.iter_mut().zip(array) {
+        *float = value.as_f64()? as f32;
+    }
+    Some(floats)
+}
+
+#[inline]
+fn gray(value: f32) -> Color {
+    Color::new(value, value, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+
+    // One triangle in the z = 0 plane, moved by its node, with the buffer embedded as base64.
+    fn triangle_gltf() -> String {
+        let mut buffer = vec![];
+        for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+        let base64 = encode_base64(&buffer);
+        format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "scene": 0,
+                "scenes": [{{ "nodes": [0, 1] }}],
+                "nodes": [
+                    {{ "mesh": 0, "translation": [0.0, 0.0, -2.0] }},
+                    {{ "camera": 0, "translation": [0.0, 0.0, 3.0] }}
+                ],
+                "cameras": [{{
+                    "type": "perspective",
+                    "perspective": {{ "yfov": 0.5, "aspectRatio": 2.0, "znear": 0.1 }}
+                }}],
+                "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "material": 0 }}] }}],
+                "materials": [{{ "pbrMetallicRoughness": {{ "baseColorFactor": [1, 0, 0, 1] }} }}],
+                "accessors": [{{
+                    "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"
+                }}],
+                "bufferViews": [{{ "buffer": 0, "byteLength": 36 }}],
+                "buffers": [{{
+                    "byteLength": 36,
+                    "uri": "data:application/octet-stream;base64,{base64}"
+                }}]
+            }}"#
+        )
+    }
+
+    fn encode_base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut text = String::new();
+        for chunk in bytes.chunks(3) {
+            let b = [
+                chunk[0],
+                *chunk.get(1).unwrap_or(&0),
+                *chunk.get(2).unwrap_or(&0),
+            ];
+            let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+            for i in 0..=chunk.len() {
+                text.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            }
+        }
+        while !text.len().is_multiple_of(4) {
+            text.push('=');
+        }
+        text
+    }
+
+    #[test]
+    fn test_base64() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode_base64(&encode_base64(&bytes)).unwrap(), bytes);
+        assert_eq!(decode_base64("aGk=").unwrap(), b"hi");
+        assert_eq!(percent_decode("my%20scene.bin"), "my scene.bin");
+    }
+
+    #[test]
+    fn test_load() {
+        // Apart from the files of other test runs at the same time.
+        let name = format!("sansara_test_triangle_{}.gltf", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, triangle_gltf()).unwrap();
+        let scene = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut rec = HitRecord::new();
+        let ray = Ray::new(Point3::new(0.2, 0.2, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(scene
+            .world
+            .hit(&ray, Interval::new(0.001, f32::INFINITY), &mut rec));
+        assert!((rec.t - 3.0).abs() < 1e-6);
+        assert!(rec.front_face);
+
+        let camera = scene.camera.unwrap();
+        assert_eq!(camera.transform.look_from, Point3::new(0.0, 0.0, 3.0));
+        assert_eq!(camera.transform.look_at, Point3::new(0.0, 0.0, 2.0));
+        assert_eq!(camera.settings.aspect_ratio, 2.0);
+        assert!((camera.settings.vfov - 0.5f32.to_degrees()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_orthographic_camera() {
+        let name = format!("sansara_test_orthographic_{}.gltf", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let gltf = triangle_gltf().replace(
+            r#""type": "perspective","#,
+            r#""type": "orthographic", "orthographic": { "xmag": 3.0, "ymag": 1.5 },"#,
+        );
+        fs::write(&path, gltf).unwrap();
+        let scene = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let camera = scene.camera.unwrap();
+        assert_eq!(camera.settings.aspect_ratio, 2.0);
+        assert_eq!(
+            camera.settings.projection,
+            Projection::Orthographic { height: 3.0 }
+        );
+    }
+
+    #[test]
+    fn test_glb() {
+        let json = triangle_gltf();
+        let mut glb = vec![];
+        let padded = json.len().next_multiple_of(4);
+        glb.extend_from_slice(GLB_MAGIC);
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&((12 + 8 + padded) as u32).to_le_bytes());
+        glb.extend_from_slice(&(padded as u32).to_le_bytes());
+        glb.extend_from_slice(&CHUNK_JSON.to_le_bytes());
+        glb.extend_from_slice(json.as_bytes());
+        glb.resize(12 + 8 + padded, b' ');
+
+        let (chunk, bin) = parse_glb(&glb).unwrap();
+        assert_eq!(chunk.len(), padded);
+        assert!(bin.is_none());
+        assert!(parse_glb(&glb[..10]).is_err());
+    }
+}
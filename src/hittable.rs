@@ -1,14 +1,23 @@
 use crate::{
+    aabb::Aabb,
+    color::Color,
     interval::Interval,
     material::MaterialType,
     ray::Ray,
     vec3::{self, Point3, Vec3},
 };
 
+use std::hash::Hasher;
 use std::sync::Arc;
 
 pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool;
+
+    fn bounding_box(&self) -> Aabb;
+
+    // Feeds everything that affects the rendered image into `state`, so that two scenes can be
+    // told apart by hash (e.g. before resuming a render from a checkpoint).
+    fn fingerprint(&self, state: &mut dyn Hasher);
 }
 
 #[derive(Default, Clone)]
@@ -16,8 +25,15 @@ pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
     pub t: f32,
+    // Surface coordinates of the hit point, for texture lookups.
+    pub u: f32,
+    pub v: f32,
+    // Interpolated vertex color of meshes that have them, multiplies the diffuse albedo.
+    pub vertex_color: Option<Color>,
     pub front_face: bool,
     pub material: Option<Arc<MaterialType>>,
+    // Tells the objects apart, which can share a material, for dielectrics to nest.
+    pub object: usize,
 }
 
 impl HitRecord {
@@ -43,13 +59,24 @@ pub struct Sphere {
 }
 
 impl Sphere {
-    pub fn new(center: Point3, radius: f32, material: MaterialType) -> Self {
+    pub fn new(center: Point3, radius: f32, material: impl Into<Arc<MaterialType>>) -> Self {
         Self {
             center,
             radius,
-            material: Some(Arc::new(material)),
+            material: Some(material.into()),
         }
     }
+
+    // Maps a point on the unit sphere to (u, v) in [0, 1], with u going around the y axis
+    // starting from -x and v from the bottom pole to the top one.
+    fn get_uv(p: Point3) -> (f32, f32) {
+        let theta = (-p[1]).clamp(-1.0, 1.0).acos();
+        let phi = (-p[2]).atan2(p[0]) + std::f32::consts::PI;
+        (
+            phi / (2.0 * std::f32::consts::PI),
+            theta / std::f32::consts::PI,
+        )
+    }
 }
 
 impl Hittable for Sphere {
@@ -80,8 +107,25 @@ impl Hittable for Sphere {
         rec.normal = (rec.p - self.center) / self.radius;
         let outward_normal = (rec.p - self.center) / self.radius;
         rec.set_face_normal(r, outward_normal);
+        (rec.u, rec.v) = Sphere::get_uv(outward_normal);
+        rec.vertex_color = None;
         rec.material = self.material.clone();
+        rec.object = self as *const Self as usize;
 
         true
     }
+
+    fn bounding_box(&self) -> Aabb {
+        let radius = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::new(self.center - radius, self.center + radius)
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        state.write(b"sphere");
+        vec3::fingerprint(&self.center, state);
+        state.write_u32(self.radius.to_bits());
+        if let Some(material) = &self.material {
+            material.fingerprint(state);
+        }
+    }
 }
@@ -1,32 +1,45 @@
-use crate::hittable::{HitRecord, Hittable, Sphere};
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
 use crate::ray::Ray;
 
+use std::hash::Hasher;
 use std::sync::Arc;
 
 #[derive(Default)]
 pub struct HittableList {
-    objects: Vec<Arc<Sphere>>,
+    objects: Vec<Arc<dyn Hittable>>,
+    bbox: Aabb,
 }
 
 impl HittableList {
     pub fn new() -> Self {
-        Self { objects: vec![] }
+        Self {
+            objects: vec![],
+            bbox: Aabb::empty(),
+        }
     }
 
     #[inline]
-    pub fn with_object(&mut self, object: Arc<Sphere>) {
+    pub fn with_object(&mut self, object: Arc<dyn Hittable>) {
         self.add(object);
     }
 
     #[inline]
-    pub fn add(&mut self, object: Arc<Sphere>) {
+    pub fn add(&mut self, object: Arc<dyn Hittable>) {
+        self.bbox = Aabb::surrounding(&self.bbox, &object.bounding_box());
         self.objects.push(object);
     }
 
     #[inline]
     pub fn clear(&mut self) {
         self.objects.clear();
+        self.bbox = Aabb::empty();
+    }
+
+    #[inline]
+    pub fn objects(&self) -> &[Arc<dyn Hittable>] {
+        &self.objects
     }
 }
 
@@ -48,4 +61,15 @@ impl Hittable for HittableList {
         }
         hit_anything
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        state.write_usize(self.objects.len());
+        for object in &self.objects {
+            object.fingerprint(state);
+        }
+    }
 }
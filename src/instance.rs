@@ -0,0 +1,217 @@
+// An object placed into the scene by a transform, which can be keyframed to move it over time.
+
+use crate::{
+    aabb::Aabb,
+    animation::{Interpolation, Track},
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    ray::Ray,
+    rtweekend,
+    transform::Transform,
+    vec3::{self, Point3, Vec3},
+};
+
+use std::hash::Hasher;
+use std::sync::Arc;
+
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    translation: Track<Vec3>,
+    // Euler angles in degrees, see `Transform::rotate_euler`.
+    rotation: Track<Vec3>,
+    scale: Track<Vec3>,
+    // Object to world and back, computed once when nothing moves.
+    fixed: Option<(Transform, Transform)>,
+    // Covers the object over the whole animation.
+    bbox: Aabb,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, interpolation: Interpolation) -> Self {
+        let mut instance = Self {
+            object,
+            translation: Track::new(interpolation),
+            rotation: Track::new(interpolation),
+            scale: Track::new(interpolation),
+            fixed: None,
+            bbox: Aabb::empty(),
+        };
+        instance.update();
+        instance
+    }
+
+    pub fn with_translation(mut self, time: f32, translation: Vec3) -> Self {
+        self.translation = self.translation.with_key(time, translation);
+        self.update();
+        self
+    }
+
+    pub fn with_rotation(mut self, time: f32, degrees: Vec3) -> Self {
+        self.rotation = self.rotation.with_key(time, degrees);
+        self.update();
+        self
+    }
+
+    pub fn with_scale(mut self, time: f32, scale: Vec3) -> Self {
+        self.scale = self.scale.with_key(time, scale);
+        self.update();
+        self
+    }
+
+    // Object to world transform at `time`.
+    pub fn transform(&self, time: f32) -> Transform {
+        let translation = self.translation.sample(time).unwrap_or_default();
+        let rotation = self.rotation.sample(time).unwrap_or_default();
+        let scale = self.scale.sample(time).unwrap_or(Vec3::new(1.0, 1.0, 1.0));
+        Transform::translate(translation)
+            * Transform::rotate_euler(rotation)
+            * Transform::scale(scale)
+    }
+
+    // Times of all keys, sorted and without duplicates.
+    fn key_times(&self) -> Vec<f32> {
+        let mut times: Vec<f32> = [
+            self.translation
+                .keys()
+                .iter()
+                .map(|key| key.0)
+                .collect::<Vec<_>>(),
+            self.rotation.keys().iter().map(|key| key.0).collect(),
+            self.scale.keys().iter().map(|key| key.0).collect(),
+        ]
+        .concat();
+        times.sort_by(f32::total_cmp);
+        times.dedup();
+        times
+    }
+
+    fn update(&mut self) {
+        let times = self.key_times();
+        let object_box = self.object.bounding_box();
+        if times.len() <= 1 {
+            let transform = self.transform(0.0);
+            self.fixed = transform.inverse().map(|inverse| (transform, inverse));
+            self.bbox = transform_box(&transform, &object_box);
+            return;
+        }
+
+        // Splines can overshoot the keys, so the box is taken over many steps between them.
+        const STEPS: usize = 16;
+        self.fixed = None;
+        self.bbox = Aabb::empty();
+        for pair in times.windows(2) {
+            for step in 0..=STEPS {
+                let time = pair[0] + (pair[1] - pair[0]) * step as f32 / STEPS as f32;
+                let moved = transform_box(&self.transform(time), &object_box);
+                self.bbox = Aabb::surrounding(&self.bbox, &moved);
+            }
+        }
+    }
+}
+
+fn transform_box(transform: &Transform, bbox: &Aabb) -> Aabb {
+    if bbox.is_empty() {
+        return *bbox;
+    }
+    let mut moved = Aabb::empty();
+    for corner in 0..8 {
+        let pick = |axis: usize| {
+            if corner & (1 << axis) == 0 {
+                bbox.min[axis]
+            } else {
+                bbox.max[axis]
+            }
+        };
+        moved = moved.include(transform.point(Point3::new(pick(0), pick(1), pick(2))));
+    }
+    moved
+}
+
+fn fingerprint_track(track: &Track<Vec3>, state: &mut dyn Hasher) {
+    state.write(track.interpolation().name().as_bytes());
+    state.write_usize(track.keys().len());
+    for (time, value) in track.keys() {
+        state.write_u32(time.to_bits());
+        vec3::fingerprint(value, state);
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let (to_world, to_object) = match self.fixed {
+            Some(fixed) => fixed,
+            None => {
+                let transform = self.transform(r.time());
+                let Some(inverse) = transform.inverse() else {
+                    return false;
+                };
+                (transform, inverse)
+            }
+        };
+
+        // The direction is not normalized, so distances along the ray stay the same.
+        let local = Ray::new(
+            to_object.point(*r.origin()),
+            to_object.vector(*r.direction()),
+        )
+        .with_time(r.time());
+        if !self.object.hit(&local, ray_t, rec) {
+            return false;
+        }
+        rec.p = to_world.point(rec.p);
+        rec.normal = to_world.normal(rec.normal);
+        // Instances of the same object are objects of their own.
+        rec.object = rtweekend::mix_seed(self as *const Self as u64, rec.object as u64) as usize;
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        state.write(b"instance");
+        self.object.fingerprint(state);
+        fingerprint_track(&self.translation, state);
+        fingerprint_track(&self.rotation, state);
+        fingerprint_track(&self.scale, state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::hittable::Sphere;
+    use crate::material::{Lambertian, MaterialType};
+
+    #[test]
+    fn test_moving_instance() {
+        let sphere = Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            MaterialType::Lambertian(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        );
+        let instance = Instance::new(Arc::new(sphere), Interpolation::Linear)
+            .with_translation(0.0, Vec3::new(0.0, 0.0, 0.0))
+            .with_translation(1.0, Vec3::new(4.0, 0.0, 0.0))
+            .with_scale(0.0, Vec3::new(2.0, 2.0, 2.0));
+        let bbox = instance.bounding_box();
+        assert!((bbox.min - Point3::new(-2.0, -2.0, -2.0)).length() < 1e-5);
+        assert!((bbox.max - Point3::new(6.0, 2.0, 2.0)).length() < 1e-5);
+
+        // Halfway the sphere of radius 2 is centered at x = 2.
+        let ray = Ray::new(Point3::new(2.0, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::new();
+        let ray_t = Interval::new(0.001, f32::INFINITY);
+        assert!(instance.hit(&ray.with_time(0.5), ray_t, &mut rec));
+        assert!((rec.t - 8.0).abs() < 1e-4);
+        assert!((rec.p - Point3::new(2.0, 0.0, 2.0)).length() < 1e-4);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-4);
+        assert!(rec.front_face);
+
+        let ray = Ray::new(Point3::new(5.0, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!instance.hit(&ray.with_time(0.0), ray_t, &mut rec));
+        assert!(instance.hit(&ray.with_time(1.0), ray_t, &mut rec));
+    }
+}
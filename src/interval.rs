@@ -1,5 +1,4 @@
-use std::f32::INFINITY;
-
+#[derive(Debug, Clone, Copy)]
 pub struct Interval {
     pub min: f32,
     pub max: f32,
@@ -8,8 +7,8 @@ pub struct Interval {
 impl Default for Interval {
     fn default() -> Self {
         Self {
-            min: -INFINITY,
-            max: INFINITY,
+            min: -f32::INFINITY,
+            max: f32::INFINITY,
         }
     }
 }
@@ -36,12 +35,12 @@ impl Interval {
 
     #[inline]
     pub fn empty() -> Self {
-        Self::new(INFINITY, -INFINITY)
+        Self::new(f32::INFINITY, -f32::INFINITY)
     }
 
     #[inline]
     pub fn universe() -> Self {
-        Self::new(-INFINITY, INFINITY)
+        Self::new(-f32::INFINITY, f32::INFINITY)
     }
 }
 
@@ -83,13 +82,13 @@ mod test {
 
     #[test]
     fn test_smoke() {
-        assert_eq!(Interval::default().min, -INFINITY);
-        assert_eq!(Interval::default().max, INFINITY);
+        assert_eq!(Interval::default().min, -f32::INFINITY);
+        assert_eq!(Interval::default().max, f32::INFINITY);
 
-        assert_eq!(Interval::universe().min, -INFINITY);
-        assert_eq!(Interval::universe().max, INFINITY);
+        assert_eq!(Interval::universe().min, -f32::INFINITY);
+        assert_eq!(Interval::universe().max, f32::INFINITY);
 
-        assert_eq!(Interval::empty().min, INFINITY);
-        assert_eq!(Interval::empty().max, -INFINITY);
+        assert_eq!(Interval::empty().min, f32::INFINITY);
+        assert_eq!(Interval::empty().max, -f32::INFINITY);
     }
 }
@@ -1,11 +1,37 @@
+pub mod aabb;
+pub mod animation;
+pub mod bvh;
 pub mod camera;
+pub mod checkpoint;
 pub mod color;
+pub mod distribution;
+pub mod environment;
+pub mod filter;
+pub mod framebuffer;
 pub mod generate_scene;
+pub mod gltf;
 pub mod hittable;
 pub mod hittable_list;
+pub mod instance;
 pub mod interval;
+pub mod light;
 pub mod material;
+pub mod medium;
+pub mod mesh;
+pub mod microfacet;
+pub mod onb;
+pub mod ply;
+pub mod progress;
 pub mod ray;
 pub mod render;
 pub mod rtweekend;
+pub mod sampler;
+pub mod scene;
+pub mod sky;
+pub mod spectrum;
+pub mod stl;
+pub mod texture;
+pub mod transform;
 pub mod vec3;
+pub mod world;
+pub mod writer;
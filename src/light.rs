@@ -0,0 +1,274 @@
+// Analytic lights. They have no surface that rays could hit, the scene only sees them through
+// shadow rays from the points they light.
+
+use crate::color::Color;
+use crate::onb::Onb;
+use crate::vec3::{self, Point3, Vec3};
+
+use std::f32::consts::PI;
+use std::hash::Hasher;
+
+pub trait Light: Send + Sync {
+    // Picks a direction from `p` towards the light, `None` when no light reaches `p`.
+    fn sample_li(&self, p: Point3, u: (f32, f32)) -> Option<LightSample>;
+}
+
+pub struct LightSample {
+    // Unit direction towards the light.
+    pub wi: Vec3,
+    // Distance to the light along `wi`, infinite for directional lights.
+    pub distance: f32,
+    // Radiance arriving from `wi`, divided by the density of picking that direction.
+    pub li: Color,
+}
+
+#[derive(Debug, Clone)]
+pub enum LightType {
+    Point(PointLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+}
+
+// Shines equally in every direction, falling off with the square of the distance.
+#[derive(Debug, Clone)]
+pub struct PointLight {
+    position: Point3,
+    // Radiant intensity, the power per solid angle.
+    intensity: Color,
+    // Distance at which the light smoothly fades out, unlimited by default.
+    range: Option<f32>,
+}
+
+// A point light shining into a cone, fading out between the inner and the outer angle.
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    position: Point3,
+    direction: Vec3,
+    intensity: Color,
+    cos_inner: f32,
+    cos_outer: f32,
+    range: Option<f32>,
+}
+
+// Light from far away, like the sun, arriving from a small disk of directions.
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    // The direction the light travels in.
+    direction: Vec3,
+    // Irradiance on a surface facing the light.
+    irradiance: Color,
+    cos_max: f32,
+}
+
+impl LightType {
+    pub fn fingerprint(&self, state: &mut dyn Hasher) {
+        match self {
+            LightType::Point(light) => {
+                state.write(b"point");
+                vec3::fingerprint(&light.position, state);
+                vec3::fingerprint(&light.intensity, state);
+                state.write_u32(light.range.unwrap_or(0.0).to_bits());
+            }
+            LightType::Spot(light) => {
+                state.write(b"spot");
+                vec3::fingerprint(&light.position, state);
+                vec3::fingerprint(&light.direction, state);
+                vec3::fingerprint(&light.intensity, state);
+                state.write_u32(light.cos_inner.to_bits());
+                state.write_u32(light.cos_outer.to_bits());
+                state.write_u32(light.range.unwrap_or(0.0).to_bits());
+            }
+            LightType::Directional(light) => {
+                state.write(b"directional");
+                vec3::fingerprint(&light.direction, state);
+                vec3::fingerprint(&light.irradiance, state);
+                state.write_u32(light.cos_max.to_bits());
+            }
+        }
+    }
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+            range: None,
+        }
+    }
+
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = Some(range);
+        self
+    }
+}
+
+impl SpotLight {
+    // The cone angles are measured from its axis, in degrees.
+    pub fn new(
+        position: Point3,
+        direction: Vec3,
+        intensity: Color,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Self {
+        let outer_angle = outer_angle.clamp(0.0, 180.0);
+        let inner_angle = inner_angle.clamp(0.0, outer_angle);
+        Self {
+            position,
+            direction: vec3::unit_vector(direction),
+            intensity,
+            cos_inner: inner_angle.to_radians().cos(),
+            cos_outer: outer_angle.to_radians().cos(),
+            range: None,
+        }
+    }
+
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = Some(range);
+        self
+    }
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, irradiance: Color) -> Self {
+        Self {
+            direction: vec3::unit_vector(direction),
+            irradiance,
+            cos_max: 1.0,
+        }
+    }
+
+    // Spreads the light over a disk of directions this wide, in degrees, for soft shadows. The
+    // sun is about half a degree across.
+    pub fn with_angular_diameter(mut self, degrees: f32) -> Self {
+        self.cos_max = (degrees.clamp(0.0, 180.0) / 2.0).to_radians().cos();
+        self
+    }
+}
+
+// Inverse square falloff, windowed to reach zero at `range` without a visible edge.
+fn falloff(distance_squared: f32, range: Option<f32>) -> f32 {
+    let window = match range {
+        Some(range) => {
+            let ratio = distance_squared / (range * range);
+            (1.0 - ratio * ratio).clamp(0.0, 1.0).powi(2)
+        }
+        None => 1.0,
+    };
+    window / distance_squared
+}
+
+impl Light for LightType {
+    fn sample_li(&self, p: Point3, u: (f32, f32)) -> Option<LightSample> {
+        match self {
+            LightType::Point(light) => {
+                let offset = light.position - p;
+                let distance_squared = offset.length_squared();
+                if distance_squared == 0.0 {
+                    return None;
+                }
+                let distance = distance_squared.sqrt();
+                Some(LightSample {
+                    wi: offset / distance,
+                    distance,
+                    li: light.intensity * falloff(distance_squared, light.range),
+                })
+            }
+
+            LightType::Spot(light) => {
+                let offset = light.position - p;
+                let distance_squared = offset.length_squared();
+                if distance_squared == 0.0 {
+                    return None;
+                }
+                let distance = distance_squared.sqrt();
+                let wi = offset / distance;
+                // Smoothstep from the outer to the inner cone.
+                let cos_theta = vec3::dot(-wi, light.direction);
+                let edge = if light.cos_inner > light.cos_outer {
+                    let t = ((cos_theta - light.cos_outer) / (light.cos_inner - light.cos_outer))
+                        .clamp(0.0, 1.0);
+                    t * t * (3.0 - 2.0 * t)
+                } else if cos_theta >= light.cos_outer {
+                    1.0
+                } else {
+                    0.0
+                };
+                if edge == 0.0 {
+                    return None;
+                }
+                Some(LightSample {
+                    wi,
+                    distance,
+                    li: light.intensity * (edge * falloff(distance_squared, light.range)),
+                })
+            }
+
+            LightType::Directional(light) => {
+                // Uniform over the cone, the radiance is the irradiance over its solid angle,
+                // which the density cancels.
+                let cos_theta = 1.0 - u.0 * (1.0 - light.cos_max);
+                let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+                let phi = 2.0 * PI * u.1;
+                let local = Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta);
+                Some(LightSample {
+                    wi: Onb::from_w(-light.direction).local(local),
+                    distance: f32::INFINITY,
+                    li: light.irradiance,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_falloff() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let point = LightType::Point(PointLight::new(Point3::new(0.0, 4.0, 0.0), white));
+        let sample = point.sample_li(Point3::default(), (0.5, 0.5)).unwrap();
+        assert_eq!(sample.distance, 4.0);
+        assert!((sample.wi - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-6);
+        assert!((sample.li[0] - 1.0 / 16.0).abs() < 1e-6);
+
+        // The range fades the light out before it's reached.
+        let ranged =
+            LightType::Point(PointLight::new(Point3::new(0.0, 4.0, 0.0), white).with_range(4.0));
+        assert_eq!(
+            ranged.sample_li(Point3::default(), (0.5, 0.5)).unwrap().li[0],
+            0.0
+        );
+
+        let spot = LightType::Spot(SpotLight::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            white,
+            20.0,
+            30.0,
+        ));
+        let center = spot.sample_li(Point3::default(), (0.5, 0.5)).unwrap();
+        assert!((center.li[0] - 1.0).abs() < 1e-6);
+        // Halfway through the soft edge, and outside of the cone.
+        let edge = spot
+            .sample_li(Point3::new(25f32.to_radians().tan(), 0.0, 0.0), (0.5, 0.5))
+            .unwrap();
+        let edge = edge.li[0] / 25f32.to_radians().cos().powi(2);
+        assert!(edge > 0.3 && edge < 0.7);
+        assert!(spot
+            .sample_li(Point3::new(1.0, 0.0, 0.0), (0.5, 0.5))
+            .is_none());
+
+        let sun = LightType::Directional(
+            DirectionalLight::new(Vec3::new(0.0, -1.0, 0.0), white).with_angular_diameter(10.0),
+        );
+        for u in [(0.0, 0.0), (0.5, 0.25), (0.99, 0.75)] {
+            let sample = sun.sample_li(Point3::default(), u).unwrap();
+            assert!(sample.wi[1] >= 5f32.to_radians().cos() - 1e-6);
+            assert_eq!(sample.distance, f32::INFINITY);
+        }
+    }
+}
@@ -1,10 +1,19 @@
 #![allow(unused_variables)]
 
-use crate::color::Color;
+use crate::animation::{Interpolation, Track};
+use crate::color::{luminance, Color};
 use crate::hittable::HitRecord;
+use crate::medium::Medium;
+use crate::microfacet::{self, TrowbridgeReitz};
+use crate::onb::Onb;
 use crate::ray::Ray;
-use crate::rtweekend::random_double;
-use crate::vec3;
+use crate::sampler::Sampler;
+use crate::spectrum::RGB_WAVELENGTHS;
+use crate::texture::{Texture, TextureType};
+use crate::vec3::{self, Vec3};
+
+use std::f32::consts::PI;
+use std::hash::Hasher;
 
 pub trait Material: Send + Sync {
     fn scatter(
@@ -13,13 +22,37 @@ pub trait Material: Send + Sync {
         rec: &HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool;
+
+    // The BSDF times the cosine for light arriving from the unit direction `wi`, to gather the
+    // analytic lights. Perfectly specular materials can't be lit that way and return black.
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color;
+
+    // Density of `scatter` picking the unit direction `wi` over solid angle, for the directions
+    // `eval` covers.
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> f32;
+
+    // Whether the directions `scatter` picks depend on the wavelength of the ray.
+    fn dispersive(&self) -> bool;
+
+    // Whether `scatter` gives the attenuation at the wavelengths of spectral rays, not in RGB.
+    fn spectral(&self) -> bool;
+
+    // The inside of the dielectric object hit at `rec`, for rays to keep track of what they are
+    // in.
+    fn medium(&self, r_in: &Ray, rec: &HitRecord) -> Option<Medium>;
 }
 
 pub enum MaterialType {
     Lambertian(Lambertian),
     Metal(Metal),
     Dielectric(Dielectric),
+    Conductor(Conductor),
+    RoughDielectric(RoughDielectric),
+    Principled(Box<Principled>),
+    Animated(Box<Animated>),
+    Coated(Box<Coated>),
 }
 
 pub struct Lambertian {
@@ -29,16 +62,240 @@ pub struct Lambertian {
 pub struct Metal {
     albedo: Color,
     fuzz: f32,
+    film: Option<ThinFilm>,
 }
 
 pub struct Dielectric {
+    // Index of Refraction
+    ir: Ior,
+    // Beer-Lambert absorption coefficient per unit of length inside, none for clear glass.
+    absorption: Option<Color>,
+    // Decides which of two overlapping dielectrics fills the overlap, the higher one does.
+    priority: i32,
+    film: Option<ThinFilm>,
+}
+
+// A transparent film a few hundred nanometers thin on a surface, like soap or the coating of a
+// lens. Light reflected off its top and its bottom interferes, some wavelengths cancel out and
+// others add up into iridescent colors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThinFilm {
+    // In nanometers.
+    thickness: f32,
     // Index of Refraction
     ir: f32,
 }
 
+// Index of refraction over the wavelength. Without a wavelength it's taken at the helium d line,
+// where glass catalogs give it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ior {
+    Constant(f32),
+    // n = a + b / λ², with λ in micrometers.
+    Cauchy { a: f32, b: f32 },
+    // n² = 1 + Σ b λ² / (λ² - c), with λ in micrometers and c in square micrometers.
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
+}
+
+// GGX microfacet metal with a complex index of refraction `eta + i k` per color channel.
+pub struct Conductor {
+    eta: Color,
+    k: Color,
+    roughness: f32,
+}
+
+// GGX microfacet glass, reflects and transmits through rough microfacets.
+pub struct RoughDielectric {
+    // Index of Refraction
+    ir: f32,
+    roughness: f32,
+}
+
+// Disney style uber material, a clear coat over either a metal or a dielectric base that
+// transmits or reflects diffusely with sheen. Every parameter but the index of refraction can be
+// textured, scalar parameters are read from the first channel of their texture.
+pub struct Principled {
+    base_color: TextureType,
+    metallic: TextureType,
+    roughness: TextureType,
+    // Reflectance of the dielectric base at normal incidence, scaled so that 0.5 is 4%.
+    specular: TextureType,
+    transmission: TextureType,
+    // Index of Refraction of the transmitting base.
+    ior: f32,
+    clearcoat: TextureType,
+    clearcoat_roughness: TextureType,
+    sheen: TextureType,
+    // Blends the sheen color from white to the hue of the base color.
+    sheen_tint: TextureType,
+}
+
+// One of the classic materials with keyframed parameters, evaluated at the time of the ray.
+pub struct Animated {
+    kind: AnimatedKind,
+    albedo: Track<Color>,
+    fuzz: Track<f32>,
+    // Index of Refraction
+    ir: Track<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimatedKind {
+    Lambertian,
+    Metal,
+    Dielectric,
+}
+
+// A clear coat over any other material, like varnish or car paint. Light reflects off the coat by
+// its Fresnel term, the rest goes through to the base and crosses the coat again on its way out.
+pub struct Coated {
+    base: MaterialType,
+    // Index of Refraction of the coat.
+    ior: f32,
+    roughness: f32,
+}
+
+impl Ior {
+    // Wavelength of the helium d line, in nanometers.
+    pub const D_LINE: f32 = 587.6;
+    // The hydrogen F and C lines, blue and red, that the Abbe number compares.
+    const F_LINE: f32 = 486.1;
+    const C_LINE: f32 = 656.3;
+
+    // Cauchy's formula through the index `nd` at the d line with the Abbe number `vd` of glass
+    // catalogs, (nd - 1) / (nF - nC). Lower Abbe numbers spread the colors more.
+    pub fn abbe(nd: f32, vd: f32) -> Self {
+        let inverse_square = |wavelength: f32| 1e6 / (wavelength * wavelength);
+        let b = (nd - 1.0)
+            / (vd.max(1.0) * (inverse_square(Self::F_LINE) - inverse_square(Self::C_LINE)));
+        Ior::Cauchy {
+            a: nd - b * inverse_square(Self::D_LINE),
+            b,
+        }
+    }
+
+    // Common crown glass, nd = 1.517 and vd = 64.2.
+    pub fn bk7() -> Self {
+        Ior::Sellmeier {
+            b: [1.039612, 0.2317923, 1.0104695],
+            c: [0.0060007, 0.020017914, 103.56065],
+        }
+    }
+
+    // Dense flint glass, nd = 1.785 and vd = 25.7.
+    pub fn sf11() -> Self {
+        Ior::Sellmeier {
+            b: [1.737597, 0.31374735, 1.898781],
+            c: [0.013188707, 0.062306814, 155.2363],
+        }
+    }
+
+    // Fused quartz, nd = 1.458 and vd = 67.8.
+    pub fn fused_silica() -> Self {
+        Ior::Sellmeier {
+            b: [0.6961663, 0.4079426, 0.8974794],
+            c: [0.004679148, 0.013512063, 97.934],
+        }
+    }
+
+    // nd = 2.417 and vd = 55.3, the fire of a diamond comes from its high index.
+    pub fn diamond() -> Self {
+        Ior::Sellmeier {
+            b: [4.3356, 0.3306, 0.0],
+            c: [0.011236, 0.030625, 0.0],
+        }
+    }
+
+    // Looks up one of the glasses above by name.
+    pub fn named(name: &str) -> Option<Self> {
+        Some(match name {
+            "bk7" => Self::bk7(),
+            "sf11" => Self::sf11(),
+            "fused-silica" => Self::fused_silica(),
+            "diamond" => Self::diamond(),
+            _ => return None,
+        })
+    }
+
+    pub fn at(&self, wavelength: f32) -> f32 {
+        let lambda = wavelength / 1000.0;
+        let lambda2 = lambda * lambda;
+        match *self {
+            Ior::Constant(ir) => ir,
+            Ior::Cauchy { a, b } => a + b / lambda2,
+            Ior::Sellmeier { b, c } => {
+                let sum: f32 = (0..3).map(|i| b[i] * lambda2 / (lambda2 - c[i])).sum();
+                (1.0 + sum).max(1.0).sqrt()
+            }
+        }
+    }
+
+    #[inline]
+    pub fn is_dispersive(&self) -> bool {
+        !matches!(self, Ior::Constant(_))
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        match *self {
+            Ior::Constant(ir) => state.write_u32(ir.to_bits()),
+            Ior::Cauchy { a, b } => {
+                state.write(b"cauchy");
+                state.write_u32(a.to_bits());
+                state.write_u32(b.to_bits());
+            }
+            Ior::Sellmeier { b, c } => {
+                state.write(b"sellmeier");
+                for value in b.iter().chain(&c) {
+                    state.write_u32(value.to_bits());
+                }
+            }
+        }
+    }
+}
+
+impl From<f32> for Ior {
+    fn from(ir: f32) -> Self {
+        Ior::Constant(ir)
+    }
+}
+
 impl Dielectric {
-    pub fn new(ir: f32) -> Self {
-        Self { ir }
+    pub fn new(ir: impl Into<Ior>) -> Self {
+        Self {
+            ir: ir.into(),
+            absorption: None,
+            priority: 0,
+            film: None,
+        }
+    }
+
+    // Tints the light inside, white light turns into `color` after `distance`. Thicker parts look
+    // darker, like colored glass or liquids.
+    pub fn with_absorption(mut self, color: Color, distance: f32) -> Self {
+        let coefficient = |c: f32| -c.clamp(1e-6, 1.0).ln() / distance.max(1e-6);
+        self.absorption = Some(Color::new(
+            coefficient(color[0]),
+            coefficient(color[1]),
+            coefficient(color[2]),
+        ));
+        self
+    }
+
+    // Nested dielectrics, like water in a glass, overlap a little. The one with the higher
+    // priority is the one inside the overlap, for a glass that's the glass.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_thin_film(mut self, film: ThinFilm) -> Self {
+        self.film = Some(film);
+        self
+    }
+
+    // The index of refraction for the wavelength of the ray.
+    fn ir(&self, r_in: &Ray) -> f32 {
+        self.ir.at(r_in.wavelength().unwrap_or(Ior::D_LINE))
     }
 
     pub fn reflectance(&self, cosine: f32, ref_idx: f32) -> f32 {
@@ -58,10 +315,597 @@ impl Lambertian {
 impl Metal {
     pub fn new(albedo: Color, fuzz: f32) -> Self {
         let fuzz = if fuzz < 1.0 { fuzz } else { 1.0 };
-        Self { albedo, fuzz }
+        Self {
+            albedo,
+            fuzz,
+            film: None,
+        }
+    }
+
+    pub fn with_thin_film(mut self, film: ThinFilm) -> Self {
+        self.film = Some(film);
+        self
+    }
+}
+
+impl ThinFilm {
+    pub fn new(thickness: f32, ir: f32) -> Self {
+        Self {
+            thickness: thickness.max(0.0),
+            ir,
+        }
+    }
+
+    // Reflectance of the film on a base at the wavelengths of `r_in`, or for red, green and blue,
+    // lit from a medium of index `n_i`. `base` gives the s and p amplitudes the base reflects for
+    // one of them from the cosine inside the film, `None` when it reflects everything.
+    pub fn reflectance(
+        &self,
+        r_in: &Ray,
+        cos_theta: f32,
+        n_i: f32,
+        base: impl Fn(usize, f32) -> Option<(f32, f32)>,
+    ) -> Color {
+        let wavelengths = r_in
+            .wavelengths()
+            .map_or(RGB_WAVELENGTHS, |wavelengths| wavelengths.lambda());
+        let mut reflectance = Color::default();
+        for (c, wavelength) in wavelengths.into_iter().enumerate() {
+            reflectance[c] = microfacet::fresnel_thin_film(
+                cos_theta,
+                n_i,
+                self.ir,
+                self.thickness,
+                wavelength,
+                |cos_theta| base(c, cos_theta),
+            );
+        }
+        reflectance
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        state.write_u32(self.thickness.to_bits());
+        state.write_u32(self.ir.to_bits());
+    }
+}
+
+impl MaterialType {
+    // Whether the material only scatters into a few directions, which `eval` leaves out.
+    fn specular(&self) -> bool {
+        match self {
+            MaterialType::Metal(_) | MaterialType::Dielectric(_) => true,
+            MaterialType::Animated(material) => material.kind != AnimatedKind::Lambertian,
+            MaterialType::Coated(material) => material.base.specular(),
+            _ => false,
+        }
+    }
+
+    pub fn fingerprint(&self, state: &mut dyn Hasher) {
+        match self {
+            MaterialType::Lambertian(material) => {
+                state.write(b"lambertian");
+                vec3::fingerprint(&material.albedo, state);
+            }
+            MaterialType::Metal(material) => {
+                state.write(b"metal");
+                vec3::fingerprint(&material.albedo, state);
+                state.write_u32(material.fuzz.to_bits());
+                if let Some(film) = &material.film {
+                    film.fingerprint(state);
+                }
+            }
+            MaterialType::Dielectric(material) => {
+                state.write(b"dielectric");
+                material.ir.fingerprint(state);
+                if let Some(absorption) = &material.absorption {
+                    vec3::fingerprint(absorption, state);
+                }
+                state.write_i32(material.priority);
+                if let Some(film) = &material.film {
+                    film.fingerprint(state);
+                }
+            }
+            MaterialType::Conductor(material) => {
+                state.write(b"conductor");
+                vec3::fingerprint(&material.eta, state);
+                vec3::fingerprint(&material.k, state);
+                state.write_u32(material.roughness.to_bits());
+            }
+            MaterialType::RoughDielectric(material) => {
+                state.write(b"rough dielectric");
+                state.write_u32(material.ir.to_bits());
+                state.write_u32(material.roughness.to_bits());
+            }
+            MaterialType::Principled(material) => {
+                state.write(b"principled");
+                material.fingerprint(state);
+            }
+            MaterialType::Animated(material) => {
+                state.write(b"animated");
+                material.fingerprint(state);
+            }
+            MaterialType::Coated(material) => {
+                state.write(b"coated");
+                material.base.fingerprint(state);
+                state.write_u32(material.ior.to_bits());
+                state.write_u32(material.roughness.to_bits());
+            }
+        }
+    }
+}
+
+impl Conductor {
+    pub fn new(eta: Color, k: Color, roughness: f32) -> Self {
+        Self { eta, k, roughness }
+    }
+
+    // Complex indices of refraction for red, green and blue (650, 550 and 450nm).
+    pub fn gold(roughness: f32) -> Self {
+        Self::new(
+            Color::new(0.143, 0.374, 1.442),
+            Color::new(3.983, 2.385, 1.603),
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f32) -> Self {
+        Self::new(
+            Color::new(0.200, 0.924, 1.102),
+            Color::new(3.912, 2.452, 2.142),
+            roughness,
+        )
+    }
+
+    pub fn aluminum(roughness: f32) -> Self {
+        Self::new(
+            Color::new(1.657, 0.880, 0.521),
+            Color::new(9.224, 6.270, 4.837),
+            roughness,
+        )
+    }
+
+    pub fn silver(roughness: f32) -> Self {
+        Self::new(
+            Color::new(0.155, 0.117, 0.138),
+            Color::new(4.828, 3.122, 2.147),
+            roughness,
+        )
+    }
+
+    pub fn fresnel(&self, cos_theta: f32) -> Color {
+        Color::new(
+            microfacet::fresnel_complex(cos_theta, self.eta[0], self.k[0]),
+            microfacet::fresnel_complex(cos_theta, self.eta[1], self.k[1]),
+            microfacet::fresnel_complex(cos_theta, self.eta[2], self.k[2]),
+        )
+    }
+}
+
+impl RoughDielectric {
+    pub fn new(ir: f32, roughness: f32) -> Self {
+        Self { ir, roughness }
+    }
+
+    // Relative index of refraction, from the side of the ray to the other one.
+    fn eta(&self, r_in: &Ray, rec: &HitRecord) -> f32 {
+        let outside = outside_ir(r_in, rec);
+        if rec.front_face {
+            self.ir / outside
+        } else {
+            outside / self.ir
+        }
+    }
+}
+
+impl Animated {
+    pub fn new(kind: AnimatedKind, interpolation: Interpolation) -> Self {
+        Self {
+            kind,
+            albedo: Track::new(interpolation),
+            fuzz: Track::new(interpolation),
+            ir: Track::new(interpolation),
+        }
+    }
+
+    pub fn with_albedo(mut self, time: f32, albedo: Color) -> Self {
+        self.albedo = self.albedo.with_key(time, albedo);
+        self
+    }
+
+    pub fn with_fuzz(mut self, time: f32, fuzz: f32) -> Self {
+        self.fuzz = self.fuzz.with_key(time, fuzz);
+        self
+    }
+
+    pub fn with_ir(mut self, time: f32, ir: f32) -> Self {
+        self.ir = self.ir.with_key(time, ir);
+        self
+    }
+
+    // The material as it is at `time`.
+    pub fn at(&self, time: f32) -> MaterialType {
+        let albedo = self
+            .albedo
+            .sample(time)
+            .unwrap_or(Color::new(0.8, 0.8, 0.8));
+        match self.kind {
+            AnimatedKind::Lambertian => MaterialType::Lambertian(Lambertian::new(albedo)),
+            AnimatedKind::Metal => {
+                MaterialType::Metal(Metal::new(albedo, self.fuzz.sample(time).unwrap_or(0.0)))
+            }
+            AnimatedKind::Dielectric => {
+                MaterialType::Dielectric(Dielectric::new(self.ir.sample(time).unwrap_or(1.5)))
+            }
+        }
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        state.write_u8(self.kind as u8);
+        state.write(self.albedo.interpolation().name().as_bytes());
+        state.write_usize(self.albedo.keys().len());
+        for (time, albedo) in self.albedo.keys() {
+            state.write_u32(time.to_bits());
+            vec3::fingerprint(albedo, state);
+        }
+        for track in [&self.fuzz, &self.ir] {
+            state.write(track.interpolation().name().as_bytes());
+            state.write_usize(track.keys().len());
+            for (time, value) in track.keys() {
+                state.write_u32(time.to_bits());
+                state.write_u32(value.to_bits());
+            }
+        }
+    }
+}
+
+impl Coated {
+    pub fn new(base: MaterialType) -> Self {
+        Self {
+            base,
+            ior: 1.5,
+            roughness: 0.0,
+        }
+    }
+
+    pub fn with_ior(mut self, ior: f32) -> Self {
+        self.ior = ior;
+        self
+    }
+
+    pub fn with_roughness(mut self, roughness: f32) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    // The coat only covers the outside, from inside of the base there is just the base.
+    fn covers(&self, rec: &HitRecord, wo: Vec3) -> bool {
+        rec.front_face && wo[2] > 0.0
+    }
+}
+
+impl Principled {
+    pub fn new(base_color: impl Into<TextureType>) -> Self {
+        Self {
+            base_color: base_color.into(),
+            metallic: 0.0.into(),
+            roughness: 0.5.into(),
+            specular: 0.5.into(),
+            transmission: 0.0.into(),
+            ior: 1.5,
+            clearcoat: 0.0.into(),
+            clearcoat_roughness: 0.03.into(),
+            sheen: 0.0.into(),
+            sheen_tint: 0.5.into(),
+        }
+    }
+
+    pub fn with_metallic(mut self, metallic: impl Into<TextureType>) -> Self {
+        self.metallic = metallic.into();
+        self
+    }
+
+    pub fn with_roughness(mut self, roughness: impl Into<TextureType>) -> Self {
+        self.roughness = roughness.into();
+        self
+    }
+
+    pub fn with_specular(mut self, specular: impl Into<TextureType>) -> Self {
+        self.specular = specular.into();
+        self
+    }
+
+    pub fn with_transmission(mut self, transmission: impl Into<TextureType>, ior: f32) -> Self {
+        self.transmission = transmission.into();
+        self.ior = ior;
+        self
+    }
+
+    pub fn with_clearcoat(
+        mut self,
+        clearcoat: impl Into<TextureType>,
+        roughness: impl Into<TextureType>,
+    ) -> Self {
+        self.clearcoat = clearcoat.into();
+        self.clearcoat_roughness = roughness.into();
+        self
+    }
+
+    pub fn with_sheen(
+        mut self,
+        sheen: impl Into<TextureType>,
+        tint: impl Into<TextureType>,
+    ) -> Self {
+        self.sheen = sheen.into();
+        self.sheen_tint = tint.into();
+        self
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        self.base_color.fingerprint(state);
+        self.metallic.fingerprint(state);
+        self.roughness.fingerprint(state);
+        self.specular.fingerprint(state);
+        self.transmission.fingerprint(state);
+        state.write_u32(self.ior.to_bits());
+        self.clearcoat.fingerprint(state);
+        self.clearcoat_roughness.fingerprint(state);
+        self.sheen.fingerprint(state);
+        self.sheen_tint.fingerprint(state);
+    }
+
+    fn transmits(&self, rec: &HitRecord) -> bool {
+        self.transmission.scalar(rec.u, rec.v, rec.p) > 0.0
+    }
+
+    // Picks one of the lobes at random, with probabilities close to their share of the reflected
+    // energy, and samples it. The attenuation is the sample weight divided by that probability.
+    fn sample(
+        &self,
+        rec: &HitRecord,
+        wo: Vec3,
+        outside: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Vec3, Color)> {
+        let (u, v, p) = (rec.u, rec.v, rec.p);
+        let unit = |texture: &TextureType| texture.scalar(u, v, p).clamp(0.0, 1.0);
+        let base_color = self.base_color.value(u, v, p);
+        let roughness = TrowbridgeReitz::new(unit(&self.roughness));
+        let transmission = unit(&self.transmission);
+
+        // Inside of a transmitting object only the refracting interface is left.
+        if !rec.front_face && transmission > 0.0 {
+            return sample_rough_dielectric(&roughness, wo, outside / self.ior, sampler)
+                .map(|(wi, weight)| (wi, Color::new(weight, weight, weight)));
+        }
+
+        let clearcoat = unit(&self.clearcoat);
+        if clearcoat > 0.0 {
+            let probability = clearcoat * microfacet::fresnel_dielectric(wo[2], 1.5);
+            if sampler.get_1d() < probability {
+                let distribution = TrowbridgeReitz::new(unit(&self.clearcoat_roughness));
+                let (wi, wm, masking) = sample_reflection(&distribution, wo, sampler)?;
+                let weight = clearcoat * microfacet::fresnel_dielectric(vec3::dot(wo, wm), 1.5)
+                    / probability
+                    * masking;
+                return Some((wi, Color::new(weight, weight, weight)));
+            }
+        }
+
+        if sampler.get_1d() < unit(&self.metallic) {
+            let (wi, wm, masking) = sample_reflection(&roughness, wo, sampler)?;
+            return Some((wi, schlick(base_color, vec3::dot(wo, wm)) * masking));
+        }
+
+        if sampler.get_1d() < transmission {
+            return sample_rough_dielectric(&roughness, wo, self.ior / outside, sampler).map(
+                |(wi, weight)| {
+                    // Only the transmitted light takes the color of the base.
+                    let tint = if wi[2] < 0.0 {
+                        base_color
+                    } else {
+                        Color::new(1.0, 1.0, 1.0)
+                    };
+                    (wi, weight * tint)
+                },
+            );
+        }
+
+        let f0 = 0.08 * unit(&self.specular);
+        let probability = schlick(Color::new(f0, f0, f0), wo[2])[0];
+        if sampler.get_1d() < probability {
+            let (wi, wm, masking) = sample_reflection(&roughness, wo, sampler)?;
+            let weight = schlick(Color::new(f0, f0, f0), vec3::dot(wo, wm))[0] / probability;
+            return Some((wi, Color::new(1.0, 1.0, 1.0) * (weight * masking)));
+        }
+
+        // Diffuse, with the Fresnel term of the specular layer spent on choosing the lobe.
+        let wi = vec3::sample_cosine_hemisphere(sampler.get_2d());
+        // The sheen lobe isn't divided by pi, the cosine weighted pdf leaves a pi here.
+        let weight = base_color + PI * self.sheen(rec, base_color, wo, wi);
+        Some((wi, weight))
+    }
+
+    // The BSDF times the cosine of the lobes `sample` picks from, each scaled by the chance of
+    // getting to it. Only reflection is evaluated, light doesn't reach through transmission.
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3, outside: f32) -> Color {
+        let (u, v, p) = (rec.u, rec.v, rec.p);
+        let unit = |texture: &TextureType| texture.scalar(u, v, p).clamp(0.0, 1.0);
+        let transmission = unit(&self.transmission);
+        if wi[2] <= 0.0 || (!rec.front_face && transmission > 0.0) {
+            return Color::default();
+        }
+        let base_color = self.base_color.value(u, v, p);
+        let roughness = TrowbridgeReitz::new(unit(&self.roughness));
+        let wm = vec3::unit_vector(wo + wi);
+        let cos_m = vec3::dot(wo, wm);
+        let lobe = reflection_lobe(&roughness, wo, wi);
+        let mut f = Color::default();
+        // Chance of getting past the lobes before.
+        let mut remaining = 1.0;
+
+        let clearcoat = unit(&self.clearcoat);
+        if clearcoat > 0.0 {
+            let distribution = TrowbridgeReitz::new(unit(&self.clearcoat_roughness));
+            let weight = clearcoat
+                * microfacet::fresnel_dielectric(cos_m, 1.5)
+                * reflection_lobe(&distribution, wo, wi);
+            f += Color::new(weight, weight, weight);
+            remaining *= 1.0 - clearcoat * microfacet::fresnel_dielectric(wo[2], 1.5);
+        }
+
+        let metallic = unit(&self.metallic);
+        f += remaining * metallic * lobe * schlick(base_color, cos_m);
+        remaining *= 1.0 - metallic;
+
+        let weight = remaining
+            * transmission
+            * lobe
+            * microfacet::fresnel_dielectric(cos_m, self.ior / outside);
+        f += Color::new(weight, weight, weight);
+        remaining *= 1.0 - transmission;
+
+        let f0 = 0.08 * unit(&self.specular);
+        let weight = remaining * lobe * schlick(Color::new(f0, f0, f0), cos_m)[0];
+        f += Color::new(weight, weight, weight);
+        remaining *= 1.0 - schlick(Color::new(f0, f0, f0), wo[2])[0];
+
+        f += remaining * wi[2] * (base_color / PI + self.sheen(rec, base_color, wo, wi));
+        f
+    }
+
+    // Density of `sample` picking `wi`, the lobes of `eval` weighted by their chances.
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3, outside: f32) -> f32 {
+        let (u, v, p) = (rec.u, rec.v, rec.p);
+        let unit = |texture: &TextureType| texture.scalar(u, v, p).clamp(0.0, 1.0);
+        let transmission = unit(&self.transmission);
+        if wi[2] <= 0.0 || (!rec.front_face && transmission > 0.0) {
+            return 0.0;
+        }
+        let roughness = TrowbridgeReitz::new(unit(&self.roughness));
+        let lobe = reflection_pdf(&roughness, wo, wi);
+        let mut pdf = 0.0;
+        let mut remaining = 1.0;
+
+        let clearcoat = unit(&self.clearcoat);
+        if clearcoat > 0.0 {
+            let probability = clearcoat * microfacet::fresnel_dielectric(wo[2], 1.5);
+            let distribution = TrowbridgeReitz::new(unit(&self.clearcoat_roughness));
+            pdf += probability * reflection_pdf(&distribution, wo, wi);
+            remaining *= 1.0 - probability;
+        }
+
+        let metallic = unit(&self.metallic);
+        pdf += remaining * metallic * lobe;
+        remaining *= 1.0 - metallic;
+
+        let wm = vec3::unit_vector(wo + wi);
+        let reflectance = microfacet::fresnel_dielectric(vec3::dot(wo, wm), self.ior / outside);
+        pdf += remaining * transmission * reflectance * lobe;
+        remaining *= 1.0 - transmission;
+
+        let f0 = 0.08 * unit(&self.specular);
+        let specular = schlick(Color::new(f0, f0, f0), wo[2])[0];
+        pdf += remaining * (specular * lobe + (1.0 - specular) * wi[2] / PI);
+        pdf
+    }
+
+    // The sheen lobe at grazing angles, for cloth like materials.
+    fn sheen(&self, rec: &HitRecord, base_color: Color, wo: Vec3, wi: Vec3) -> Color {
+        let (u, v, p) = (rec.u, rec.v, rec.p);
+        let unit = |texture: &TextureType| texture.scalar(u, v, p).clamp(0.0, 1.0);
+        let sheen = unit(&self.sheen);
+        if sheen <= 0.0 {
+            return Color::default();
+        }
+        let tint = if luminance(base_color) > 0.0 {
+            base_color / luminance(base_color)
+        } else {
+            Color::new(1.0, 1.0, 1.0)
+        };
+        let sheen_tint = unit(&self.sheen_tint);
+        let sheen_color = (1.0 - sheen_tint) * Color::new(1.0, 1.0, 1.0) + sheen_tint * tint;
+        let wh = vec3::unit_vector(wo + wi);
+        let grazing = (1.0 - vec3::dot(wi, wh)).clamp(0.0, 1.0).powi(5);
+        sheen * grazing * sheen_color
     }
 }
 
+// Schlick's approximation of the Fresnel reflectance with the given reflectance at normal
+// incidence.
+#[inline]
+fn schlick(f0: Color, cos_theta: f32) -> Color {
+    let weight = (1.0 - cos_theta).clamp(0.0, 1.0).powi(5);
+    f0 + weight * (Color::new(1.0, 1.0, 1.0) - f0)
+}
+
+// The microfacet BRDF times the cosine of `wi` without its Fresnel term, D * G / (4 cos_o).
+fn reflection_lobe(distribution: &TrowbridgeReitz, wo: Vec3, wi: Vec3) -> f32 {
+    if wo[2] <= 0.0 || wi[2] <= 0.0 {
+        return 0.0;
+    }
+    let wm = vec3::unit_vector(wo + wi);
+    distribution.d(wm) * distribution.g(wo, wi) / (4.0 * wo[2])
+}
+
+// Density of reflecting `wo` into `wi` on a sampled visible microfacet normal.
+fn reflection_pdf(distribution: &TrowbridgeReitz, wo: Vec3, wi: Vec3) -> f32 {
+    if wo[2] <= 0.0 || wi[2] <= 0.0 {
+        return 0.0;
+    }
+    let wm = vec3::unit_vector(wo + wi);
+    distribution.visible_d(wo, wm) / (4.0 * vec3::dot(wo, wm))
+}
+
+// Samples a visible microfacet normal and reflects `wo` on it. Returns the reflected direction,
+// the microfacet normal and the masking part G2 / G1 of the sample weight.
+fn sample_reflection(
+    distribution: &TrowbridgeReitz,
+    wo: Vec3,
+    sampler: &mut dyn Sampler,
+) -> Option<(Vec3, Vec3, f32)> {
+    let wm = distribution.sample_wm(wo, sampler.get_2d());
+    let wi = vec3::reflect(-wo, wm);
+    if wi[2] <= 0.0 {
+        return None;
+    }
+    Some((wi, wm, distribution.g(wo, wi) / distribution.g1(wo)))
+}
+
+// The index of refraction on the other side of a dielectric surface, air or the dielectric around
+// this one.
+fn outside_ir(r_in: &Ray, rec: &HitRecord) -> f32 {
+    r_in.media()
+        .outside(rec.object)
+        .map_or(1.0, |medium| medium.ir)
+}
+
+// Samples reflection or refraction through a rough dielectric interface, chosen by the Fresnel
+// term, with `eta` the relative index of refraction. Returns the direction and its weight.
+fn sample_rough_dielectric(
+    distribution: &TrowbridgeReitz,
+    wo: Vec3,
+    eta: f32,
+    sampler: &mut dyn Sampler,
+) -> Option<(Vec3, f32)> {
+    let wm = distribution.sample_wm(wo, sampler.get_2d());
+    let reflectance = microfacet::fresnel_dielectric(vec3::dot(wo, wm), eta);
+
+    // Choosing by the Fresnel term leaves G2 / G1 as the weight of either.
+    let wi = if sampler.get_1d() < reflectance {
+        let wi = vec3::reflect(-wo, wm);
+        if wi[2] <= 0.0 {
+            return None;
+        }
+        wi
+    } else {
+        match microfacet::refract(wo, wm, eta) {
+            Some(wi) if wi[2] < 0.0 => wi,
+            _ => return None,
+        }
+    };
+    Some((wi, distribution.g(wo, wi) / distribution.g1(wo)))
+}
+
 impl Material for MaterialType {
     fn scatter(
         &self,
@@ -69,17 +913,21 @@ impl Material for MaterialType {
         rec: &HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool {
         match self {
             MaterialType::Lambertian(material) => {
-                let mut scatter_direction = rec.normal + vec3::random_unit_vector();
+                let mut scatter_direction = rec.normal + vec3::sample_unit_vector(sampler.get_2d());
 
                 // Catch degenerate scatter direction
                 if scatter_direction.near_zero() {
                     scatter_direction = rec.normal;
                 }
                 *scattered = Ray::new(rec.p, scatter_direction);
-                *attenuation = material.albedo;
+                *attenuation = match rec.vertex_color {
+                    Some(color) => material.albedo * color,
+                    None => material.albedo,
+                };
                 true
             }
 
@@ -87,26 +935,63 @@ impl Material for MaterialType {
                 let reflected = vec3::reflect(vec3::unit_vector(*r_in.direction()), rec.normal);
                 *scattered = Ray::new(
                     rec.p,
-                    reflected + material.fuzz * vec3::random_unit_vector(),
+                    reflected + material.fuzz * vec3::sample_unit_vector(sampler.get_2d()),
                 );
-                *attenuation = material.albedo;
+                *attenuation = match &material.film {
+                    // The metal reflects the amplitude sqrt(albedo) and turns its phase around.
+                    Some(film) => {
+                        let cos_theta =
+                            vec3::dot(-*r_in.direction(), rec.normal) / r_in.direction().length();
+                        let albedo = r_in.wavelengths().map_or(material.albedo, |wavelengths| {
+                            wavelengths.lift(material.albedo)
+                        });
+                        film.reflectance(r_in, cos_theta, 1.0, |c, _| {
+                            let amplitude = -albedo[c].max(0.0).sqrt();
+                            Some((amplitude, amplitude))
+                        })
+                    }
+                    None => material.albedo,
+                };
                 // vec3::dot(*scattered.direction(), rec.normal) >= 0.0
                 true
             }
 
             MaterialType::Dielectric(material) => {
                 *attenuation = Color::new(1.0, 1.0, 1.0);
+                let ir = material.ir(r_in);
+                let outside = outside_ir(r_in, rec);
                 let refraction_ratio = if rec.front_face {
-                    1.0 / material.ir
+                    outside / ir
                 } else {
-                    material.ir
+                    ir / outside
                 };
                 let unit_direction = vec3::unit_vector(*r_in.direction());
                 let cos_theta = vec3::dot(-unit_direction, rec.normal).min(1.0);
                 let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
-                let direction = if refraction_ratio * sin_theta > 1.0
-                    || material.reflectance(cos_theta, refraction_ratio) > random_double()
+                let direction = if let Some(film) = &material.film {
+                    let (n_i, n_t) = if rec.front_face {
+                        (outside, ir)
+                    } else {
+                        (ir, outside)
+                    };
+                    // `ir` is the same at every wavelength, or a dispersive base has left only
+                    // the one it's at.
+                    let reflectance = film.reflectance(r_in, cos_theta, n_i, |_, cos_theta| {
+                        microfacet::fresnel_amplitudes(cos_theta, film.ir, n_t)
+                    });
+                    // Reflect or refract by the mean over the colors, weighting each color by
+                    // its share.
+                    let probability = (reflectance[0] + reflectance[1] + reflectance[2]) / 3.0;
+                    if sampler.get_1d() < probability {
+                        *attenuation = reflectance / probability;
+                        vec3::reflect(unit_direction, rec.normal)
+                    } else {
+                        *attenuation = (*attenuation - reflectance) / (1.0 - probability);
+                        vec3::refract(unit_direction, rec.normal, refraction_ratio)
+                    }
+                } else if refraction_ratio * sin_theta > 1.0
+                    || material.reflectance(cos_theta, refraction_ratio) > sampler.get_1d()
                 {
                     vec3::reflect(unit_direction, rec.normal)
                 } else {
@@ -116,6 +1001,621 @@ impl Material for MaterialType {
                 *scattered = Ray::new(rec.p, direction);
                 true
             }
+
+            MaterialType::Conductor(material) => {
+                // The hit record normal always faces the incoming ray.
+                let frame = Onb::from_w(rec.normal);
+                let wo = frame.to_local(-vec3::unit_vector(*r_in.direction()));
+                if wo[2] <= 0.0 {
+                    return false;
+                }
+                let distribution = TrowbridgeReitz::new(material.roughness);
+                let wm = distribution.sample_wm(wo, sampler.get_2d());
+                let wi = vec3::reflect(-wo, wm);
+                if wi[2] <= 0.0 {
+                    return false;
+                }
+
+                // Sampling visible normals leaves F * G2 / G1 as the weight of the sample.
+                *attenuation = material.fresnel(vec3::dot(wo, wm))
+                    * (distribution.g(wo, wi) / distribution.g1(wo));
+                *scattered = Ray::new(rec.p, frame.local(wi));
+                true
+            }
+
+            MaterialType::RoughDielectric(material) => {
+                let frame = Onb::from_w(rec.normal);
+                let wo = frame.to_local(-vec3::unit_vector(*r_in.direction()));
+                if wo[2] <= 0.0 {
+                    return false;
+                }
+                let eta = material.eta(r_in, rec);
+                let distribution = TrowbridgeReitz::new(material.roughness);
+                let Some((wi, masking)) = sample_rough_dielectric(&distribution, wo, eta, sampler)
+                else {
+                    return false;
+                };
+                *attenuation = Color::new(masking, masking, masking);
+                *scattered = Ray::new(rec.p, frame.local(wi));
+                true
+            }
+
+            MaterialType::Principled(material) => {
+                let frame = Onb::from_w(rec.normal);
+                let wo = frame.to_local(-vec3::unit_vector(*r_in.direction()));
+                if wo[2] <= 0.0 {
+                    return false;
+                }
+                let Some((wi, weight)) = material.sample(rec, wo, outside_ir(r_in, rec), sampler)
+                else {
+                    return false;
+                };
+                *attenuation = weight;
+                *scattered = Ray::new(rec.p, frame.local(wi));
+                true
+            }
+
+            MaterialType::Animated(material) => {
+                material
+                    .at(r_in.time())
+                    .scatter(r_in, rec, attenuation, scattered, sampler)
+            }
+
+            MaterialType::Coated(material) => {
+                let frame = Onb::from_w(rec.normal);
+                let wo = frame.to_local(-vec3::unit_vector(*r_in.direction()));
+                if !material.covers(rec, wo) {
+                    return material
+                        .base
+                        .scatter(r_in, rec, attenuation, scattered, sampler);
+                }
+                let reflectance = microfacet::fresnel_dielectric(wo[2], material.ior);
+                if sampler.get_1d() < reflectance {
+                    let distribution = TrowbridgeReitz::new(material.roughness);
+                    let Some((wi, wm, masking)) = sample_reflection(&distribution, wo, sampler)
+                    else {
+                        return false;
+                    };
+                    // Chosen by the Fresnel term of the surface rather than of the microfacet.
+                    let weight = microfacet::fresnel_dielectric(vec3::dot(wo, wm), material.ior)
+                        / reflectance
+                        * masking;
+                    *attenuation = Color::new(weight, weight, weight);
+                    *scattered = Ray::new(rec.p, frame.local(wi));
+                    return true;
+                }
+                if !material
+                    .base
+                    .scatter(r_in, rec, attenuation, scattered, sampler)
+                {
+                    return false;
+                }
+                let cos_theta =
+                    vec3::dot(*scattered.direction(), rec.normal) / scattered.direction().length();
+                if cos_theta > 0.0 {
+                    *attenuation *= 1.0 - microfacet::fresnel_dielectric(cos_theta, material.ior);
+                }
+                true
+            }
+        }
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local(-vec3::unit_vector(*r_in.direction()));
+        let wi = frame.to_local(wi);
+        match self {
+            MaterialType::Lambertian(material) => {
+                if wi[2] <= 0.0 {
+                    return Color::default();
+                }
+                let albedo = match rec.vertex_color {
+                    Some(color) => material.albedo * color,
+                    None => material.albedo,
+                };
+                albedo * (wi[2] / PI)
+            }
+
+            MaterialType::Metal(_) | MaterialType::Dielectric(_) => Color::default(),
+
+            MaterialType::Conductor(material) => {
+                let distribution = TrowbridgeReitz::new(material.roughness);
+                let wm = vec3::unit_vector(wo + wi);
+                material.fresnel(vec3::dot(wo, wm)) * reflection_lobe(&distribution, wo, wi)
+            }
+
+            MaterialType::RoughDielectric(material) => {
+                // Only the reflection, the shadow ray stops at the surface on the other side.
+                let eta = material.eta(r_in, rec);
+                let distribution = TrowbridgeReitz::new(material.roughness);
+                let wm = vec3::unit_vector(wo + wi);
+                let weight = microfacet::fresnel_dielectric(vec3::dot(wo, wm), eta)
+                    * reflection_lobe(&distribution, wo, wi);
+                Color::new(weight, weight, weight)
+            }
+
+            MaterialType::Principled(material) => {
+                if wo[2] <= 0.0 {
+                    return Color::default();
+                }
+                material.eval(rec, wo, wi, outside_ir(r_in, rec))
+            }
+
+            MaterialType::Animated(material) => {
+                material.at(r_in.time()).eval(r_in, rec, frame.local(wi))
+            }
+
+            MaterialType::Coated(material) => {
+                let base = material.base.eval(r_in, rec, frame.local(wi));
+                if !material.covers(rec, wo) {
+                    return base;
+                }
+                // The coat over a specular base is left to scattering as well.
+                if material.base.specular() || wi[2] <= 0.0 {
+                    return Color::default();
+                }
+                let distribution = TrowbridgeReitz::new(material.roughness);
+                let wm = vec3::unit_vector(wo + wi);
+                let coat = microfacet::fresnel_dielectric(vec3::dot(wo, wm), material.ior)
+                    * reflection_lobe(&distribution, wo, wi);
+                let through = (1.0 - microfacet::fresnel_dielectric(wo[2], material.ior))
+                    * (1.0 - microfacet::fresnel_dielectric(wi[2], material.ior));
+                Color::new(coat, coat, coat) + through * base
+            }
+        }
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> f32 {
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local(-vec3::unit_vector(*r_in.direction()));
+        let wi = frame.to_local(wi);
+        match self {
+            MaterialType::Lambertian(_) => wi[2].max(0.0) / PI,
+
+            MaterialType::Metal(_) | MaterialType::Dielectric(_) => 0.0,
+
+            MaterialType::Conductor(material) => {
+                reflection_pdf(&TrowbridgeReitz::new(material.roughness), wo, wi)
+            }
+
+            MaterialType::RoughDielectric(material) => {
+                let eta = material.eta(r_in, rec);
+                let wm = vec3::unit_vector(wo + wi);
+                microfacet::fresnel_dielectric(vec3::dot(wo, wm), eta)
+                    * reflection_pdf(&TrowbridgeReitz::new(material.roughness), wo, wi)
+            }
+
+            MaterialType::Principled(material) => {
+                if wo[2] <= 0.0 {
+                    return 0.0;
+                }
+                material.pdf(rec, wo, wi, outside_ir(r_in, rec))
+            }
+
+            MaterialType::Animated(material) => {
+                material.at(r_in.time()).pdf(r_in, rec, frame.local(wi))
+            }
+
+            MaterialType::Coated(material) => {
+                let base = material.base.pdf(r_in, rec, frame.local(wi));
+                if !material.covers(rec, wo) {
+                    return base;
+                }
+                if material.base.specular() {
+                    return 0.0;
+                }
+                let reflectance = microfacet::fresnel_dielectric(wo[2], material.ior);
+                let distribution = TrowbridgeReitz::new(material.roughness);
+                reflectance * reflection_pdf(&distribution, wo, wi) + (1.0 - reflectance) * base
+            }
+        }
+    }
+
+    fn dispersive(&self) -> bool {
+        match self {
+            MaterialType::Dielectric(material) => material.ir.is_dispersive(),
+            MaterialType::Coated(material) => material.base.dispersive(),
+            _ => false,
+        }
+    }
+
+    fn spectral(&self) -> bool {
+        match self {
+            MaterialType::Metal(material) => material.film.is_some(),
+            MaterialType::Dielectric(material) => material.film.is_some(),
+            MaterialType::Coated(material) => material.base.spectral(),
+            _ => false,
+        }
+    }
+
+    fn medium(&self, r_in: &Ray, rec: &HitRecord) -> Option<Medium> {
+        match self {
+            MaterialType::Dielectric(material) => Some(Medium::new(
+                rec.object,
+                material.priority,
+                material.ir(r_in),
+                material.absorption,
+            )),
+            MaterialType::RoughDielectric(material) => {
+                Some(Medium::new(rec.object, 0, material.ir, None))
+            }
+            // Only where it transmits, opaque surfaces don't bound a medium.
+            MaterialType::Principled(material) if material.transmits(rec) => {
+                Some(Medium::new(rec.object, 0, material.ior, None))
+            }
+            MaterialType::Coated(material) => material.base.medium(r_in, rec),
+            MaterialType::Animated(material) if material.kind == AnimatedKind::Dielectric => {
+                material.at(r_in.time()).medium(r_in, rec)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::{Hittable, Sphere};
+    use crate::interval::Interval;
+    use crate::medium::MediumStack;
+    use crate::sampler::{SamplerKind, SamplerType};
+    use crate::spectrum::SampledWavelengths;
+    use crate::vec3::{Point3, Vec3};
+
+    use std::sync::Arc;
+
+    fn scatter_all(material: &MaterialType, front_face: bool) -> Vec<(Color, Ray)> {
+        let mut rec = HitRecord::new();
+        rec.p = Point3::new(0.0, 0.0, 0.0);
+        let r_in = Ray::new(Point3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        rec.set_face_normal(
+            &r_in,
+            Vec3::new(0.0, if front_face { 1.0 } else { -1.0 }, 0.0),
+        );
+
+        let mut sampler = SamplerType::new(SamplerKind::Sobol, 256, 1);
+        let mut scattered_rays = vec![];
+        for index in 0..256 {
+            sampler.start_pixel_sample((0, 0), index);
+            let mut attenuation = Color::default();
+            let mut scattered = Ray::default();
+            if material.scatter(&r_in, &rec, &mut attenuation, &mut scattered, &mut sampler) {
+                scattered_rays.push((attenuation, scattered));
+            }
+        }
+        scattered_rays
+    }
+
+    #[test]
+    fn test_conductor_reflects() {
+        for roughness in [0.0, 0.3, 1.0] {
+            let material = MaterialType::Conductor(Conductor::gold(roughness));
+            for (attenuation, scattered) in scatter_all(&material, true) {
+                assert!(scattered.direction()[1] > 0.0);
+                for c in 0..3 {
+                    assert!((0.0..=1.0).contains(&attenuation[c]));
+                }
+                // Gold stays yellow at every angle.
+                assert!(attenuation[0] > attenuation[2]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rough_dielectric_transmits() {
+        for front_face in [true, false] {
+            let material = MaterialType::RoughDielectric(RoughDielectric::new(1.5, 0.3));
+            let scattered = scatter_all(&material, front_face);
+            // The shading normal always faces the incoming ray.
+            let reflected = scattered
+                .iter()
+                .filter(|(_, ray)| ray.direction()[1] > 0.0)
+                .count();
+            for (attenuation, _) in &scattered {
+                assert!((0.0..=1.0).contains(&attenuation[0]));
+            }
+            if front_face {
+                // Mostly transmitted when entering glass at 45 degrees.
+                assert!(reflected < scattered.len() / 4);
+            } else {
+                // And mostly totally reflected when leaving it at that angle.
+                assert!(reflected > scattered.len() * 3 / 4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_eval_matches_scatter() {
+        // The reflectance is the mean weight of the sampled directions, and the integral of the
+        // evaluated BSDF over the hemisphere. The density of the samples integrates likewise.
+        let materials = [
+            MaterialType::Lambertian(Lambertian::new(Color::new(0.8, 0.5, 0.2))),
+            MaterialType::Conductor(Conductor::copper(0.5)),
+            MaterialType::Principled(Box::new(
+                Principled::new(Color::new(0.8, 0.5, 0.2))
+                    .with_roughness(0.6)
+                    .with_clearcoat(1.0, 0.4)
+                    .with_sheen(1.0, 0.5),
+            )),
+            MaterialType::Coated(Box::new(
+                Coated::new(MaterialType::Lambertian(Lambertian::new(Color::new(
+                    0.8, 0.5, 0.2,
+                ))))
+                .with_roughness(0.4),
+            )),
+        ];
+        let mut rec = HitRecord::new();
+        let r_in = Ray::new(Point3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        rec.set_face_normal(&r_in, Vec3::new(0.0, 1.0, 0.0));
+        for material in materials {
+            let scattered = scatter_all(&material, true);
+            let sampled = scattered
+                .iter()
+                .fold(Color::default(), |sum, (attenuation, _)| sum + *attenuation)
+                / 256.0;
+
+            // Midpoint rule over the hemisphere, uniform in the cosine and the azimuth.
+            const STEPS: usize = 256;
+            let mut evaluated = Color::default();
+            let mut density = 0.0;
+            for i in 0..STEPS {
+                for j in 0..STEPS {
+                    let cos_theta = (i as f32 + 0.5) / STEPS as f32;
+                    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                    let phi = 2.0 * PI * (j as f32 + 0.5) / STEPS as f32;
+                    let wi = Vec3::new(phi.cos() * sin_theta, cos_theta, phi.sin() * sin_theta);
+                    evaluated += material.eval(&r_in, &rec, wi);
+                    density += material.pdf(&r_in, &rec, wi);
+                }
+            }
+            let evaluated = 2.0 * PI * evaluated / (STEPS * STEPS) as f32;
+            for c in 0..3 {
+                assert!((sampled[c] - evaluated[c]).abs() < 0.02);
+            }
+            // The density covers the directions that scatter, the rest are absorbed.
+            let density = 2.0 * PI * density / (STEPS * STEPS) as f32;
+            assert!((density - scattered.len() as f32 / 256.0).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn test_dispersive_ior() {
+        let bk7 = Ior::bk7();
+        assert!((bk7.at(Ior::D_LINE) - 1.5168).abs() < 1e-3);
+        assert!(bk7.at(450.0) > bk7.at(650.0));
+        let cauchy = Ior::Cauchy {
+            a: 1.5046,
+            b: 0.0042,
+        };
+        assert!((cauchy.at(Ior::D_LINE) - bk7.at(Ior::D_LINE)).abs() < 0.01);
+        assert!(bk7.is_dispersive() && !Ior::from(1.5).is_dispersive());
+
+        // The Abbe number of the glasses, and of the formula made from it.
+        let abbe_number =
+            |ior: Ior| (ior.at(Ior::D_LINE) - 1.0) / (ior.at(Ior::F_LINE) - ior.at(Ior::C_LINE));
+        assert!((abbe_number(Ior::diamond()) - 55.3).abs() < 0.5);
+        assert!((abbe_number(Ior::sf11()) - 25.7).abs() < 0.5);
+        let flint = Ior::abbe(1.62, 36.0);
+        assert!((flint.at(Ior::D_LINE) - 1.62).abs() < 1e-4);
+        assert!((abbe_number(flint) - 36.0).abs() < 0.1);
+
+        // The ray's hero wavelength picks the index, bending blue light more than red.
+        let mut rec = HitRecord::new();
+        let r_in = Ray::new(Point3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        rec.set_face_normal(&r_in, Vec3::new(0.0, 1.0, 0.0));
+        let glass = Dielectric::new(bk7);
+        let at = |wavelength: f32| {
+            let wavelengths = (0..256)
+                .map(|k| SampledWavelengths::sample_visible(k as f32 / 256.0))
+                .find(|w| (w.hero() - wavelength).abs() < 10.0)
+                .unwrap();
+            glass.ir(&r_in.with_wavelengths(Some(wavelengths)))
+        };
+        assert!(at(450.0) > at(650.0));
+        assert_eq!(glass.ir(&r_in), bk7.at(Ior::D_LINE));
+        // RGB rays narrowed to a channel take its wavelength.
+        let blue = glass.ir(&r_in.with_channel(Some(2)));
+        let red = glass.ir(&r_in.with_channel(Some(0)));
+        assert!(blue > red);
+    }
+
+    #[test]
+    fn test_absorption() {
+        let material = MaterialType::Dielectric(
+            Dielectric::new(1.5).with_absorption(Color::new(0.8, 0.5, 0.2), 2.0),
+        );
+        // White light turns into the color after the distance, and further on keeps darkening.
+        let medium = material.medium(&Ray::default(), &HitRecord::new()).unwrap();
+        assert!((medium.transmittance(2.0) - Color::new(0.8, 0.5, 0.2)).length() < 1e-5);
+        assert!((medium.transmittance(4.0) - Color::new(0.64, 0.25, 0.04)).length() < 1e-5);
+        let clear = MaterialType::Dielectric(Dielectric::new(1.5))
+            .medium(&Ray::default(), &HitRecord::new())
+            .unwrap();
+        assert_eq!(clear.transmittance(4.0), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_thin_film() {
+        // A soap bubble, the film doesn't absorb anything and reflects some colors more.
+        let bubble = MaterialType::Dielectric(
+            Dielectric::new(1.0).with_thin_film(ThinFilm::new(400.0, 1.33)),
+        );
+        let scattered = scatter_all(&bubble, true);
+        let total = scattered
+            .iter()
+            .fold(Color::default(), |sum, (attenuation, _)| sum + *attenuation)
+            / scattered.len() as f32;
+        assert!((total - Color::new(1.0, 1.0, 1.0)).length() < 0.05);
+        let film = ThinFilm::new(400.0, 1.33);
+        let base = |_, cos_theta| microfacet::fresnel_amplitudes(cos_theta, 1.33, 1.0);
+        let reflectance = film.reflectance(&Ray::default(), 1.0, 1.0, base);
+        assert!(reflectance[0].max(reflectance[2]) > 2.0 * reflectance[1]);
+
+        // Spectral rays get it at their own wavelengths.
+        let wavelengths = SampledWavelengths::sample_visible(0.3);
+        let r_in = Ray::default().with_wavelengths(Some(wavelengths));
+        let reflectance = film.reflectance(&r_in, 1.0, 1.0, base);
+        for (i, wavelength) in wavelengths.lambda().into_iter().enumerate() {
+            let expected =
+                microfacet::fresnel_thin_film(1.0, 1.0, 1.33, 400.0, wavelength, |cos_theta| {
+                    base(i, cos_theta)
+                });
+            assert!((reflectance[i] - expected).abs() < 1e-6);
+        }
+
+        // A film matching the air leaves the metal as it was.
+        let albedo = Color::new(0.9, 0.6, 0.3);
+        let metal =
+            MaterialType::Metal(Metal::new(albedo, 0.0).with_thin_film(ThinFilm::new(300.0, 1.0)));
+        for (attenuation, _) in scatter_all(&metal, true) {
+            assert!((attenuation - albedo).length() < 1e-5);
+        }
+        assert!(metal.spectral() && !MaterialType::Metal(Metal::new(albedo, 0.0)).spectral());
+        let r_in = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0))
+            .with_wavelengths(Some(wavelengths));
+        let mut rec = HitRecord::new();
+        rec.set_face_normal(&r_in, Vec3::new(0.0, 1.0, 0.0));
+        let (mut attenuation, mut scattered) = (Color::default(), Ray::default());
+        let mut sampler = SamplerType::new(SamplerKind::Independent, 1, 0);
+        sampler.start_pixel_sample((0, 0), 0);
+        assert!(metal.scatter(&r_in, &rec, &mut attenuation, &mut scattered, &mut sampler));
+        assert!((attenuation - wavelengths.lift(albedo)).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_nested_dielectrics() {
+        let water = MaterialType::Dielectric(Dielectric::new(1.33));
+        let medium = |material: &MaterialType, object| {
+            let mut rec = HitRecord::new();
+            rec.object = object;
+            material.medium(&Ray::default(), &rec).unwrap()
+        };
+        // Leaving the glass at 45 degrees, all of it reflects back into air but some gets
+        // through into water.
+        let refracted = |glass: &MaterialType, media: MediumStack| {
+            let r_in =
+                Ray::new(Point3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0)).with_media(media);
+            let mut rec = HitRecord::new();
+            rec.set_face_normal(&r_in, Vec3::new(0.0, -1.0, 0.0));
+            rec.object = 1;
+            let mut sampler = SamplerType::new(SamplerKind::Sobol, 64, 1);
+            (0..64)
+                .filter(|&index| {
+                    sampler.start_pixel_sample((0, 0), index);
+                    let mut attenuation = Color::default();
+                    let mut scattered = Ray::default();
+                    glass.scatter(&r_in, &rec, &mut attenuation, &mut scattered, &mut sampler)
+                        && scattered.direction()[1] < 0.0
+                })
+                .count()
+        };
+        for glass in [
+            MaterialType::Dielectric(Dielectric::new(1.5).with_priority(1)),
+            MaterialType::RoughDielectric(RoughDielectric::new(1.5, 0.01)),
+            MaterialType::Principled(Box::new(
+                Principled::new(Color::new(1.0, 1.0, 1.0))
+                    .with_roughness(0.01)
+                    .with_transmission(1.0, 1.5),
+            )),
+        ] {
+            let in_glass = MediumStack::default().enter(medium(&glass, 1));
+            assert_eq!(refracted(&glass, in_glass), 0);
+            assert!(refracted(&glass, in_glass.enter(medium(&water, 2))) > 48);
+        }
+    }
+
+    #[test]
+    fn test_media_of_objects() {
+        // Two overlapping balls of one glass are two media, the ray is still in the second one
+        // after leaving the first.
+        let glass = Arc::new(MaterialType::Dielectric(Dielectric::new(1.5)));
+        let first = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, glass.clone());
+        let second = Sphere::new(Point3::new(1.0, 0.0, 0.0), 1.0, glass.clone());
+        let r_in = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let ray_t = Interval::new(0.001, f32::INFINITY);
+        let (mut into_first, mut into_second) = (HitRecord::new(), HitRecord::new());
+        assert!(first.hit(&r_in, ray_t, &mut into_first));
+        assert!(second.hit(&r_in, ray_t, &mut into_second));
+        let first = glass.medium(&r_in, &into_first).unwrap();
+        let second = glass.medium(&r_in, &into_second).unwrap();
+        assert_ne!(first.id, second.id);
+        let media = MediumStack::default().enter(first).enter(second);
+        assert_eq!(media.leave(first.id).top(), Some(&second));
+
+        // Animated dielectrics are media too, of the index at the time of the ray.
+        let animated = MaterialType::Animated(Box::new(
+            Animated::new(AnimatedKind::Dielectric, Interpolation::Linear)
+                .with_ir(0.0, 1.3)
+                .with_ir(1.0, 1.7),
+        ));
+        let medium = animated.medium(&r_in.with_time(0.5), &into_first).unwrap();
+        assert_eq!(medium.id, first.id);
+        assert!((medium.ir - 1.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_animated_parameters() {
+        let material = Animated::new(AnimatedKind::Metal, Interpolation::Linear)
+            .with_albedo(0.0, Color::new(1.0, 0.0, 0.0))
+            .with_albedo(2.0, Color::new(0.0, 0.0, 1.0))
+            .with_fuzz(1.0, 0.5);
+        let MaterialType::Metal(metal) = material.at(1.0) else {
+            panic!("an animated metal stays metal");
+        };
+        assert_eq!(metal.albedo, Color::new(0.5, 0.0, 0.5));
+        assert_eq!(metal.fuzz, 0.5);
+
+        // Scattering picks the parameters at the time of the incoming ray.
+        let mut rec = HitRecord::new();
+        rec.set_face_normal(
+            &Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        let mut sampler = SamplerType::new(SamplerKind::Sobol, 1, 1);
+        sampler.start_pixel_sample((0, 0), 0);
+        let material = MaterialType::Animated(Box::new(material));
+        let mut attenuation = Color::default();
+        let mut scattered = Ray::default();
+        let r_in = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).with_time(2.0);
+        material.scatter(&r_in, &rec, &mut attenuation, &mut scattered, &mut sampler);
+        assert_eq!(attenuation, Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_principled_lobes() {
+        let base_color = Color::new(0.8, 0.4, 0.2);
+        let materials = [
+            Principled::new(base_color),
+            Principled::new(base_color)
+                .with_metallic(1.0)
+                .with_roughness(0.2),
+            Principled::new(base_color).with_clearcoat(1.0, 0.1),
+            Principled::new(base_color).with_sheen(1.0, 1.0),
+        ];
+        for material in materials {
+            let material = MaterialType::Principled(Box::new(material));
+            let scattered = scatter_all(&material, true);
+            assert!(scattered.len() > 200);
+            for (attenuation, ray) in scattered {
+                // Opaque, everything is reflected.
+                assert!(ray.direction()[1] > 0.0);
+                assert!(attenuation[0] >= 0.0 && attenuation[0].is_finite());
+            }
+        }
+
+        // Fully transmitting, most of the light passes through.
+        let glass = MaterialType::Principled(Box::new(
+            Principled::new(base_color).with_transmission(1.0, 1.5),
+        ));
+        let scattered = scatter_all(&glass, true);
+        let transmitted: Vec<_> = scattered
+            .iter()
+            .filter(|(_, ray)| ray.direction()[1] < 0.0)
+            .collect();
+        assert!(transmitted.len() > scattered.len() * 3 / 4);
+        for (attenuation, _) in transmitted {
+            // Tinted by the base color.
+            assert!(attenuation[0] > attenuation[2]);
         }
     }
 }
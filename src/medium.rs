@@ -0,0 +1,184 @@
+// The media a ray travels through, for nested dielectrics like a liquid in a glass (Schmidt and
+// Budge, "Simple Nested Dielectrics in Ray Traced Images"). Touching objects are modeled as
+// overlapping, the one of higher priority owns the overlap and the surfaces of the other one in
+// there don't count.
+
+use crate::color::Color;
+
+use log::warn;
+use std::sync::Once;
+
+// Deepest nesting that is tracked, rays entering more media forget the ones of lowest priority.
+const MAX_MEDIA: usize = 4;
+
+// Kept small, every ray carries a few of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Medium {
+    // Tells the media apart, the object filled with it, see `HitRecord::object`.
+    pub id: usize,
+    pub priority: i32,
+    // Index of refraction at the wavelength of the ray that entered.
+    pub ir: f32,
+    // Beer-Lambert absorption coefficient per unit of length, zero when clear.
+    pub absorption: Color,
+}
+
+impl Medium {
+    pub fn new(object: usize, priority: i32, ir: f32, absorption: Option<Color>) -> Self {
+        Self {
+            id: object,
+            priority,
+            ir,
+            absorption: absorption.unwrap_or_default(),
+        }
+    }
+
+    // What's left of light traveling `distance` inside.
+    pub fn transmittance(&self, distance: f32) -> Color {
+        Color::new(
+            (-self.absorption[0] * distance).exp(),
+            (-self.absorption[1] * distance).exp(),
+            (-self.absorption[2] * distance).exp(),
+        )
+    }
+}
+
+// The media a ray is inside of, in the order it entered them.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MediumStack {
+    media: [Option<Medium>; MAX_MEDIA],
+}
+
+impl MediumStack {
+    // The medium the ray travels in, of the highest priority and the latest of those.
+    pub fn top(&self) -> Option<&Medium> {
+        self.highest(None)
+    }
+
+    // The medium on the other side of a surface of `id`, whether the ray enters or leaves it.
+    pub fn outside(&self, id: usize) -> Option<&Medium> {
+        self.highest(Some(id))
+    }
+
+    // A surface of `medium` is only there when nothing of higher priority surrounds it.
+    pub fn is_false_interface(&self, medium: &Medium) -> bool {
+        self.outside(medium.id)
+            .is_some_and(|outside| outside.priority > medium.priority)
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.media.iter().flatten().any(|medium| medium.id == id)
+    }
+
+    pub fn enter(mut self, medium: Medium) -> Self {
+        if self.contains(medium.id) {
+            return self;
+        }
+        if let Some(slot) = self.media.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(medium);
+            return self;
+        }
+
+        static FULL: Once = Once::new();
+        FULL.call_once(|| {
+            warn!("a ray entered more than {MAX_MEDIA} dielectrics, forgetting those of lowest priority");
+        });
+        // The oldest of the lowest priority makes room, unless the new one is lower still.
+        let (index, lowest) = self
+            .media
+            .iter()
+            .flatten()
+            .enumerate()
+            .min_by_key(|(_, m)| m.priority)
+            .expect("the stack is full");
+        if medium.priority >= lowest.priority {
+            self.media[index..].rotate_left(1);
+            self.media[MAX_MEDIA - 1] = Some(medium);
+        }
+        self
+    }
+
+    pub fn leave(mut self, id: usize) -> Self {
+        if let Some(index) = self
+            .media
+            .iter()
+            .position(|m| m.is_some_and(|m| m.id == id))
+        {
+            // Keep the order of the others.
+            self.media[index..].rotate_left(1);
+            self.media[MAX_MEDIA - 1] = None;
+        }
+        self
+    }
+
+    fn highest(&self, except: Option<usize>) -> Option<&Medium> {
+        self.media
+            .iter()
+            .flatten()
+            .filter(|medium| Some(medium.id) != except)
+            .fold(None, |best: Option<&Medium>, medium| match best {
+                Some(best) if best.priority > medium.priority => Some(best),
+                _ => Some(medium),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn medium(object: usize, priority: i32, ir: f32) -> Medium {
+        Medium::new(object, priority, ir, None)
+    }
+
+    #[test]
+    fn test_medium_stack() {
+        let (glass, water, ice) = (medium(1, 2, 1.5), medium(2, 1, 1.33), medium(3, 1, 1.31));
+        let stack = MediumStack::default();
+        assert!(stack.top().is_none());
+
+        // Into the glass, then into the water where the two overlap.
+        let stack = stack.enter(glass);
+        assert!(stack.is_false_interface(&water));
+        let stack = stack.enter(water);
+        assert_eq!(stack.top(), Some(&glass));
+        // Out of the glass wall into the water, which is on the other side of it.
+        assert_eq!(stack.outside(glass.id), Some(&water));
+        let stack = stack.leave(glass.id);
+        assert_eq!(stack.top(), Some(&water));
+        assert!(!stack.is_false_interface(&water));
+
+        // Of equal priorities the latest counts.
+        let stack = stack.enter(ice);
+        assert_eq!(stack.top(), Some(&ice));
+        assert_eq!(stack.outside(ice.id), Some(&water));
+        let stack = stack.leave(water.id).leave(ice.id);
+        assert!(stack.top().is_none());
+        // Leaving what the ray never entered changes nothing.
+        assert_eq!(stack.leave(glass.id), stack);
+
+        let tinted = Medium::new(1, 2, 1.5, Some(Color::new(0.5, 1.0, 0.0)));
+        let transmittance = tinted.transmittance(2.0);
+        assert!(
+            (transmittance - Color::new((-1.0f32).exp(), (-2.0f32).exp(), 1.0)).length() < 1e-6
+        );
+        assert_eq!(glass.transmittance(2.0), Color::new(1.0, 1.0, 1.0));
+        assert!(std::mem::size_of::<MediumStack>() <= 160);
+    }
+
+    #[test]
+    fn test_full_medium_stack() {
+        let full = (0..MAX_MEDIA).fold(MediumStack::default(), |stack, i| {
+            stack.enter(medium(i + 1, [1, 0, 2, 0][i], 1.5))
+        });
+
+        // Of the two of priority 0 the one entered first makes room.
+        let stack = full.enter(medium(10, 0, 1.33));
+        assert!(!stack.contains(2));
+        assert!(stack.contains(4) && stack.contains(10));
+        assert_eq!(stack.outside(3).map(|m| m.id), Some(1));
+
+        // Nothing goes for a medium of a priority lower than all of them.
+        assert_eq!(full.enter(medium(10, -1, 1.33)), full);
+    }
+}
@@ -0,0 +1,333 @@
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::color::Color;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::{Lambertian, MaterialType};
+use crate::ray::Ray;
+use crate::transform::Transform;
+use crate::vec3::{self, Point3, Vec3};
+
+use std::hash::Hasher;
+use std::sync::Arc;
+
+// Indexed triangle mesh. Normals, texture coordinates and colors are optional, but when present
+// there is one per position.
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f32, f32)>,
+    colors: Vec<Color>,
+    indices: Vec<[u32; 3]>,
+}
+
+impl Mesh {
+    pub fn new(positions: Vec<Point3>, indices: Vec<[u32; 3]>) -> Self {
+        Self {
+            positions,
+            indices,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_normals(mut self, normals: Vec<Vec3>) -> Self {
+        assert_eq!(normals.len(), self.positions.len());
+        self.normals = normals;
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: Vec<(f32, f32)>) -> Self {
+        assert_eq!(uvs.len(), self.positions.len());
+        self.uvs = uvs;
+        self
+    }
+
+    pub fn with_colors(mut self, colors: Vec<Color>) -> Self {
+        assert_eq!(colors.len(), self.positions.len());
+        self.colors = colors;
+        self
+    }
+
+    #[inline]
+    pub fn positions(&self) -> &[Point3] {
+        &self.positions
+    }
+
+    #[inline]
+    pub fn normals(&self) -> &[Vec3] {
+        &self.normals
+    }
+
+    #[inline]
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    #[inline]
+    pub fn indices(&self) -> &[[u32; 3]] {
+        &self.indices
+    }
+
+    #[inline]
+    pub fn has_colors(&self) -> bool {
+        !self.colors.is_empty()
+    }
+
+    // Gray diffuse material for meshes that come without one, white when the vertex colors
+    // provide the albedo.
+    pub fn default_material(&self) -> MaterialType {
+        let albedo = if self.has_colors() { 1.0 } else { 0.8 };
+        MaterialType::Lambertian(Lambertian::new(Color::new(albedo, albedo, albedo)))
+    }
+
+    #[inline]
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    // Moves the mesh into the space of `transform`, keeping the triangles facing outwards when it
+    // mirrors.
+    pub fn transform(mut self, transform: &Transform) -> Self {
+        for p in &mut self.positions {
+            *p = transform.point(*p);
+        }
+        for n in &mut self.normals {
+            *n = transform.normal(*n);
+        }
+        if transform.determinant() < 0.0 {
+            for triangle in &mut self.indices {
+                triangle.swap(1, 2);
+            }
+        }
+        self
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        state.write_usize(self.positions.len());
+        for p in &self.positions {
+            vec3::fingerprint(p, state);
+        }
+        for n in &self.normals {
+            vec3::fingerprint(n, state);
+        }
+        for (u, v) in &self.uvs {
+            state.write_u32(u.to_bits());
+            state.write_u32(v.to_bits());
+        }
+        for color in &self.colors {
+            vec3::fingerprint(color, state);
+        }
+        for triangle in &self.indices {
+            for index in triangle {
+                state.write_u32(*index);
+            }
+        }
+    }
+}
+
+// A mesh with a single material, with its triangles in their own bounding volume hierarchy.
+pub struct TriangleMesh {
+    mesh: Arc<Mesh>,
+    material: Arc<MaterialType>,
+    bvh: Bvh,
+}
+
+impl TriangleMesh {
+    pub fn new(mesh: Mesh, material: impl Into<Arc<MaterialType>>) -> Self {
+        let mesh = Arc::new(mesh);
+        let material = material.into();
+        let triangles = (0..mesh.triangle_count())
+            .map(|index| {
+                Arc::new(Triangle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    index,
+                }) as Arc<dyn Hittable>
+            })
+            .collect();
+        Self {
+            bvh: Bvh::new(triangles),
+            mesh,
+            material,
+        }
+    }
+}
+
+impl Hittable for TriangleMesh {
+    #[inline]
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.bvh.hit(r, ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    // Hashes the shared data once rather than through every triangle.
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        state.write(b"mesh");
+        self.mesh.fingerprint(state);
+        self.material.fingerprint(state);
+    }
+}
+
+struct Triangle {
+    mesh: Arc<Mesh>,
+    material: Arc<MaterialType>,
+    index: usize,
+}
+
+impl Triangle {
+    #[inline]
+    fn vertices(&self) -> [usize; 3] {
+        self.mesh.indices[self.index].map(|i| i as usize)
+    }
+}
+
+impl Hittable for Triangle {
+    // Möller-Trumbore ray triangle intersection.
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let [i0, i1, i2] = self.vertices();
+        let positions = &self.mesh.positions;
+        let (p0, p1, p2) = (positions[i0], positions[i1], positions[i2]);
+
+        let edge1 = p1 - p0;
+        let edge2 = p2 - p0;
+        let pvec = vec3::cross(*r.direction(), edge2);
+        let det = vec3::dot(edge1, pvec);
+        if det.abs() < 1e-12 {
+            return false;
+        }
+        let inv_det = 1.0 / det;
+
+        let tvec = *r.origin() - p0;
+        let b1 = vec3::dot(tvec, pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return false;
+        }
+        let qvec = vec3::cross(tvec, edge1);
+        let b2 = vec3::dot(*r.direction(), qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return false;
+        }
+        let t = vec3::dot(edge2, qvec) * inv_det;
+        if !ray_t.surrounds(t) {
+            return false;
+        }
+        let b0 = 1.0 - b1 - b2;
+
+        rec.t = t;
+        rec.p = r.at(t);
+        let outward_normal = vec3::unit_vector(vec3::cross(edge1, edge2));
+        rec.set_face_normal(r, outward_normal);
+        if !self.mesh.normals.is_empty() {
+            // Interpolated shading normal, kept on the side of the ray like the geometric one.
+            let normals = &self.mesh.normals;
+            let shading = vec3::unit_vector(b0 * normals[i0] + b1 * normals[i1] + b2 * normals[i2]);
+            if !shading.near_zero() {
+                rec.normal = if rec.front_face { shading } else { -shading };
+            }
+        }
+        (rec.u, rec.v) = if self.mesh.uvs.is_empty() {
+            (b1, b2)
+        } else {
+            let uvs = &self.mesh.uvs;
+            (
+                b0 * uvs[i0].0 + b1 * uvs[i1].0 + b2 * uvs[i2].0,
+                b0 * uvs[i0].1 + b1 * uvs[i1].1 + b2 * uvs[i2].1,
+            )
+        };
+        rec.vertex_color = if self.mesh.colors.is_empty() {
+            None
+        } else {
+            let colors = &self.mesh.colors;
+            Some(b0 * colors[i0] + b1 * colors[i1] + b2 * colors[i2])
+        };
+        rec.material = Some(self.material.clone());
+        // The whole mesh is one object, its triangles close a single medium.
+        rec.object = self as *const Self as usize;
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [i0, i1, i2] = self.vertices();
+        let positions = &self.mesh.positions;
+        Aabb::new(positions[i0], positions[i1]).include(positions[i2])
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        state.write(b"triangle");
+        for i in self.vertices() {
+            vec3::fingerprint(&self.mesh.positions[i], state);
+        }
+        self.material.fingerprint(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+
+    fn quad() -> Mesh {
+        let positions = vec![
+            Point3::new(-1.0, -1.0, 0.0),
+            Point3::new(1.0, -1.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(-1.0, 1.0, 0.0),
+        ];
+        Mesh::new(positions, vec![[0, 1, 2], [0, 2, 3]]).with_uvs(vec![
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (1.0, 1.0),
+            (0.0, 1.0),
+        ])
+    }
+
+    #[test]
+    fn test_hit_quad() {
+        let material = MaterialType::Lambertian(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let mesh = TriangleMesh::new(quad(), material);
+        let interval = Interval::new(0.001, f32::INFINITY);
+
+        let mut rec = HitRecord::new();
+        let ray = Ray::new(Point3::new(0.5, -0.5, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(mesh.hit(&ray, interval, &mut rec));
+        assert_eq!(rec.t, 2.0);
+        assert!(rec.front_face);
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!((rec.u - 0.75).abs() < 1e-6 && (rec.v - 0.25).abs() < 1e-6);
+        assert_eq!(rec.vertex_color, None);
+
+        let ray = Ray::new(Point3::new(0.5, 1.5, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!mesh.hit(&ray, interval, &mut rec));
+    }
+
+    #[test]
+    fn test_vertex_colors() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let mesh = quad().with_colors(vec![red, red, blue, blue]);
+        let material = mesh.default_material();
+        let mesh = TriangleMesh::new(mesh, material);
+
+        let mut rec = HitRecord::new();
+        let ray = Ray::new(Point3::new(0.5, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(mesh.hit(&ray, Interval::new(0.001, f32::INFINITY), &mut rec));
+        assert_eq!(rec.vertex_color, Some(Color::new(0.5, 0.0, 0.5)));
+    }
+
+    #[test]
+    fn test_mirrored_transform_keeps_winding() {
+        let mirror = Transform::scale(Vec3::new(1.0, 1.0, -1.0));
+        let material = MaterialType::Lambertian(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let mesh = TriangleMesh::new(quad().transform(&mirror), material);
+
+        // Mirrored along z, the quad faces the other way.
+        let mut rec = HitRecord::new();
+        let ray = Ray::new(Point3::new(0.1, 0.1, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(mesh.hit(&ray, Interval::new(0.001, f32::INFINITY), &mut rec));
+        assert!(!rec.front_face);
+    }
+}
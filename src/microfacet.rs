@@ -0,0 +1,362 @@
+// Trowbridge-Reitz (GGX) microfacet distribution and the Fresnel terms used with it. All
+// directions are in the local frame of the surface, with the normal along +z.
+
+use crate::vec3::{self, Vec3};
+
+use std::f32::consts::PI;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TrowbridgeReitz {
+    alpha: f32,
+}
+
+impl TrowbridgeReitz {
+    // `roughness` is the perceptual roughness, squared into the distribution's alpha.
+    pub fn new(roughness: f32) -> Self {
+        let roughness = roughness.clamp(0.0, 1.0);
+        // Keep a tiny bit of roughness, a perfectly smooth GGX surface is a delta function.
+        Self {
+            alpha: (roughness * roughness).max(1e-4),
+        }
+    }
+
+    #[inline]
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    // Distribution of microfacet normals.
+    pub fn d(&self, wm: Vec3) -> f32 {
+        let cos2_theta = wm[2] * wm[2];
+        if cos2_theta <= 0.0 {
+            return 0.0;
+        }
+        let alpha2 = self.alpha * self.alpha;
+        let denom = cos2_theta * (alpha2 - 1.0) + 1.0;
+        alpha2 / (PI * denom * denom)
+    }
+
+    pub fn lambda(&self, w: Vec3) -> f32 {
+        let cos2_theta = w[2] * w[2];
+        if cos2_theta <= 0.0 {
+            return f32::INFINITY;
+        }
+        let tan2_theta = (1.0 - cos2_theta).max(0.0) / cos2_theta;
+        ((1.0 + self.alpha * self.alpha * tan2_theta).sqrt() - 1.0) / 2.0
+    }
+
+    // Smith masking of a single direction.
+    pub fn g1(&self, w: Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    // Height correlated masking-shadowing.
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // Density of the microfacet normals visible from `w`.
+    pub fn visible_d(&self, w: Vec3, wm: Vec3) -> f32 {
+        if w[2] == 0.0 {
+            return 0.0;
+        }
+        self.g1(w) / w[2].abs() * self.d(wm) * vec3::dot(w, wm).abs()
+    }
+
+    // Samples a microfacet normal visible from `w` (Heitz, "Sampling the GGX Distribution of
+    // Visible Normals"). `w` has to be in the upper hemisphere.
+    pub fn sample_wm(&self, w: Vec3, u: (f32, f32)) -> Vec3 {
+        // Transform to the hemisphere configuration.
+        let wh = vec3::unit_vector(Vec3::new(self.alpha * w[0], self.alpha * w[1], w[2]));
+
+        // Orthonormal basis around it.
+        let t1 = if wh[2] < 0.99999 {
+            vec3::unit_vector(vec3::cross(Vec3::new(0.0, 0.0, 1.0), wh))
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vec3::cross(wh, t1);
+
+        // Uniform point on the disk, warped to the visible part of the hemisphere.
+        let p = vec3::sample_unit_disk(u);
+        let h = (1.0 - p[0] * p[0]).max(0.0).sqrt();
+        let s = 0.5 * (1.0 + wh[2]);
+        let py = (1.0 - s) * h + s * p[1];
+        let pz = (1.0 - p[0] * p[0] - py * py).max(0.0).sqrt();
+
+        let nh = p[0] * t1 + py * t2 + pz * wh;
+        vec3::unit_vector(Vec3::new(
+            self.alpha * nh[0],
+            self.alpha * nh[1],
+            nh[2].max(1e-6),
+        ))
+    }
+}
+
+// Fresnel reflectance of a dielectric interface, `eta` is the relative index of refraction
+// (transmitted over incident side).
+pub fn fresnel_dielectric(cos_theta_i: f32, eta: f32) -> f32 {
+    let (cos_theta_i, eta) = if cos_theta_i < 0.0 {
+        (-cos_theta_i, 1.0 / eta)
+    } else {
+        (cos_theta_i, eta)
+    };
+    let cos_theta_i = cos_theta_i.min(1.0);
+
+    let sin2_theta_t = (1.0 - cos_theta_i * cos_theta_i) / (eta * eta);
+    if sin2_theta_t >= 1.0 {
+        // Total internal reflection.
+        return 1.0;
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+
+    let r_parl = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let r_perp = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+    (r_parl * r_parl + r_perp * r_perp) / 2.0
+}
+
+// Fresnel reflectance of a conductor with the complex index of refraction `eta + i k`.
+pub fn fresnel_complex(cos_theta_i: f32, eta: f32, k: f32) -> f32 {
+    let cos_theta_i = Complex::real(cos_theta_i.clamp(0.0, 1.0));
+    let eta = Complex::new(eta, k);
+
+    let sin2_theta_i = Complex::real(1.0) - cos_theta_i * cos_theta_i;
+    let sin2_theta_t = sin2_theta_i / (eta * eta);
+    let cos_theta_t = (Complex::real(1.0) - sin2_theta_t).sqrt();
+
+    let r_parl = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let r_perp = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+    (r_parl.norm() + r_perp.norm()) / 2.0
+}
+
+// Amplitudes of the s and p polarized light reflected off a dielectric interface, from the
+// indices of both sides. `None` on total internal reflection.
+pub fn fresnel_amplitudes(cos_theta_i: f32, n_i: f32, n_t: f32) -> Option<(f32, f32)> {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let sin2_theta_t = (n_i / n_t).powi(2) * (1.0 - cos_theta_i * cos_theta_i);
+    if sin2_theta_t >= 1.0 {
+        return None;
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    let r_perp = (n_i * cos_theta_i - n_t * cos_theta_t) / (n_i * cos_theta_i + n_t * cos_theta_t);
+    let r_parl = (n_t * cos_theta_i - n_i * cos_theta_t) / (n_t * cos_theta_i + n_i * cos_theta_t);
+    Some((r_perp, r_parl))
+}
+
+// Reflectance of a film `thickness` nanometers thick, of index `film`, on top of a base, for light
+// of `wavelength` nanometers coming from a medium of index `n_i` (Airy's formula). The waves
+// reflected off both sides of the film interfere. `base` gives the s and p amplitudes reflected
+// by the base from the cosine inside the film, `None` when it reflects everything.
+pub fn fresnel_thin_film(
+    cos_theta_i: f32,
+    n_i: f32,
+    film: f32,
+    thickness: f32,
+    wavelength: f32,
+    base: impl Fn(f32) -> Option<(f32, f32)>,
+) -> f32 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let Some(top) = fresnel_amplitudes(cos_theta_i, n_i, film) else {
+        return 1.0;
+    };
+    let sin2_theta_film = (n_i / film).powi(2) * (1.0 - cos_theta_i * cos_theta_i);
+    let cos_theta_film = (1.0 - sin2_theta_film).sqrt();
+    // The film is lossless, what can't leave through the base comes back out.
+    let Some(bottom) = base(cos_theta_film) else {
+        return 1.0;
+    };
+
+    // Phase difference of the round trip through the film.
+    let phase = 4.0 * PI * film * thickness * cos_theta_film / wavelength;
+    let shift = Complex::new(phase.cos(), phase.sin());
+    let reflectance = |r12: f32, r23: f32| {
+        let r23 = shift * Complex::real(r23);
+        ((Complex::real(r12) + r23) / (Complex::real(1.0) + Complex::real(r12) * r23)).norm()
+    };
+    (reflectance(top.0, bottom.0) + reflectance(top.1, bottom.1)) / 2.0
+}
+
+// Refracts `wo` through a surface with normal `n` facing towards it, `eta` is the relative index
+// of refraction. Returns `None` on total internal reflection.
+pub fn refract(wo: Vec3, n: Vec3, eta: f32) -> Option<Vec3> {
+    let cos_theta_i = vec3::dot(n, wo);
+    let sin2_theta_t = (1.0 - cos_theta_i * cos_theta_i).max(0.0) / (eta * eta);
+    if sin2_theta_t >= 1.0 {
+        return None;
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    Some(-wo / eta + (cos_theta_i / eta - cos_theta_t) * n)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Complex {
+    re: f32,
+    im: f32,
+}
+
+impl Complex {
+    fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    fn real(re: f32) -> Self {
+        Self { re, im: 0.0 }
+    }
+
+    // Squared magnitude.
+    fn norm(self) -> f32 {
+        self.re * self.re + self.im * self.im
+    }
+
+    fn sqrt(self) -> Self {
+        let n = self.norm().sqrt();
+        if n == 0.0 {
+            return Complex::real(0.0);
+        }
+        let t1 = (0.5 * (n + self.re.abs())).sqrt();
+        let t2 = 0.5 * self.im / t1;
+        if self.re >= 0.0 {
+            Complex::new(t1, t2)
+        } else {
+            Complex::new(t2.abs(), t1.copysign(self.im))
+        }
+    }
+}
+
+impl std::ops::Add for Complex {
+    type Output = Complex;
+    fn add(self, rhs: Complex) -> Complex {
+        Complex::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl std::ops::Sub for Complex {
+    type Output = Complex;
+    fn sub(self, rhs: Complex) -> Complex {
+        Complex::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl std::ops::Mul for Complex {
+    type Output = Complex;
+    fn mul(self, rhs: Complex) -> Complex {
+        Complex::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl std::ops::Div for Complex {
+    type Output = Complex;
+    fn div(self, rhs: Complex) -> Complex {
+        let scale = 1.0 / rhs.norm();
+        Complex::new(
+            scale * (self.re * rhs.re + self.im * rhs.im),
+            scale * (self.im * rhs.re - self.re * rhs.im),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fresnel() {
+        assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-6);
+        assert_eq!(fresnel_dielectric(0.1, 1.0 / 1.5), 1.0);
+        assert_eq!(fresnel_dielectric(-0.1, 1.5), 1.0);
+        for cos_theta in [0.1, 0.5, 0.9, 1.0] {
+            let dielectric = fresnel_dielectric(cos_theta, 1.5);
+            let complex = fresnel_complex(cos_theta, 1.5, 0.0);
+            assert!((dielectric - complex).abs() < 1e-5);
+        }
+        // Gold reflects red much better than blue.
+        assert!(fresnel_complex(1.0, 0.143, 3.983) > 0.9);
+        assert!(fresnel_complex(1.0, 1.442, 1.603) < 0.5);
+    }
+
+    #[test]
+    fn test_thin_film() {
+        // A film as thick as nothing, or matching the air, leaves the bare surface.
+        for cos_theta in [0.2, 0.7, 1.0] {
+            let bare = fresnel_dielectric(cos_theta, 1.5);
+            let film = fresnel_thin_film(cos_theta, 1.0, 1.33, 0.0, 550.0, |cos_theta| {
+                fresnel_amplitudes(cos_theta, 1.33, 1.5)
+            });
+            assert!((film - bare).abs() < 1e-5);
+            let film = fresnel_thin_film(cos_theta, 1.0, 1.0, 300.0, 550.0, |cos_theta| {
+                fresnel_amplitudes(cos_theta, 1.0, 1.5)
+            });
+            assert!((film - bare).abs() < 1e-5);
+        }
+
+        // A quarter wave coating of the geometric mean index cancels the reflection.
+        let n = 1.5f32.sqrt();
+        let coating = |wavelength| {
+            fresnel_thin_film(1.0, 1.0, n, 550.0 / (4.0 * n), wavelength, |cos_theta| {
+                fresnel_amplitudes(cos_theta, n, 1.5)
+            })
+        };
+        assert!(coating(550.0) < 1e-6);
+        assert!(coating(400.0) > 0.005);
+        // A soap bubble reflects the most where it's a quarter wave thick and nothing at half.
+        let soap = |thickness| {
+            fresnel_thin_film(1.0, 1.0, 1.33, thickness, 532.0, |cos_theta| {
+                fresnel_amplitudes(cos_theta, 1.33, 1.0)
+            })
+        };
+        assert!(soap(100.0) > 0.07);
+        assert!(soap(532.0 / 2.66) < 1e-6);
+    }
+
+    #[test]
+    fn test_visible_normals_integrate_to_one() {
+        // Riemann sum of the visible normal density over the hemisphere.
+        let distribution = TrowbridgeReitz::new(0.5);
+        let w = vec3::unit_vector(Vec3::new(0.4, 0.1, 0.6));
+        let (n_theta, n_phi) = (400, 400);
+        let mut integral = 0.0;
+        for i in 0..n_theta {
+            let theta = (i as f32 + 0.5) / n_theta as f32 * PI / 2.0;
+            for j in 0..n_phi {
+                let phi = (j as f32 + 0.5) / n_phi as f32 * 2.0 * PI;
+                let wm = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+                let area = theta.sin() * (PI / 2.0 / n_theta as f32) * (2.0 * PI / n_phi as f32);
+                integral += distribution.visible_d(w, wm) * area;
+            }
+        }
+        assert!((integral - 1.0).abs() < 0.01, "{}", integral);
+    }
+
+    #[test]
+    fn test_sampled_normals_are_visible() {
+        let distribution = TrowbridgeReitz::new(0.8);
+        let w = vec3::unit_vector(Vec3::new(0.9, -0.2, 0.1));
+        for i in 0..16 {
+            for j in 0..16 {
+                let u = ((i as f32 + 0.5) / 16.0, (j as f32 + 0.5) / 16.0);
+                let wm = distribution.sample_wm(w, u);
+                assert!((wm.length() - 1.0).abs() < 1e-4);
+                assert!(wm[2] > 0.0);
+                assert!(vec3::dot(w, wm) > -1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_refract() {
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        let wo = vec3::unit_vector(Vec3::new(1.0, 0.0, 1.0));
+        let wi = refract(wo, n, 1.5).unwrap();
+        // Snell's law, sin(theta_i) = eta * sin(theta_t).
+        assert!((wo[0] - 1.5 * -wi[0]).abs() < 1e-5);
+        assert!(wi[2] < 0.0);
+        assert!(refract(wo, n, 1.0 / 1.5).is_none());
+    }
+}
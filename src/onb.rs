@@ -0,0 +1,64 @@
+use crate::vec3::{self, Vec3};
+
+// Orthonormal basis around `w`, used to express directions relative to a surface normal.
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn from_w(n: Vec3) -> Self {
+        // Duff et al., "Building an Orthonormal Basis, Revisited".
+        let w = vec3::unit_vector(n);
+        let sign = 1.0f32.copysign(w[2]);
+        let a = -1.0 / (sign + w[2]);
+        let b = w[0] * w[1] * a;
+        let u = Vec3::new(1.0 + sign * w[0] * w[0] * a, sign * b, -sign * w[0]);
+        let v = Vec3::new(b, sign + w[1] * w[1] * a, -w[1]);
+        Self { u, v, w }
+    }
+
+    // From local coordinates to world space.
+    #[inline]
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        a[0] * self.u + a[1] * self.v + a[2] * self.w
+    }
+
+    // From world space to local coordinates.
+    #[inline]
+    pub fn to_local(&self, a: Vec3) -> Vec3 {
+        Vec3::new(
+            vec3::dot(a, self.u),
+            vec3::dot(a, self.v),
+            vec3::dot(a, self.w),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orthonormal() {
+        for n in [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 2.0, 3.0),
+            Vec3::new(-0.3, 0.1, -0.9),
+        ] {
+            let onb = Onb::from_w(n);
+            assert!((onb.u.length() - 1.0).abs() < 1e-5);
+            assert!((onb.v.length() - 1.0).abs() < 1e-5);
+            assert!(vec3::dot(onb.u, onb.v).abs() < 1e-5);
+            assert!(vec3::dot(onb.u, onb.w).abs() < 1e-5);
+            assert!(vec3::dot(onb.v, onb.w).abs() < 1e-5);
+
+            let a = Vec3::new(0.2, -0.5, 0.7);
+            let back = onb.to_local(onb.local(a));
+            assert!((back - a).length() < 1e-5);
+        }
+    }
+}
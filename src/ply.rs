@@ -0,0 +1,416 @@
+// Reader for Stanford PLY meshes in ASCII and binary little or big endian encoding. Vertex
+// positions are required, normals, colors and texture coordinates are read when present. Faces
+// with more than three vertices are triangulated as fans.
+
+use crate::color::Color;
+use crate::mesh::{Mesh, TriangleMesh};
+use crate::rtweekend::invalid_data;
+use crate::vec3::{Point3, Vec3};
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::SplitAsciiWhitespace;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> io::Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return Err(invalid_data(&format!("unknown PLY type `{name}`"))),
+        })
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    // Largest value of unsigned integer types, which colors are normalized by.
+    fn color_scale(&self) -> f64 {
+        match self {
+            Scalar::U8 => 255.0,
+            Scalar::U16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar(String, Scalar),
+    List(String, Scalar, Scalar),
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+// Loads a mesh with the default material of `Mesh`.
+pub fn load(path: &Path) -> io::Result<TriangleMesh> {
+    let mesh = parse(&fs::read(path)?)?;
+    let material = mesh.default_material();
+    Ok(TriangleMesh::new(mesh, material))
+}
+
+pub fn parse(bytes: &[u8]) -> io::Result<Mesh> {
+    let (format, elements, body) = parse_header(bytes)?;
+    let mut reader = match format {
+        Format::Ascii => {
+            let text = std::str::from_utf8(body)
+                .map_err(|_| invalid_data("ASCII PLY body isn't valid UTF-8"))?;
+            Reader::Ascii(text.split_ascii_whitespace())
+        }
+        _ => Reader::Binary {
+            bytes: body,
+            big_endian: format == Format::BinaryBigEndian,
+        },
+    };
+
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut colors = vec![];
+    let mut uvs = vec![];
+    let mut indices = vec![];
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => {
+                let find = |names: &[&str]| {
+                    element.properties.iter().position(|property| {
+                        matches!(property, Property::Scalar(name, _) if names.contains(&name.as_str()))
+                    })
+                };
+                let [Some(x), Some(y), Some(z)] = [find(&["x"]), find(&["y"]), find(&["z"])] else {
+                    return Err(invalid_data("PLY vertices without x, y and z"));
+                };
+                let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+                let color = [
+                    find(&["red", "r", "diffuse_red"]),
+                    find(&["green", "g", "diffuse_green"]),
+                    find(&["blue", "b", "diffuse_blue"]),
+                ];
+                let uv = [
+                    find(&["u", "s", "texture_u", "texture_s"]),
+                    find(&["v", "t", "texture_v", "texture_t"]),
+                ];
+                let color_scale = |i: usize| match &element.properties[i] {
+                    Property::Scalar(_, ty) => ty.color_scale(),
+                    Property::List(..) => 1.0,
+                };
+
+                let mut values = vec![0.0; element.properties.len()];
+                for _ in 0..element.count {
+                    for (value, property) in values.iter_mut().zip(&element.properties) {
+                        *value = match property {
+                            Property::Scalar(_, ty) => reader.read(*ty)?,
+                            Property::List(_, count_type, item_type) => {
+                                reader.skip_list(*count_type, *item_type)?;
+                                0.0
+                            }
+                        };
+                    }
+                    let value = |i: usize| values[i] as f32;
+                    positions.push(Point3::new(value(x), value(y), value(z)));
+                    if let [Some(nx), Some(ny), Some(nz)] = normal {
+                        normals.push(Vec3::new(value(nx), value(ny), value(nz)));
+                    }
+                    if let [Some(r), Some(g), Some(b)] = color {
+                        let channel = |i: usize| value(i) / color_scale(i) as f32;
+                        colors.push(Color::new(channel(r), channel(g), channel(b)));
+                    }
+                    if let [Some(u), Some(v)] = uv {
+                        uvs.push((value(u), value(v)));
+                    }
+                }
+            }
+            "face" => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        match property {
+                            Property::List(name, count_type, item_type)
+                                if name == "vertex_indices" || name == "vertex_index" =>
+                            {
+                                let count = reader.read(*count_type)? as usize;
+                                let mut face = Vec::with_capacity(count.min(16));
+                                for _ in 0..count {
+                                    face.push(reader.read(*item_type)? as u32);
+                                }
+                                for i in 1..count.saturating_sub(1) {
+                                    indices.push([face[0], face[i], face[i + 1]]);
+                                }
+                            }
+                            Property::List(_, count_type, item_type) => {
+                                reader.skip_list(*count_type, *item_type)?
+                            }
+                            Property::Scalar(_, ty) => {
+                                reader.read(*ty)?;
+                            }
+                        }
+                    }
+                }
+            }
+            // Anything else, like edges, is skipped.
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        match property {
+                            Property::Scalar(_, ty) => {
+                                reader.read(*ty)?;
+                            }
+                            Property::List(_, count_type, item_type) => {
+                                reader.skip_list(*count_type, *item_type)?
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if indices
+        .iter()
+        .flatten()
+        .any(|&i| i as usize >= positions.len())
+    {
+        return Err(invalid_data("PLY face with a vertex index out of range"));
+    }
+    let mut mesh = Mesh::new(positions, indices);
+    if !normals.is_empty() {
+        mesh = mesh.with_normals(normals);
+    }
+    if !uvs.is_empty() {
+        mesh = mesh.with_uvs(uvs);
+    }
+    if !colors.is_empty() {
+        mesh = mesh.with_colors(colors);
+    }
+    Ok(mesh)
+}
+
+// Splits the file into its format, the element declarations and the body following the header.
+fn parse_header(bytes: &[u8]) -> io::Result<(Format, Vec<Element>, &[u8])> {
+    const END: &[u8] = b"end_header";
+    let end = bytes
+        .windows(END.len())
+        .position(|window| window == END)
+        .ok_or_else(|| invalid_data("PLY file without end_header"))?;
+    // The body starts after the line break, which may be "\r\n".
+    let mut body = end + END.len();
+    while body < bytes.len() && bytes[body] != b'\n' {
+        body += 1;
+    }
+    let header = std::str::from_utf8(&bytes[..end])
+        .map_err(|_| invalid_data("PLY header isn't valid UTF-8"))?;
+
+    let mut lines = header.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err(invalid_data("not a PLY file"));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    for line in lines {
+        let words: Vec<&str> = line.split_ascii_whitespace().collect();
+        match words.as_slice() {
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(invalid_data(&format!("unknown PLY format `{name}`"))),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| invalid_data(&format!("invalid PLY element count `{count}`")))?,
+                properties: vec![],
+            }),
+            ["property", "list", count_type, item_type, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid_data("PLY property outside of an element"))?
+                .properties
+                .push(Property::List(
+                    name.to_string(),
+                    Scalar::parse(count_type)?,
+                    Scalar::parse(item_type)?,
+                )),
+            ["property", ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid_data("PLY property outside of an element"))?
+                .properties
+                .push(Property::Scalar(name.to_string(), Scalar::parse(ty)?)),
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(invalid_data(&format!("invalid PLY header line `{line}`"))),
+        }
+    }
+    let format = format.ok_or_else(|| invalid_data("PLY header without format"))?;
+    Ok((format, elements, bytes.get(body + 1..).unwrap_or_default()))
+}
+
+enum Reader<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl Reader<'_> {
+    fn read(&mut self, ty: Scalar) -> io::Result<f64> {
+        match self {
+            Reader::Ascii(words) => {
+                let word = words
+                    .next()
+                    .ok_or_else(|| invalid_data("unexpected end of PLY data"))?;
+                word.parse()
+                    .map_err(|_| invalid_data(&format!("invalid PLY value `{word}`")))
+            }
+            Reader::Binary { bytes, big_endian } => {
+                let size = ty.size();
+                if bytes.len() < size {
+                    return Err(invalid_data("unexpected end of PLY data"));
+                }
+                let (value, rest) = bytes.split_at(size);
+                *bytes = rest;
+                let mut buffer = [0u8; 8];
+                buffer[..size].copy_from_slice(value);
+                if *big_endian {
+                    buffer[..size].reverse();
+                }
+                let [b0, b1, b2, b3, ..] = buffer;
+                Ok(match ty {
+                    Scalar::I8 => b0 as i8 as f64,
+                    Scalar::U8 => b0 as f64,
+                    Scalar::I16 => i16::from_le_bytes([b0, b1]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([b0, b1]) as f64,
+                    Scalar::I32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    Scalar::U32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    Scalar::F32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    Scalar::F64 => f64::from_le_bytes(buffer),
+                })
+            }
+        }
+    }
+
+    fn skip_list(&mut self, count_type: Scalar, item_type: Scalar) -> io::Result<()> {
+        let count = self.read(count_type)? as usize;
+        for _ in 0..count {
+            self.read(item_type)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    const VERTICES: [([f32; 3], [u8; 3]); 4] = [
+        ([0.0, 0.0, 0.0], [255, 0, 0]),
+        ([1.0, 0.0, 0.0], [0, 255, 0]),
+        ([1.0, 1.0, 0.0], [0, 0, 255]),
+        ([0.0, 1.0, 0.0], [255, 255, 255]),
+    ];
+
+    fn check(mesh: &Mesh) {
+        // The quad is split into two triangles.
+        assert_eq!(mesh.indices(), &[[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.positions()[2], Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(mesh.colors()[1], Color::new(0.0, 1.0, 0.0));
+        assert!(mesh.normals().is_empty());
+    }
+
+    fn binary(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian {
+            "binary_big_endian"
+        } else {
+            "binary_little_endian"
+        };
+        let mut bytes = format!("ply\nformat {format} 1.0\ncomment test\n{HEADER}").into_bytes();
+        let mut push = |value: &[u8]| {
+            if big_endian {
+                bytes.extend(value.iter().rev());
+            } else {
+                bytes.extend_from_slice(value);
+            }
+        };
+        for (position, color) in VERTICES {
+            for x in position {
+                push(&x.to_le_bytes());
+            }
+            for c in color {
+                push(&[c]);
+            }
+        }
+        push(&[4]);
+        for index in [0i32, 1, 2, 3] {
+            push(&index.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_ascii() {
+        let mut text = format!("ply\r\nformat ascii 1.0\r\n{HEADER}");
+        for (position, color) in VERTICES {
+            text += &format!(
+                "{} {} {} {} {} {}\n",
+                position[0], position[1], position[2], color[0], color[1], color[2]
+            );
+        }
+        text += "4 0 1 2 3\n";
+        check(&parse(text.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_binary() {
+        check(&parse(&binary(false)).unwrap());
+        check(&parse(&binary(true)).unwrap());
+
+        let mut truncated = binary(false);
+        truncated.pop();
+        assert!(parse(&truncated).is_err());
+    }
+}
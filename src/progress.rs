@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub type ProgressCallback = Box<dyn Fn(&Progress) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    // Current pass, counting from one, out of `passes`.
+    pub pass: u32,
+    pub passes: u32,
+    // Tiles finished within the current pass.
+    pub tiles_done: usize,
+    pub tiles_total: usize,
+    // Samples taken over the whole render, including passes restored from a checkpoint.
+    pub samples_done: u64,
+    pub samples_total: u64,
+    pub elapsed: Duration,
+    pub eta: Option<Duration>,
+}
+
+impl Progress {
+    #[inline]
+    pub fn fraction(&self) -> f32 {
+        if self.samples_total == 0 {
+            return 1.0;
+        }
+        self.samples_done as f32 / self.samples_total as f32
+    }
+}
+
+// Shared flag to stop a render from another thread. The renderer checks it between tiles and
+// drops the unfinished pass, so the samples that are kept are always evenly distributed.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Counts finished work from the render threads and turns it into `Progress` reports.
+pub(crate) struct ProgressTracker {
+    start: Instant,
+    samples_at_start: u64,
+    samples_total: u64,
+    samples_done: AtomicU64,
+    tiles_done: AtomicUsize,
+}
+
+impl ProgressTracker {
+    pub(crate) fn new(samples_at_start: u64, samples_total: u64) -> Self {
+        Self {
+            start: Instant::now(),
+            samples_at_start,
+            samples_total,
+            samples_done: AtomicU64::new(samples_at_start),
+            tiles_done: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn start_pass(&self) {
+        self.tiles_done.store(0, Ordering::Relaxed);
+    }
+
+    pub(crate) fn tile_done(&self, samples: u64) -> (usize, u64) {
+        let tiles_done = self.tiles_done.fetch_add(1, Ordering::Relaxed) + 1;
+        let samples_done = self.samples_done.fetch_add(samples, Ordering::Relaxed) + samples;
+        (tiles_done, samples_done)
+    }
+
+    pub(crate) fn report(
+        &self,
+        pass: u32,
+        passes: u32,
+        tiles: (usize, usize),
+        samples_done: u64,
+    ) -> Progress {
+        let elapsed = self.start.elapsed();
+        // Extrapolate from the samples taken in this run, restored ones cost us nothing.
+        let sampled = samples_done.saturating_sub(self.samples_at_start);
+        let remaining = self.samples_total.saturating_sub(samples_done);
+        let eta = if sampled > 0 {
+            Some(elapsed.mul_f64(remaining as f64 / sampled as f64))
+        } else {
+            None
+        };
+        Progress {
+            pass,
+            passes,
+            tiles_done: tiles.0,
+            tiles_total: tiles.1,
+            samples_done,
+            samples_total: self.samples_total,
+            elapsed,
+            eta,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_token_is_shared() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        token.cancel();
+        assert!(clone.is_cancelled());
+    }
+
+    #[test]
+    fn test_tracker_counts_resumed_samples() {
+        let tracker = ProgressTracker::new(100, 400);
+        tracker.start_pass();
+        let (tiles_done, samples_done) = tracker.tile_done(100);
+        assert_eq!(tiles_done, 1);
+        assert_eq!(samples_done, 200);
+
+        let progress = tracker.report(2, 4, (tiles_done, 4), samples_done);
+        assert_eq!(progress.fraction(), 0.5);
+        assert!(progress.eta.is_some());
+    }
+}
@@ -1,14 +1,56 @@
+use crate::medium::MediumStack;
+use crate::spectrum::{self, SampledWavelengths};
 use crate::vec3::{Point3, Vec3};
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Ray {
     orig: Point3,
     dir: Vec3,
+    // Moment the ray is traced at, for animated scenes and motion blur.
+    time: f32,
+    // Set when rendering spectrally, see `spectrum`.
+    wavelengths: Option<SampledWavelengths>,
+    // The one RGB channel left after dispersion split the colors.
+    channel: Option<usize>,
+    // Dielectrics the ray is inside of.
+    media: MediumStack,
 }
 
 impl Ray {
     pub fn new(orig: Point3, dir: Vec3) -> Self {
-        Self { orig, dir }
+        Self {
+            orig,
+            dir,
+            time: 0.0,
+            wavelengths: None,
+            channel: None,
+            media: MediumStack::default(),
+        }
+    }
+
+    // A ray from `orig` along `dir` at the same moment, wavelengths and media as this one.
+    pub fn continued(&self, orig: Point3, dir: Vec3) -> Self {
+        Self { orig, dir, ..*self }
+    }
+
+    pub fn with_time(mut self, time: f32) -> Self {
+        self.time = time;
+        self
+    }
+
+    pub fn with_wavelengths(mut self, wavelengths: Option<SampledWavelengths>) -> Self {
+        self.wavelengths = wavelengths;
+        self
+    }
+
+    pub fn with_channel(mut self, channel: Option<usize>) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    pub fn with_media(mut self, media: MediumStack) -> Self {
+        self.media = media;
+        self
     }
 
     #[inline]
@@ -21,6 +63,35 @@ impl Ray {
         &self.dir
     }
 
+    #[inline]
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    #[inline]
+    pub fn wavelengths(&self) -> Option<SampledWavelengths> {
+        self.wavelengths
+    }
+
+    #[inline]
+    pub fn channel(&self) -> Option<usize> {
+        self.channel
+    }
+
+    #[inline]
+    pub fn media(&self) -> &MediumStack {
+        &self.media
+    }
+
+    // The wavelength that decides where the light goes, for materials that depend on it.
+    pub fn wavelength(&self) -> Option<f32> {
+        match (self.wavelengths, self.channel) {
+            (Some(wavelengths), _) => Some(wavelengths.hero()),
+            (None, Some(channel)) => Some(spectrum::RGB_WAVELENGTHS[channel]),
+            (None, None) => None,
+        }
+    }
+
     #[inline]
     pub fn at(&self, t: f32) -> Point3 {
         let point = self.orig + self.dir * t;
@@ -1,20 +1,72 @@
-use crate::camera::Camera;
+use crate::camera::{Camera, FisheyeMapping, Projection};
 
 use crate::{
-    color::{self, Color},
-    hittable::{HitRecord, Hittable},
+    checkpoint::{Checkpoint, Fnv1a},
+    color::{luminance, Color},
+    environment::Background,
+    filter::Filter,
+    framebuffer::Framebuffer,
+    hittable::HitRecord,
     interval::Interval,
-    material::Material,
+    light::Light,
+    material::{Material, MaterialType},
+    progress::{CancelToken, Progress, ProgressCallback, ProgressTracker},
     ray::Ray,
-    rtweekend::{degrees_to_radians, random_double},
-    vec3::{self, random_in_unit_disk, Point3, Vec3},
+    rtweekend::{self, degrees_to_radians},
+    sampler::{Sampler, SamplerKind, SamplerType},
+    spectrum::SampledWavelengths,
+    vec3::{self, Point3, Vec3},
+    world::World,
 };
-use log::info;
+use log::{info, warn};
 use rayon::prelude::*;
 
+use std::f32::consts::PI;
+use std::hash::Hasher;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// Edge length of the square tiles a pass is split into.
+const TILE_SIZE: i32 = 32;
+// Rays start this far from the surface they leave, to not hit it again.
+const EPSILON: f32 = 0.001;
+
+#[derive(Clone, Copy)]
+struct Tile {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+}
+
+impl Tile {
+    #[inline]
+    fn area(&self) -> usize {
+        ((self.x1 - self.x0) * (self.y1 - self.y0)) as usize
+    }
+}
+
+#[derive(Clone)]
+pub struct CheckpointSettings {
+    pub path: PathBuf,
+    pub interval: Duration,
+}
+
+#[derive(Clone)]
 pub struct RenderSettings {
     sample_per_pixel: u32,
     max_depth: u32,
+    samples_per_pass: u32,
+    seed: u64,
+    sampler: SamplerKind,
+    filter: Filter,
+    checkpoint: Option<CheckpointSettings>,
+    // Optional budgets, `sample_per_pixel` stays the upper bound when they are set.
+    time_limit: Option<Duration>,
+    noise_threshold: Option<f32>,
+    // Trace every camera ray at a few wavelengths rather than in RGB.
+    spectral: bool,
 }
 
 impl RenderSettings {
@@ -22,21 +74,149 @@ impl RenderSettings {
         Self {
             sample_per_pixel,
             max_depth,
+            samples_per_pass: 16,
+            seed: 0,
+            sampler: SamplerKind::default(),
+            filter: Filter::default(),
+            checkpoint: None,
+            time_limit: None,
+            noise_threshold: None,
+            spectral: false,
         }
     }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_sampler(mut self, sampler: SamplerKind) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_samples_per_pass(mut self, samples_per_pass: u32) -> Self {
+        self.samples_per_pass = samples_per_pass.max(1);
+        self
+    }
+
+    pub fn with_checkpoint(mut self, path: impl Into<PathBuf>, interval: Duration) -> Self {
+        self.checkpoint = Some(CheckpointSettings {
+            path: path.into(),
+            interval,
+        });
+        self
+    }
+
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    // Stop once the average relative standard error of the pixel luminance drops below
+    // `noise_threshold`.
+    pub fn with_noise_threshold(mut self, noise_threshold: f32) -> Self {
+        self.noise_threshold = Some(noise_threshold);
+        self
+    }
+
+    pub fn with_spectral(mut self, spectral: bool) -> Self {
+        self.spectral = spectral;
+        self
+    }
+
+    fn fingerprint(&self, state: &mut dyn Hasher) {
+        state.write_u32(self.sample_per_pixel);
+        state.write_u32(self.max_depth);
+        state.write_u32(self.samples_per_pass);
+        state.write_u64(self.seed);
+        state.write(self.sampler.name().as_bytes());
+        state.write(self.filter.kind.name().as_bytes());
+        state.write_u32(self.filter.radius.to_bits());
+        state.write_u8(self.spectral as u8);
+    }
 }
 
 pub struct Render {
     settings: RenderSettings,
     camera: Camera,
+    framebuffer: Framebuffer,
+    passes: u32,
+    progress: Option<ProgressCallback>,
+    cancel: CancelToken,
 }
 
 impl Render {
     pub fn new(settings: RenderSettings, camera: Camera) -> Self {
-        Self { settings, camera }
+        Self {
+            settings,
+            camera,
+            framebuffer: Framebuffer::default(),
+            passes: 0,
+            progress: None,
+            cancel: CancelToken::new(),
+        }
+    }
+
+    // Called from the render threads every time a tile is finished.
+    pub fn set_progress_callback(&mut self, callback: impl Fn(&Progress) + Send + Sync + 'static) {
+        self.progress = Some(Box::new(callback));
+    }
+
+    // Token to stop the render from another thread, see `CancelToken`.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    pub fn resume(&mut self, path: &Path, world: &World) -> io::Result<()> {
+        let checkpoint = Checkpoint::load(path)?;
+        if checkpoint.scene_hash != Self::scene_hash(world) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "checkpoint was saved for a different scene",
+            ));
+        }
+        let image = &self.camera.image;
+        if checkpoint.settings_hash != self.settings_hash()
+            || checkpoint.framebuffer.width() != image.width
+            || checkpoint.framebuffer.height() != image.height
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "checkpoint was saved with different render settings",
+            ));
+        }
+
+        info!(
+            "Resuming from {} after {} passes",
+            path.display(),
+            checkpoint.passes
+        );
+        self.framebuffer = checkpoint.framebuffer;
+        self.passes = checkpoint.passes;
+        Ok(())
+    }
+
+    fn scene_hash(world: &World) -> u64 {
+        let mut hasher = Fnv1a::default();
+        world.fingerprint(&mut hasher);
+        hasher.finish()
+    }
+
+    fn settings_hash(&self) -> u64 {
+        let mut hasher = Fnv1a::default();
+        self.settings.fingerprint(&mut hasher);
+        self.camera.fingerprint(&mut hasher);
+        hasher.finish()
     }
 
     fn initialize(&mut self) {
+        let eye_offset = self.camera.eye_offset();
         let settings = &self.camera.settings;
         let transform = &mut self.camera.transform;
         let image = &self.camera.image;
@@ -48,8 +228,12 @@ impl Render {
 
         // let focal_length = (self.look_from - self.look_at).length();
         // Set the camera position to the origin.
-        let viewport_height = 2.0 * h * settings.focus_distance;
-        let viewport_width = viewport_height * (image.width / image.height) as f32;
+        // The panoramic projections don't use the viewport, only the basis vectors.
+        let viewport_height = match settings.projection {
+            Projection::Orthographic { height } => height,
+            _ => 2.0 * h * settings.focus_distance,
+        };
+        let viewport_width = viewport_height * (image.width as f32 / image.height as f32);
 
         // Calculate the u,v,w unit basis vectors for the camera coordinate frame.
         let w = vec3::unit_vector(transform.look_from - transform.look_at);
@@ -61,7 +245,7 @@ impl Render {
         let viewport_v = viewport_height * -v;
 
         // Calculate the horizontal and vertical delta vectors from pixel to pixel.
-        transform.pixel_delta_u = settings.aspect_ratio * viewport_u / image.width as f32;
+        transform.pixel_delta_u = viewport_u / image.width as f32;
         transform.pixel_delta_v = viewport_v / image.height as f32;
 
         // Calculate the location of the upper left pixel.
@@ -75,67 +259,439 @@ impl Render {
 
         transform.defocus_disk_u = u * defocus_radius;
         transform.defocus_disk_v = v * defocus_radius;
+        transform.u = u;
+        transform.v = v;
+        transform.w = w;
+
+        // Off-axis stereo for the planar projections: the eye moves sideways and the image plane
+        // follows it, so that both eyes frame the same rectangle at the convergence distance.
+        if let (Some(stereo), Projection::Perspective | Projection::Orthographic { .. }) =
+            (settings.stereo, settings.projection)
+        {
+            let eye = eye_offset * u;
+            transform.center += eye;
+            transform.pixel00_loc += (1.0 - settings.focus_distance / stereo.convergence) * eye;
+        }
     }
 
-    pub fn render(&mut self, world: &impl Hittable) {
+    pub fn render(&mut self, world: &World) -> Framebuffer {
         self.initialize();
 
-        let image = &self.camera.image;
+        let image = self.camera.image;
+        let len = (image.width * image.height) as usize;
+        if self.framebuffer.width() != image.width || self.framebuffer.height() != image.height {
+            self.framebuffer = Framebuffer::new(image.width, image.height);
+            self.passes = 0;
+        }
+
+        let sample_per_pixel = self.settings.sample_per_pixel;
+        let samples_per_pass = self.settings.samples_per_pass;
+        let total_passes = sample_per_pixel.div_ceil(samples_per_pass);
+        let scene_hash = Self::scene_hash(world);
+        let start = Instant::now();
+        let first_pass = self.passes;
+        let mut last_checkpoint = start;
+        let tracker = ProgressTracker::new(
+            self.framebuffer.total_samples(),
+            len as u64 * sample_per_pixel as u64,
+        );
+
         info!("Rendering...");
-        println!("P3\n{} {}\n255", image.width, image.height);
-
-        for j in 0..image.height {
-            info!("Scanlines remaining: {}", image.height - j);
-            for i in 0..image.width {
-                let pixel_color = (0..self.settings.sample_per_pixel)
-                    .into_par_iter()
-                    .fold(Color::default, |acc_color, _| {
-                        let ray = self.get_ray(i, j);
-                        acc_color + Self::ray_color(&ray, self.settings.max_depth, world)
-                    })
-                    .reduce(Color::default, |acc_color, color| acc_color + color);
-                color::write_color(
-                    &mut std::io::stdout(),
-                    pixel_color,
+        for pass in first_pass..total_passes {
+            if self.cancel.is_cancelled() {
+                info!("Render cancelled");
+                break;
+            }
+            if !self.within_budget(start, pass - first_pass) {
+                break;
+            }
+
+            info!("Pass {} of {}", pass + 1, total_passes);
+            let samples = samples_per_pass.min(sample_per_pixel - pass * samples_per_pass);
+            if !self.render_pass(world, (pass, total_passes), samples, &tracker) {
+                info!("Render cancelled, dropping the unfinished pass");
+                break;
+            }
+            self.passes = pass + 1;
+
+            if let Some(checkpoint) = &self.settings.checkpoint {
+                if self.passes < total_passes && last_checkpoint.elapsed() >= checkpoint.interval {
+                    self.save_checkpoint(&checkpoint.path, scene_hash);
+                    last_checkpoint = Instant::now();
+                }
+            }
+        }
+
+        if let Some(checkpoint) = &self.settings.checkpoint {
+            // Stopped by a budget or cancelled, keep what we have so that the render can be
+            // continued.
+            if self.passes < total_passes {
+                self.save_checkpoint(&checkpoint.path, scene_hash);
+            }
+        }
+
+        info!(
+            "Achieved {} samples per pixel in {:.1?}, noise level {:.4}",
+            self.achieved_samples(),
+            start.elapsed(),
+            self.framebuffer.noise_level()
+        );
+        info!(" \rDone.                 \n");
+        self.framebuffer.clone()
+    }
+
+    // Samples per pixel taken so far, every pass covers the whole image evenly.
+    pub fn achieved_samples(&self) -> u32 {
+        match self.framebuffer.width() {
+            0 => 0,
+            _ => self.framebuffer.samples(0, 0),
+        }
+    }
+
+    // `finished` counts the passes done since `start`, restored ones don't tell us anything
+    // about this run's timing.
+    fn within_budget(&self, start: Instant, finished: u32) -> bool {
+        if let Some(noise_threshold) = self.settings.noise_threshold {
+            if self.framebuffer.noise_level() <= noise_threshold {
+                info!("Noise threshold reached");
+                return false;
+            }
+        }
+        if let Some(time_limit) = self.settings.time_limit {
+            // Only start a pass if, judging by the ones before it, it will end in time. The first
+            // one always runs, for there to be an image at all.
+            let elapsed = start.elapsed();
+            if finished > 0 && elapsed + elapsed / finished > time_limit {
+                info!("Time limit reached");
+                return false;
+            }
+        }
+        true
+    }
+
+    fn tiles(&self) -> Vec<Tile> {
+        let image = &self.camera.image;
+        let mut tiles = vec![];
+        for y0 in (0..image.height).step_by(TILE_SIZE as usize) {
+            for x0 in (0..image.width).step_by(TILE_SIZE as usize) {
+                tiles.push(Tile {
+                    x0,
+                    y0,
+                    x1: (x0 + TILE_SIZE).min(image.width),
+                    y1: (y0 + TILE_SIZE).min(image.height),
+                });
+            }
+        }
+        tiles
+    }
+
+    // Adds `samples` samples to every pixel, returns false if the pass was cancelled.
+    fn render_pass(
+        &mut self,
+        world: &World,
+        (pass, passes): (u32, u32),
+        samples: u32,
+        tracker: &ProgressTracker,
+    ) -> bool {
+        let width = self.camera.image.width;
+        let pass_seed = rtweekend::mix_seed(self.settings.seed, pass as u64);
+        let tiles = self.tiles();
+        tracker.start_pass();
+
+        let rendered: Vec<Option<Framebuffer>> = tiles
+            .par_iter()
+            .map(|tile| {
+                if self.cancel.is_cancelled() {
+                    return None;
+                }
+
+                let mut sampler = SamplerType::new(
+                    self.settings.sampler,
                     self.settings.sample_per_pixel,
+                    self.settings.seed,
+                );
+                // Samples near the tile border reach into the neighbouring tiles.
+                let filter = &self.settings.filter;
+                let margin = filter.margin();
+                let exposure = self.camera.settings.exposure;
+                let mut pixels = Framebuffer::new(
+                    tile.x1 - tile.x0 + 2 * margin,
+                    tile.y1 - tile.y0 + 2 * margin,
                 );
+                for j in tile.y0..tile.y1 {
+                    for i in tile.x0..tile.x1 {
+                        rtweekend::seed(rtweekend::mix_seed(pass_seed, (j * width + i) as u64));
+                        let (x, y) = (i - tile.x0 + margin, j - tile.y0 + margin);
+                        for s in 0..samples {
+                            let sample_index = pass * self.settings.samples_per_pass + s;
+                            sampler.start_pixel_sample((i, j), sample_index);
+                            let u = sampler.get_2d();
+                            let offset = (u.0 - 0.5, u.1 - 0.5);
+                            let sample = match self.get_ray(i, j, offset, &mut sampler) {
+                                Some(ray) => {
+                                    let wavelengths = self.settings.spectral.then(|| {
+                                        SampledWavelengths::sample_visible(sampler.get_1d())
+                                    });
+                                    let ray = ray.with_wavelengths(wavelengths);
+                                    let color = Self::ray_color(
+                                        &ray,
+                                        self.settings.max_depth,
+                                        world,
+                                        &mut sampler,
+                                        None,
+                                    );
+                                    exposure
+                                        * match wavelengths {
+                                            Some(wavelengths) => wavelengths.to_rgb(color),
+                                            None => color,
+                                        }
+                                }
+                                None => Color::default(),
+                            };
+
+                            pixels.add_statistics(x, y, luminance(sample));
+                            for dy in -margin..=margin {
+                                for dx in -margin..=margin {
+                                    let weight =
+                                        filter.evaluate(dx as f32 - offset.0, dy as f32 - offset.1);
+                                    if weight != 0.0 {
+                                        pixels.splat(x + dx, y + dy, sample, weight);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                let (tiles_done, samples_done) =
+                    tracker.tile_done(tile.area() as u64 * samples as u64);
+                if let Some(callback) = &self.progress {
+                    let tiles = (tiles_done, tiles.len());
+                    callback(&tracker.report(pass + 1, passes, tiles, samples_done));
+                }
+                Some(pixels)
+            })
+            .collect();
+
+        // Only whole passes are kept, a partial one would leave the image unevenly sampled.
+        if rendered.iter().any(Option::is_none) {
+            return false;
+        }
+
+        let margin = self.settings.filter.margin();
+        for (tile, pixels) in tiles.iter().zip(rendered.into_iter().flatten()) {
+            self.framebuffer
+                .merge(&pixels, tile.x0 - margin, tile.y0 - margin);
+        }
+        true
+    }
+
+    fn save_checkpoint(&self, path: &Path, scene_hash: u64) {
+        let checkpoint = Checkpoint {
+            scene_hash,
+            settings_hash: self.settings_hash(),
+            passes: self.passes,
+            framebuffer: self.framebuffer.clone(),
+        };
+        match checkpoint.save(path) {
+            Ok(()) => info!("Checkpoint saved to {}", path.display()),
+            Err(err) => warn!("Failed to save checkpoint to {}: {}", path.display(), err),
+        }
+    }
+
+    // `offset` is the position of the sample relative to the pixel center. There is no ray for
+    // the samples outside the image circle of a fisheye lens.
+    fn get_ray(
+        &self,
+        i: i32,
+        j: i32,
+        offset: (f32, f32),
+        sampler: &mut dyn Sampler,
+    ) -> Option<Ray> {
+        let transform = &self.camera.transform;
+        let settings = &self.camera.settings;
+        let image = &self.camera.image;
+
+        // Always draw the lens and time samples, so that the later dimensions don't shift around.
+        let lens_sample = sampler.get_2d();
+        let time = settings.shutter_open
+            + sampler.get_1d() * (settings.shutter_close - settings.shutter_open);
+
+        // Position of the sample on the image, from 0 to 1 left to right and top to bottom. The
+        // panoramic projections are pinholes, without depth of field.
+        let x = (i as f32 + 0.5 + offset.0) / image.width as f32;
+        let y = (j as f32 + 0.5 + offset.1) / image.height as f32;
+        let eye_offset = self.camera.eye_offset();
+        let (direction, eye) = match settings.projection {
+            Projection::Equirectangular => {
+                let phi = (x - 0.5) * 2.0 * PI;
+                let theta = (0.5 - y) * PI;
+                // Keep the horizon level, the poles are along the up vector rather than the tilted
+                // camera axis.
+                let up = vec3::unit_vector(transform.vup);
+                let forward = vec3::unit_vector(vec3::dot(transform.w, up) * up - transform.w);
+                let right = vec3::cross(forward, up);
+                let direction =
+                    theta.cos() * (phi.sin() * right + phi.cos() * forward) + theta.sin() * up;
+                // Omni-directional stereo, the eyes turn with the view on a circle around the
+                // center.
+                let eye = eye_offset * (phi.cos() * right - phi.sin() * forward);
+                (direction, eye)
+            }
+            Projection::Fisheye { mapping, fov } => {
+                let aspect_ratio = image.width as f32 / image.height as f32;
+                let (px, py) = ((2.0 * x - 1.0) * aspect_ratio, 1.0 - 2.0 * y);
+                let r = (px * px + py * py).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+                let half_fov = degrees_to_radians(fov) / 2.0;
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * half_fov,
+                    FisheyeMapping::Equisolid => 2.0 * (r * (half_fov / 2.0).sin()).asin(),
+                };
+                let (sin_phi, cos_phi) = if r > 0.0 {
+                    (py / r, px / r)
+                } else {
+                    (0.0, 1.0)
+                };
+                let direction = theta.sin() * (cos_phi * transform.u + sin_phi * transform.v)
+                    - theta.cos() * transform.w;
+                (direction, eye_offset * transform.u)
             }
+            Projection::Perspective | Projection::Orthographic { .. } => {
+                return Some(
+                    self.get_planar_ray(i, j, offset, lens_sample)
+                        .with_time(time),
+                );
+            }
+        };
+        // Both eyes look at the point seen by the center at the convergence distance.
+        match settings.stereo {
+            Some(stereo) if eye_offset != 0.0 => Some(
+                Ray::new(transform.center + eye, stereo.convergence * direction - eye)
+                    .with_time(time),
+            ),
+            _ => Some(Ray::new(transform.center, direction).with_time(time)),
         }
-        info!(" \rDone.                 \n");
     }
 
-    fn get_ray(&self, i: i32, j: i32) -> Ray {
+    // Perspective and orthographic rays through the image plane at the focus distance.
+    fn get_planar_ray(&self, i: i32, j: i32, offset: (f32, f32), lens_sample: (f32, f32)) -> Ray {
         let transform = &self.camera.transform;
         let settings = &self.camera.settings;
 
         let pixel_center = transform.pixel00_loc
             + (i as f32 * transform.pixel_delta_u)
             + (j as f32 * transform.pixel_delta_v);
-        let pixel_sample = pixel_center + self.pixel_sample_square();
+        let pixel_sample = pixel_center
+            + (offset.0 * transform.pixel_delta_u)
+            + (offset.1 * transform.pixel_delta_v);
+        let center = match settings.projection {
+            // Parallel rays, each one starts on the plane of the camera right behind its pixel.
+            // With stereo the eye moves less than the image plane, the rays are skewed towards
+            // the convergence distance.
+            Projection::Orthographic { .. } => {
+                let shift = match settings.stereo {
+                    Some(stereo) => settings.focus_distance / stereo.convergence,
+                    None => 0.0,
+                };
+                pixel_sample
+                    + settings.focus_distance * transform.w
+                    + shift * self.camera.eye_offset() * transform.u
+            }
+            _ => transform.center,
+        };
         let ray_origin = if settings.defocus_angle <= 0.0 {
-            transform.center
+            center
         } else {
-            self.defocus_disk_sample()
+            self.defocus_disk_sample(center, lens_sample)
         };
         let ray_direction = pixel_sample - ray_origin;
         Ray::new(ray_origin, ray_direction)
     }
 
-    fn defocus_disk_sample(&self) -> Point3 {
+    fn defocus_disk_sample(&self, center: Point3, u: (f32, f32)) -> Point3 {
         let transform = &self.camera.transform;
-        let p = random_in_unit_disk();
-        transform.center + (p[0] * transform.defocus_disk_u) + (p[1] * transform.defocus_disk_v)
+        let p = self.camera.settings.aperture.sample(u);
+        center + (p.0 * transform.defocus_disk_u) + (p.1 * transform.defocus_disk_v)
     }
 
-    fn pixel_sample_square(&self) -> Vec3 {
-        let transform = &self.camera.transform;
-        let offset = -0.5;
-        let px = -offset + random_double();
-        let py = -offset + random_double();
-        px * transform.pixel_delta_u + py * transform.pixel_delta_v
+    // Light reaching the hit point straight from the analytic lights, one shadow ray per light,
+    // and from the environment image.
+    fn sample_lights(
+        r: &Ray,
+        rec: &HitRecord,
+        material: &MaterialType,
+        world: &World,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let visible = |wi: Vec3, distance: f32| {
+            let shadow = Ray::new(rec.p, wi).with_time(r.time());
+            let mut shadow_rec = HitRecord::default();
+            let unoccluded = Interval::new(EPSILON, distance * (1.0 - EPSILON));
+            !world.hit(&shadow, unoccluded, &mut shadow_rec)
+        };
+
+        let mut color = Color::default();
+        for light in world.lights() {
+            let Some(sample) = light.sample_li(rec.p, sampler.get_2d()) else {
+                continue;
+            };
+            let f = material.eval(r, rec, sample.wi);
+            if f.near_zero() || sample.li.near_zero() {
+                continue;
+            }
+            if visible(sample.wi, sample.distance) {
+                color += lift(r, f) * lift(r, sample.li);
+            }
+        }
+
+        // The environment can also be reached by scattering, the two ways share its light.
+        if let Some(Background::Environment(environment)) = world.background() {
+            if let Some((wi, radiance, pdf)) = environment.sample(sampler.get_2d()) {
+                let f = material.eval(r, rec, wi);
+                if !f.near_zero() && visible(wi, f32::INFINITY) {
+                    let weight = power_heuristic(pdf, material.pdf(r, rec, wi));
+                    color += lift(r, f) * lift(r, radiance) * (weight / pdf);
+                }
+            }
+        }
+        color
+    }
+
+    // A dispersive material sends every wavelength its own way, the path follows only one of
+    // them from there: the hero wavelength, or one of the RGB channels picked at random. Returns
+    // the ray to scatter and the weight that moves the light of the others to it.
+    fn narrow(r: &Ray, material: &MaterialType, sampler: &mut dyn Sampler) -> (Ray, Color) {
+        let white = Color::new(1.0, 1.0, 1.0);
+        if !material.dispersive() {
+            return (*r, white);
+        }
+        match (r.wavelengths(), r.channel()) {
+            (Some(mut wavelengths), _) if !wavelengths.secondary_terminated() => {
+                let weight = wavelengths.terminate_secondary();
+                (r.with_wavelengths(Some(wavelengths)), weight)
+            }
+            (None, None) => {
+                let channel = ((sampler.get_1d() * 3.0) as usize).min(2);
+                let mut weight = Color::default();
+                weight[channel] = 3.0;
+                (r.with_channel(Some(channel)), weight)
+            }
+            _ => (*r, white),
+        }
     }
 
-    fn ray_color(r: &Ray, depth: u32, world: &impl Hittable) -> Color {
+    // `bsdf_pdf` is the density the ray was scattered with, when the environment at its end has
+    // been sampled as a light too.
+    fn ray_color(
+        r: &Ray,
+        depth: u32,
+        world: &World,
+        sampler: &mut dyn Sampler,
+        bsdf_pdf: Option<f32>,
+    ) -> Color {
         let mut rec = HitRecord::default();
 
         // If we've exceeded the ray bounce limit, no more light is gathered.
@@ -143,23 +699,285 @@ impl Render {
             return Color::default();
         }
 
-        const EPSILON: f32 = 0.001;
-        if world.hit(r, Interval::new(EPSILON, std::f32::INFINITY), &mut rec) {
+        if world.hit(r, Interval::new(EPSILON, f32::INFINITY), &mut rec) {
+            let material = rec.material.as_ref().expect("No material in hit record.");
+            let medium = material.medium(r, &rec);
+            // Light absorbed on the way through the dielectric the ray is in. Rays that start
+            // inside of one only find out at its surface.
+            let traveled = r
+                .media()
+                .top()
+                .copied()
+                .or(medium.filter(|_| !rec.front_face));
+            let transmittance = match traveled {
+                Some(traveled) => lift(r, traveled.transmittance(rec.t * r.direction().length())),
+                None => Color::new(1.0, 1.0, 1.0),
+            };
+            // Crossing the surface of a dielectric enters or leaves it, the medium keeps the
+            // index at the wavelength of `r`.
+            let cross = |r: &Ray| match material.medium(r, &rec) {
+                Some(medium) if rec.front_face => r.media().enter(medium),
+                Some(medium) => r.media().leave(medium.id),
+                None => *r.media(),
+            };
+            if let Some(medium) = &medium {
+                // Passing through counts as a bounce, or a ray caught between surfaces that
+                // don't count would never end.
+                if r.media().is_false_interface(medium) {
+                    // Into a dispersive medium at one wavelength, as through a real surface.
+                    let (r, narrowing) = Self::narrow(r, material, sampler);
+                    let through = r.continued(rec.p, *r.direction()).with_media(cross(&r));
+                    return transmittance
+                        * narrowing
+                        * Self::ray_color(&through, depth - 1, world, sampler, bsdf_pdf);
+                }
+            }
+
+            let mut color = Self::sample_lights(r, &rec, material, world, sampler);
+            let (r, narrowing) = Self::narrow(r, material, sampler);
+            let r = &r;
             let mut scattered = Ray::default();
             let mut attenuation = Color::default();
-            if rec
-                .material
-                .as_ref()
-                .expect("No material in hit record.")
-                .scatter(r, &rec, &mut attenuation, &mut scattered)
-            {
-                return attenuation * Self::ray_color(&scattered, depth - 1, world);
+            if material.scatter(r, &rec, &mut attenuation, &mut scattered, sampler) {
+                let attenuation = if material.spectral() {
+                    attenuation
+                } else {
+                    lift(r, attenuation)
+                } * narrowing;
+                // Refracted rays go through to the other side.
+                let media = if vec3::dot(*scattered.direction(), rec.normal) < 0.0 {
+                    cross(r)
+                } else {
+                    *r.media()
+                };
+                // Bounces happen at the same moment and wavelengths as the ray that hit the
+                // surface.
+                let scattered = r
+                    .continued(*scattered.origin(), *scattered.direction())
+                    .with_media(media);
+                let bsdf_pdf = match world.background() {
+                    Some(Background::Environment(_)) => {
+                        let wi = vec3::unit_vector(*scattered.direction());
+                        // Directions the lights can't be gathered from keep all of it.
+                        (!material.eval(r, &rec, wi).near_zero()).then(|| material.pdf(r, &rec, wi))
+                    }
+                    _ => None,
+                };
+                color +=
+                    attenuation * Self::ray_color(&scattered, depth - 1, world, sampler, bsdf_pdf);
             }
-            return Color::default();
+            return transmittance * color;
+        }
+
+        let radiance = match world.background() {
+            Some(Background::Environment(environment)) => {
+                let radiance = environment.radiance(*r.direction());
+                match bsdf_pdf {
+                    Some(pdf) => radiance * power_heuristic(pdf, environment.pdf(*r.direction())),
+                    None => radiance,
+                }
+            }
+            Some(background) => background.radiance(*r.direction()),
+            None => Background::Gradient.radiance(*r.direction()),
+        };
+        lift(r, radiance)
+    }
+}
+
+// An RGB color of the scene at the wavelengths of the ray, if it has them.
+#[inline]
+fn lift(r: &Ray, color: Color) -> Color {
+    match r.wavelengths() {
+        Some(wavelengths) => wavelengths.lift(color),
+        None => color,
+    }
+}
+
+// Multiple importance sampling weight of a strategy with density `f` against one with `g`.
+fn power_heuristic(f: f32, g: f32) -> f32 {
+    let (f2, g2) = (f * f, g * g);
+    if f2 + g2 == 0.0 {
+        return 0.0;
+    }
+    f2 / (f2 + g2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{CameraSettings, Eye, Image, Stereo, Xform};
+    use crate::hittable::Sphere;
+    use crate::material::Lambertian;
+
+    use std::sync::Arc;
+
+    // A camera at the origin looking down -z, ready to shoot rays.
+    fn camera(width: i32, settings: CameraSettings) -> Render {
+        let camera = Camera::new(
+            Image::new(width, width),
+            Xform::new(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, -1.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ),
+            settings,
+        );
+        let mut render = Render::new(RenderSettings::new(1, 1), camera);
+        render.initialize();
+        render
+    }
+
+    // A small gray ball in front of a camera of a few pixels.
+    fn render(settings: RenderSettings) -> Render {
+        let world = World::new(vec![Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            MaterialType::Lambertian(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        ))]);
+        let camera = Camera::new(
+            Image::new(8, 8),
+            Xform::new(
+                Point3::new(0.0, 0.0, 5.0),
+                Point3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ),
+            CameraSettings::new(1.0, 30.0, 0.0, 5.0),
+        );
+        let mut render = Render::new(settings.with_samples_per_pass(4), camera);
+        render.render(&world);
+        render
+    }
+
+    #[test]
+    fn test_budgets() {
+        // However short the time, the first pass is done.
+        let settings = RenderSettings::new(64, 4).with_time_limit(Duration::ZERO);
+        assert_eq!(render(settings).achieved_samples(), 4);
+        let settings = RenderSettings::new(64, 4).with_time_limit(Duration::from_nanos(1));
+        assert_eq!(render(settings).achieved_samples(), 4);
+
+        // Any image is less noisy than this.
+        let settings = RenderSettings::new(64, 4).with_noise_threshold(1e6);
+        assert_eq!(render(settings).achieved_samples(), 4);
+
+        // Budgets that are never reached leave the samples per pixel as the limit, also when it
+        // isn't a multiple of the samples per pass.
+        let settings = RenderSettings::new(10, 4)
+            .with_time_limit(Duration::from_secs(3600))
+            .with_noise_threshold(0.0);
+        assert_eq!(render(settings).achieved_samples(), 10);
+    }
+
+    #[test]
+    fn test_orthographic() {
+        let settings = CameraSettings::new(2.0, 30.0, 0.0, 4.0)
+            .with_projection(Projection::Orthographic { height: 3.0 });
+        let render = camera(20, settings);
+        let transform = &render.camera.transform;
+        let ray = |i, j| render.get_planar_ray(i, j, (0.0, 0.0), (0.5, 0.5));
+
+        let origin = *ray(0, 0).origin();
+        for (i, j) in [(0, 0), (7, 3), (19, 9)] {
+            let ray = ray(i, j);
+            assert!((vec3::unit_vector(*ray.direction()) + transform.w).length() < 1e-5);
+            let moved =
+                origin + i as f32 * transform.pixel_delta_u + j as f32 * transform.pixel_delta_v;
+            assert!((*ray.origin() - moved).length() < 1e-5);
+        }
+        // The height of the view is given, the width follows the aspect ratio.
+        assert!((transform.pixel_delta_v.length() * 10.0 - 3.0).abs() < 1e-5);
+        assert!((transform.pixel_delta_u.length() * 20.0 - 6.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_panoramic_projections() {
+        let mut sampler = SamplerType::new(SamplerKind::Independent, 1, 0);
+        sampler.start_pixel_sample((0, 0), 0);
+        let angle = |a: Vec3, b: Vec3| vec3::dot(vec3::unit_vector(a), vec3::unit_vector(b)).acos();
+
+        // Offsets of half a pixel put the sample on the pixel's corner.
+        let settings =
+            CameraSettings::new(2.0, 90.0, 0.0, 1.0).with_projection(Projection::Equirectangular);
+        let render = camera(40, settings);
+        let transform = render.camera.transform;
+        let center = render.get_ray(20, 10, (-0.5, -0.5), &mut sampler).unwrap();
+        assert!(angle(*center.direction(), -transform.w) < 1e-3);
+        for i in [0, 13, 39] {
+            let top = render.get_ray(i, 0, (0.0, -0.5), &mut sampler).unwrap();
+            assert!(angle(*top.direction(), transform.vup) < 1e-3);
+        }
+
+        for mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid] {
+            for fov in [120.0, 180.0, 220.0] {
+                let settings = CameraSettings::new(1.0, 90.0, 0.0, 1.0)
+                    .with_projection(Projection::Fisheye { mapping, fov });
+                let render = camera(20, settings);
+                let w = render.camera.transform.w;
+                let edge = render.get_ray(19, 10, (0.5, -0.5), &mut sampler).unwrap();
+                let half_fov = degrees_to_radians(fov) / 2.0;
+                assert!((angle(*edge.direction(), -w) - half_fov).abs() < 1e-3);
+                let top = render.get_ray(10, 0, (-0.5, -0.5), &mut sampler).unwrap();
+                assert!((angle(*top.direction(), -w) - half_fov).abs() < 1e-3);
+                // The corners are outside of the image circle.
+                assert!(render.get_ray(0, 0, (0.0, 0.0), &mut sampler).is_none());
+                assert!(render.get_ray(19, 19, (0.0, 0.0), &mut sampler).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_stereo() {
+        let stereo = Stereo::new(0.5, 6.0);
+        // Distance of `p` from the line of the ray.
+        let distance = |ray: &Ray, p: Point3| {
+            vec3::cross(p - *ray.origin(), vec3::unit_vector(*ray.direction())).length()
+        };
+        let eyes = |width: i32, settings: &CameraSettings| {
+            [Eye::Left, Eye::Right].map(|eye| {
+                let mut render = camera(width, settings.clone());
+                render.camera.eye = eye;
+                render.initialize();
+                render
+            })
+        };
+
+        // The eyes of the planar projections see the middle of the image on the center ray at
+        // the convergence distance.
+        let convergence = Point3::new(0.0, 0.0, -6.0);
+        for projection in [
+            Projection::Perspective,
+            Projection::Orthographic { height: 2.0 },
+        ] {
+            let settings = CameraSettings::new(1.0, 40.0, 0.0, 2.0)
+                .with_projection(projection)
+                .with_stereo(stereo);
+            let [left, right] = eyes(20, &settings);
+            let left = left.get_planar_ray(10, 10, (-0.5, -0.5), (0.5, 0.5));
+            let right = right.get_planar_ray(10, 10, (-0.5, -0.5), (0.5, 0.5));
+            assert!((*right.origin() - *left.origin() - Vec3::new(0.5, 0.0, 0.0)).length() < 1e-5);
+            assert!(distance(&left, convergence) < 1e-4);
+            assert!(distance(&right, convergence) < 1e-4);
         }
 
-        let unit_direction = vec3::unit_vector(*r.direction());
-        let a = 0.5 * (unit_direction[1] + 1.0);
-        (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.5, 0.7, 1.0)
+        // Omni-directional stereo, the eyes sit on a circle around the center, sideways to the
+        // view of every column, and still converge.
+        let settings = CameraSettings::new(2.0, 90.0, 0.0, 1.0)
+            .with_projection(Projection::Equirectangular)
+            .with_stereo(stereo);
+        let center = camera(40, settings.clone());
+        let [left, right] = eyes(40, &settings);
+        let mut sampler = SamplerType::new(SamplerKind::Independent, 1, 0);
+        sampler.start_pixel_sample((0, 0), 0);
+        for (i, j) in [(0, 10), (7, 4), (20, 10), (33, 17)] {
+            let view = center.get_ray(i, j, (0.0, 0.0), &mut sampler).unwrap();
+            let target = *view.origin() + stereo.convergence * vec3::unit_vector(*view.direction());
+            for eye in [&left, &right] {
+                let ray = eye.get_ray(i, j, (0.0, 0.0), &mut sampler).unwrap();
+                let offset = *ray.origin() - *view.origin();
+                assert!((offset.length() - 0.25).abs() < 1e-5);
+                assert!(vec3::dot(offset, *view.direction()).abs() < 1e-5);
+                assert!(distance(&ray, target) < 1e-4);
+            }
+        }
     }
 }
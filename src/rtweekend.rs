@@ -2,7 +2,18 @@
 
 // Constants
 
-use rand::Rng;
+use crate::vec3::Vec3;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::io;
+
+thread_local! {
+    // Every thread owns its generator so that a render can reseed it per pixel and per pass,
+    // which makes the random stream reproducible regardless of how rayon schedules the work.
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
 
 // Utility Functions
 
@@ -11,9 +22,23 @@ pub fn degrees_to_radians(degrees: f32) -> f32 {
     degrees * std::f32::consts::PI / 180.0
 }
 
+#[inline]
+pub fn seed(state: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(state));
+}
+
+#[inline]
+pub fn mix_seed(seed: u64, value: u64) -> u64 {
+    // SplitMix64 finalizer, good enough to decorrelate neighbouring pixel and pass indices.
+    let mut z = seed ^ value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
 #[inline]
 pub fn random_double() -> f32 {
-    rand::thread_rng().gen_range(0.0..1.0)
+    RNG.with(|rng| rng.borrow_mut().gen_range(0.0..1.0))
 }
 
 #[inline]
@@ -21,6 +46,23 @@ pub fn random_double_range(min: f32, max: f32) -> f32 {
     min + (max - min) * random_double()
 }
 
+// The error of every loader that meets a malformed file.
+pub fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// A JSON array of three numbers, as the scene and camera path files write points and colors.
+pub fn json_vec3(value: &Value) -> Option<Vec3> {
+    match value.as_array().map(Vec::as_slice) {
+        Some([x, y, z]) => Some(Vec3::new(
+            x.as_f64()? as f32,
+            y.as_f64()? as f32,
+            z.as_f64()? as f32,
+        )),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -42,8 +84,20 @@ mod tests {
             -0.42236972,
             -1.3718288,
         ];
-        for (degree, expect) in degrees.into_iter().zip(expected.into_iter()) {
+        for (degree, expect) in degrees.into_iter().zip(expected) {
             assert_eq!(degrees_to_radians(degree), expect);
         }
     }
+
+    #[test]
+    fn test_seeded_random_is_reproducible() {
+        seed(42);
+        let first: Vec<f32> = (0..8).map(|_| random_double()).collect();
+        seed(42);
+        let second: Vec<f32> = (0..8).map(|_| random_double()).collect();
+        assert_eq!(first, second);
+
+        assert_ne!(mix_seed(42, 0), mix_seed(42, 1));
+        assert_ne!(mix_seed(0, 42), mix_seed(1, 42));
+    }
 }
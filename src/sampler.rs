@@ -0,0 +1,563 @@
+use crate::rtweekend::{self, mix_seed};
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+// Source of the random numbers for one pixel sample. The dimensions are consumed in a fixed
+// order: the pixel offset, the lens position and then the scattering decisions of every bounce.
+pub trait Sampler {
+    fn start_pixel_sample(&mut self, pixel: (i32, i32), sample_index: u32);
+    fn get_1d(&mut self) -> f32;
+
+    #[inline]
+    fn get_2d(&mut self) -> (f32, f32) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SamplerKind {
+    #[default]
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+impl SamplerKind {
+    pub const ALL: [SamplerKind; 5] = [
+        SamplerKind::Independent,
+        SamplerKind::Stratified,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+        SamplerKind::BlueNoise,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SamplerKind::Independent => "independent",
+            SamplerKind::Stratified => "stratified",
+            SamplerKind::Halton => "halton",
+            SamplerKind::Sobol => "sobol",
+            SamplerKind::BlueNoise => "blue-noise",
+        }
+    }
+}
+
+impl Display for SamplerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for SamplerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SamplerKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| format!("unknown sampler `{}`", s))
+    }
+}
+
+pub enum SamplerType {
+    Independent(Independent),
+    Stratified(Stratified),
+    Halton(Halton),
+    Sobol(Sobol),
+    BlueNoise(BlueNoise),
+}
+
+// Uniform random numbers from `rtweekend::random_double`, the renderer seeds it per pixel.
+pub struct Independent;
+
+// Jittered sampling: every dimension is split into as many strata as there are samples per
+// pixel and each sample lands in its own, randomly permuted stratum.
+pub struct Stratified {
+    samples_per_pixel: u32,
+    state: SampleState,
+}
+
+// Halton sequence with random digit permutations per pixel.
+pub struct Halton {
+    state: SampleState,
+}
+
+// Sobol sequence with hash based Owen scrambling, padded in groups of four dimensions.
+pub struct Sobol {
+    state: SampleState,
+}
+
+// One Owen scrambled Sobol sequence shared by all pixels and toroidally shifted by a blue noise
+// mask, which pushes the remaining error to high frequencies where it is less visible.
+pub struct BlueNoise {
+    state: SampleState,
+    mask: Arc<Vec<f32>>,
+}
+
+struct SampleState {
+    seed: u64,
+    pixel: (i32, i32),
+    index: u32,
+    dimension: u32,
+}
+
+impl SampleState {
+    fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn start(&mut self, pixel: (i32, i32), index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    #[inline]
+    fn pixel_seed(&self) -> u64 {
+        let pixel = ((self.pixel.0 as u32 as u64) << 32) | self.pixel.1 as u32 as u64;
+        mix_seed(self.seed, pixel)
+    }
+
+    #[inline]
+    fn next_dimension(&mut self) -> u32 {
+        self.dimension += 1;
+        self.dimension - 1
+    }
+}
+
+impl SamplerType {
+    pub fn new(kind: SamplerKind, samples_per_pixel: u32, seed: u64) -> Self {
+        let state = SampleState::new(seed);
+        match kind {
+            SamplerKind::Independent => SamplerType::Independent(Independent),
+            SamplerKind::Stratified => SamplerType::Stratified(Stratified {
+                samples_per_pixel: samples_per_pixel.max(1),
+                state,
+            }),
+            SamplerKind::Halton => SamplerType::Halton(Halton { state }),
+            SamplerKind::Sobol => SamplerType::Sobol(Sobol { state }),
+            SamplerKind::BlueNoise => SamplerType::BlueNoise(BlueNoise {
+                state,
+                mask: blue_noise_mask(),
+            }),
+        }
+    }
+}
+
+impl Sampler for SamplerType {
+    fn start_pixel_sample(&mut self, pixel: (i32, i32), sample_index: u32) {
+        match self {
+            SamplerType::Independent(_) => {}
+            SamplerType::Stratified(sampler) => sampler.state.start(pixel, sample_index),
+            SamplerType::Halton(sampler) => sampler.state.start(pixel, sample_index),
+            SamplerType::Sobol(sampler) => sampler.state.start(pixel, sample_index),
+            SamplerType::BlueNoise(sampler) => sampler.state.start(pixel, sample_index),
+        }
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        match self {
+            SamplerType::Independent(_) => rtweekend::random_double(),
+
+            SamplerType::Stratified(sampler) => {
+                let state = &mut sampler.state;
+                let dimension_seed = mix_seed(state.pixel_seed(), state.next_dimension() as u64);
+                let count = sampler.samples_per_pixel;
+                let stratum =
+                    permutation_element(state.index % count, count, dimension_seed as u32);
+                let jitter = hash_to_float(mix_seed(dimension_seed, state.index as u64));
+                ((stratum as f32 + jitter) / count as f32).min(ONE_MINUS_EPSILON)
+            }
+
+            SamplerType::Halton(sampler) => {
+                let state = &mut sampler.state;
+                let dimension = state.next_dimension();
+                let dimension_seed = mix_seed(state.pixel_seed(), dimension as u64);
+                match PRIMES.get(dimension as usize) {
+                    Some(base) => {
+                        scrambled_radical_inverse(*base, state.index as u64, dimension_seed)
+                    }
+                    // The high dimensions of Halton are badly correlated anyway.
+                    None => hash_to_float(mix_seed(dimension_seed, state.index as u64)),
+                }
+            }
+
+            SamplerType::Sobol(sampler) => {
+                let state = &mut sampler.state;
+                let dimension = state.next_dimension();
+                owen_sobol(state.index, dimension, state.pixel_seed())
+            }
+
+            SamplerType::BlueNoise(sampler) => {
+                let state = &mut sampler.state;
+                let dimension = state.next_dimension();
+                let x = owen_sobol(state.index, dimension, state.seed);
+                // Shift the mask lookup per dimension so that the dimensions stay uncorrelated.
+                let offset = mix_seed(state.seed, dimension as u64);
+                let i = (state.pixel.0 as u64 + offset) as usize % BLUE_NOISE_SIZE;
+                let j = (state.pixel.1 as u64 + (offset >> 32)) as usize % BLUE_NOISE_SIZE;
+                let shifted = x + sampler.mask[j * BLUE_NOISE_SIZE + i];
+                (shifted - shifted.floor()).min(ONE_MINUS_EPSILON)
+            }
+        }
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        match self {
+            SamplerType::Stratified(sampler) => {
+                // Jitter on a grid of at least `samples_per_pixel` cells, in a random order.
+                let state = &mut sampler.state;
+                let dimension_seed = mix_seed(state.pixel_seed(), state.next_dimension() as u64);
+                state.dimension += 1;
+                let count = sampler.samples_per_pixel;
+                let nx = (count as f32).sqrt().ceil() as u32;
+                let ny = count.div_ceil(nx);
+                let stratum =
+                    permutation_element(state.index % count, nx * ny, dimension_seed as u32);
+                let jitter = mix_seed(dimension_seed, state.index as u64);
+                let x = (stratum % nx) as f32 + hash_to_float(jitter);
+                let y = (stratum / nx) as f32 + hash_to_float(mix_seed(jitter, 1));
+                (
+                    (x / nx as f32).min(ONE_MINUS_EPSILON),
+                    (y / ny as f32).min(ONE_MINUS_EPSILON),
+                )
+            }
+            _ => (self.get_1d(), self.get_1d()),
+        }
+    }
+}
+
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+#[inline]
+fn hash_to_float(hash: u64) -> f32 {
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+// Kensler's hash based permutation of `i` within `0..l`, "Correlated Multi-Jittered Sampling".
+fn permutation_element(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l.wrapping_sub(1);
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    (i.wrapping_add(p)) % l
+}
+
+fn scrambled_radical_inverse(base: u32, mut a: u64, seed: u64) -> f32 {
+    let inv_base = 1.0 / base as f32;
+    let mut inv_base_m = 1.0f32;
+    let mut reversed_digits = 0u64;
+    // Permute every digit depending on the digits before it, which also scrambles the
+    // infinite tail of zero digits.
+    while 1.0 - inv_base_m < 1.0 {
+        let next = a / base as u64;
+        let digit = (a - next * base as u64) as u32;
+        let digit_seed = mix_seed(seed, reversed_digits) as u32;
+        let digit = permutation_element(digit, base, digit_seed);
+        reversed_digits = reversed_digits * base as u64 + digit as u64;
+        inv_base_m *= inv_base;
+        a = next;
+    }
+    (inv_base_m * reversed_digits as f32).min(ONE_MINUS_EPSILON)
+}
+
+// Direction numbers of the first four Sobol dimensions (Joe and Kuo), the rest is padded.
+const SOBOL_DIRECTIONS: [[u32; 32]; 4] = [
+    sobol_directions(0, 0, [1, 0, 0]),
+    sobol_directions(1, 0, [1, 0, 0]),
+    sobol_directions(2, 1, [1, 3, 0]),
+    sobol_directions(3, 1, [1, 3, 1]),
+];
+
+const fn sobol_directions(s: usize, a: u32, m: [u32; 3]) -> [u32; 32] {
+    let mut v = [0u32; 32];
+    let mut i = 0;
+    while i < 32 {
+        if s == 0 {
+            // The first dimension is the van der Corput sequence.
+            v[i] = 1 << (31 - i);
+        } else if i < s {
+            v[i] = m[i] << (31 - i);
+        } else {
+            v[i] = v[i - s] ^ (v[i - s] >> s);
+            let mut k = 1;
+            while k < s {
+                if (a >> (s - 1 - k)) & 1 == 1 {
+                    v[i] ^= v[i - k];
+                }
+                k += 1;
+            }
+        }
+        i += 1;
+    }
+    v
+}
+
+#[inline]
+fn sobol(mut index: u32, dimension: usize) -> u32 {
+    let mut x = 0;
+    let mut bit = 0;
+    while index != 0 {
+        if index & 1 == 1 {
+            x ^= SOBOL_DIRECTIONS[dimension][bit];
+        }
+        index >>= 1;
+        bit += 1;
+    }
+    x
+}
+
+// Burley, "Practical Hash-based Owen Scrambling".
+#[inline]
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits();
+    x ^= x.wrapping_mul(0x3d20adea);
+    x = x.wrapping_add(seed);
+    x = x.wrapping_mul((seed >> 16) | 1);
+    x ^= x.wrapping_mul(0x05526c56);
+    x ^= x.wrapping_mul(0x53a22864);
+    x.reverse_bits()
+}
+
+fn owen_sobol(index: u32, dimension: u32, seed: u64) -> f32 {
+    // Each group of four dimensions walks the sequence in its own shuffled order.
+    let group_seed = mix_seed(seed, (dimension / 4) as u64 | 1 << 32);
+    let index = nested_uniform_scramble(index, group_seed as u32);
+    let x = sobol(index, (dimension % 4) as usize);
+    let x = nested_uniform_scramble(x, mix_seed(seed, dimension as u64) as u32);
+    (x as f32 / 4_294_967_296.0).min(ONE_MINUS_EPSILON)
+}
+
+const BLUE_NOISE_SIZE: usize = 64;
+
+fn blue_noise_mask() -> Arc<Vec<f32>> {
+    static MASK: OnceLock<Arc<Vec<f32>>> = OnceLock::new();
+    MASK.get_or_init(|| Arc::new(void_and_cluster(BLUE_NOISE_SIZE, 0x5eed)))
+        .clone()
+}
+
+// Ulichney's void-and-cluster method, returns a tileable `size` x `size` mask of evenly
+// distributed ranks in [0, 1).
+fn void_and_cluster(size: usize, seed: u64) -> Vec<f32> {
+    const SIGMA: f32 = 1.5;
+    const RADIUS: isize = 6;
+
+    let len = size * size;
+    let mut kernel = vec![];
+    for dy in -RADIUS..=RADIUS {
+        for dx in -RADIUS..=RADIUS {
+            let weight = (-((dx * dx + dy * dy) as f32) / (2.0 * SIGMA * SIGMA)).exp();
+            kernel.push((dx, dy, weight));
+        }
+    }
+
+    let splat = |energy: &mut [f32], index: usize, sign: f32| {
+        let (x, y) = ((index % size) as isize, (index / size) as isize);
+        for (dx, dy, weight) in &kernel {
+            let i = (x + dx).rem_euclid(size as isize) as usize;
+            let j = (y + dy).rem_euclid(size as isize) as usize;
+            energy[j * size + i] += sign * weight;
+        }
+    };
+    let tightest_cluster = |pattern: &[bool], energy: &[f32]| {
+        (0..len)
+            .filter(|index| pattern[*index])
+            .max_by(|a, b| energy[*a].total_cmp(&energy[*b]))
+            .unwrap()
+    };
+    let largest_void = |pattern: &[bool], energy: &[f32]| {
+        (0..len)
+            .filter(|index| !pattern[*index])
+            .min_by(|a, b| energy[*a].total_cmp(&energy[*b]))
+            .unwrap()
+    };
+
+    // Random initial pattern with a tenth of the points, relaxed until it's evenly spread.
+    let mut pattern = vec![false; len];
+    let mut energy = vec![0.0; len];
+    let ones = len / 10;
+    let mut placed = 0;
+    let mut counter = 0;
+    while placed < ones {
+        let index = (mix_seed(seed, counter) % len as u64) as usize;
+        counter += 1;
+        if !pattern[index] {
+            pattern[index] = true;
+            splat(&mut energy, index, 1.0);
+            placed += 1;
+        }
+    }
+    loop {
+        let cluster = tightest_cluster(&pattern, &energy);
+        pattern[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        splat(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; len];
+
+    // Rank the initial points by removing the tightest clusters first.
+    let mut reduced = pattern.clone();
+    let mut reduced_energy = energy.clone();
+    for rank in (0..ones).rev() {
+        let cluster = tightest_cluster(&reduced, &reduced_energy);
+        reduced[cluster] = false;
+        splat(&mut reduced_energy, cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+
+    // And fill the largest voids with the remaining ranks.
+    for rank in ones..len {
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        splat(&mut energy, void, 1.0);
+        ranks[void] = rank;
+    }
+
+    ranks
+        .into_iter()
+        .map(|rank| (rank as f32 + 0.5) / len as f32)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn integration_error(kind: SamplerKind, f: impl Fn(f32, f32) -> f32, expected: f32) -> f32 {
+        const PIXELS: i32 = 64;
+        const SAMPLES: u32 = 64;
+
+        let mut sampler = SamplerType::new(kind, SAMPLES, 7);
+        let mut squared_error = 0.0;
+        for pixel in 0..PIXELS {
+            rtweekend::seed(pixel as u64);
+            let mut sum = 0.0;
+            for index in 0..SAMPLES {
+                sampler.start_pixel_sample((pixel, 3), index);
+                // Skip to a later dimension pair to check more than the first two dimensions.
+                sampler.get_2d();
+                let (x, y) = sampler.get_2d();
+                assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+                sum += f(x, y);
+            }
+            let error = sum / SAMPLES as f32 - expected;
+            squared_error += error * error;
+        }
+        (squared_error / PIXELS as f32).sqrt()
+    }
+
+    #[test]
+    fn test_convergence() {
+        let quarter_disk = |x: f32, y: f32| if x * x + y * y < 1.0 { 1.0 } else { 0.0 };
+        let smooth = |x: f32, y: f32| x * y;
+
+        let reference = integration_error(
+            SamplerKind::Independent,
+            quarter_disk,
+            std::f32::consts::FRAC_PI_4,
+        );
+        for kind in &SamplerKind::ALL[1..] {
+            let error = integration_error(*kind, quarter_disk, std::f32::consts::FRAC_PI_4);
+            assert!(error < 0.5 * reference, "{} {} {}", kind, error, reference);
+        }
+
+        let reference = integration_error(SamplerKind::Independent, smooth, 0.25);
+        for kind in &SamplerKind::ALL[1..] {
+            let error = integration_error(*kind, smooth, 0.25);
+            assert!(error < 0.5 * reference, "{} {} {}", kind, error, reference);
+        }
+    }
+
+    #[test]
+    fn test_stratified_covers_every_stratum() {
+        let mut sampler = SamplerType::new(SamplerKind::Stratified, 16, 3);
+        let mut strata = [false; 16];
+        for index in 0..16 {
+            sampler.start_pixel_sample((5, 5), index);
+            strata[(sampler.get_1d() * 16.0) as usize] = true;
+        }
+        assert!(strata.iter().all(|stratum| *stratum));
+    }
+
+    #[test]
+    fn test_sobol_is_a_net() {
+        // Scrambling keeps the first 2^m points of a dimension one per interval of width 2^-m.
+        let mut intervals = [false; 32];
+        for index in 0..32 {
+            intervals[(owen_sobol(index, 5, 11) * 32.0) as usize] = true;
+        }
+        assert!(intervals.iter().all(|interval| *interval));
+    }
+
+    #[test]
+    fn test_permutation_element() {
+        for l in [1, 2, 7, 64, 100] {
+            let mut seen = vec![false; l as usize];
+            for i in 0..l {
+                seen[permutation_element(i, l, 0xdead_beef) as usize] = true;
+            }
+            assert!(seen.iter().all(|seen| *seen));
+        }
+    }
+
+    #[test]
+    fn test_blue_noise_mask_is_a_permutation() {
+        let mask = void_and_cluster(16, 1);
+        let mut ranks: Vec<usize> = mask.iter().map(|value| (value * 256.0) as usize).collect();
+        ranks.sort();
+        assert_eq!(ranks, (0..256).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_parse_kind() {
+        for kind in SamplerKind::ALL {
+            assert_eq!(kind.name().parse::<SamplerKind>(), Ok(kind));
+        }
+        assert!("uniform".parse::<SamplerKind>().is_err());
+    }
+}
@@ -0,0 +1,579 @@
+// JSON scene description, to place spheres and meshes with their materials and to animate them
+// with keyframes. A scene looks like
+//
+// {
+//   "camera": {"look_from": [13, 2, 3], "look_at": [0, 0, 0], "vfov": 20},
+//   "materials": {
+//     "ground": {"type": "lambertian", "albedo": [0.5, 0.5, 0.5]},
+//     "pulse": {"type": "metal", "keyframes": [
+//       {"time": 0, "albedo": [0.8, 0.2, 0.2], "fuzz": 0},
+//       {"time": 2, "albedo": [0.2, 0.2, 0.8], "fuzz": 0.5}]}
+//   },
+//   "objects": [
+//     {"type": "sphere", "radius": 1000, "center": [0, -1000, 0], "material": "ground"},
+//     {"type": "sphere", "radius": 1, "material": "pulse", "interpolation": "linear",
+//      "keyframes": [{"time": 0, "translation": [-2, 1, 0]}, {"time": 2, "translation": [2, 1, 0]}]},
+//     {"type": "mesh", "path": "bunny.ply", "scale": [10, 10, 10], "rotation": [0, 45, 0]}
+//   ],
+//   "lights": [
+//     {"type": "point", "position": [0, 4, 0], "intensity": 20, "range": 10},
+//     {"type": "spot", "position": [4, 4, 0], "look_at": [0, 0, 0], "intensity": [40, 30, 20],
+//      "inner_angle": 15, "outer_angle": 25},
+//     {"type": "directional", "direction": [-1, -2, -1], "irradiance": 3, "angular_diameter": 0.5}
+//   ]
+// }
+//
+// A physical sky with its sun replaces the gradient with
+//
+//   "sky": {"sun_elevation": 30, "sun_azimuth": 45, "turbidity": 3, "intensity": 0.05}
+//
+// or an HDR image of the surroundings, relative to the scene file, with
+//
+//   "environment": {"path": "studio.hdr", "rotation": 90, "intensity": 1}
+//
+// The index of refraction `ir` of a dielectric can also follow the wavelength, with the index and
+// Abbe number {"abbe": [1.52, 64]}, {"cauchy": [1.5046, 0.0042]}, {"sellmeier": {"b": [...],
+// "c": [...]}} or one of the glasses of `Ior::named` like "diamond". Colored glass absorbs light
+// inside, white turns into the given color after the distance:
+//
+//   "wine": {"type": "dielectric", "ir": 1.34,
+//            "absorption": {"color": [0.6, 0.05, 0.1], "distance": 0.5}}
+//
+// Dielectrics inside of others, like wine in a glass, overlap them a little and the glass gets the
+// higher "priority" to own the overlap. Rays keep track of 4 nested dielectrics at most, a fifth one
+// takes the place of the oldest of lowest priority.
+//
+// Metals and dielectrics can carry a thin film, its thickness in nanometers, and any material can
+// get a clear coat:
+//
+//   "bubble": {"type": "dielectric", "ir": 1, "film": {"thickness": 400, "ir": 1.33}},
+//   "paint": {"type": "coated", "ir": 1.5, "roughness": 0.1,
+//             "base": {"type": "lambertian", "albedo": [0.6, 0.05, 0.05]}}
+//
+// Colors can be given as a single number for gray. Keyframe times are in seconds. The camera can
+// carry keyframes as well, in the format of `CameraPath::load`.
+
+use crate::animation::Interpolation;
+use crate::bvh::Bvh;
+use crate::camera::{CameraPath, CameraSettings, Xform};
+use crate::color::Color;
+use crate::environment::{Background, Environment};
+use crate::gltf::{self, GltfCamera};
+use crate::hittable::{Hittable, Sphere};
+use crate::instance::Instance;
+use crate::light::{DirectionalLight, LightType, PointLight, SpotLight};
+use crate::material::{
+    Animated, AnimatedKind, Coated, Conductor, Dielectric, Ior, Lambertian, MaterialType, Metal,
+    Principled, RoughDielectric, ThinFilm,
+};
+use crate::mesh::TriangleMesh;
+use crate::rtweekend::{invalid_data, json_vec3};
+use crate::sky::Sky;
+use crate::vec3::{Point3, Vec3};
+use crate::world::World;
+use crate::{ply, stl};
+
+use serde_json::{Map, Value};
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+// The same as the default camera.
+const DEFAULT_ASPECT_RATIO: f32 = 16.0 / 9.0;
+
+pub struct Scene {
+    pub world: World,
+    pub camera: Option<GltfCamera>,
+    pub camera_path: Option<CameraPath>,
+}
+
+pub fn load(path: &Path) -> io::Result<Scene> {
+    let document: Value = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|err| invalid_data(&format!("invalid scene JSON: {err}")))?;
+    let base = path.parent().unwrap_or(Path::new(""));
+
+    let mut materials = HashMap::new();
+    if let Some(entries) = document["materials"].as_object() {
+        for (name, entry) in entries {
+            let material = parse_material(entry)
+                .map_err(|err| invalid_data(&format!("material `{name}`: {err}")))?;
+            materials.insert(name.as_str(), Arc::new(material));
+        }
+    }
+
+    let mut objects = vec![];
+    for (index, entry) in document["objects"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+    {
+        let material = match entry["material"].as_str() {
+            Some(name) => Some(
+                materials
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| invalid_data(&format!("unknown material `{name}`")))?,
+            ),
+            None => None,
+        };
+        let object = parse_object(entry, material, base)
+            .map_err(|err| invalid_data(&format!("object {index}: {err}")))?;
+        objects.push(object);
+    }
+    let mut world = World::new(objects);
+
+    for (index, entry) in document["lights"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+    {
+        let light =
+            parse_light(entry).map_err(|err| invalid_data(&format!("light {index}: {err}")))?;
+        world = world.with_light(light);
+    }
+
+    if !document["sky"].is_null() {
+        let entry = &document["sky"];
+        let sky = Sky::new(
+            number(entry, "sun_elevation", 45.0),
+            number(entry, "sun_azimuth", 0.0),
+            number(entry, "turbidity", 3.0),
+        )
+        .with_intensity(number(entry, "intensity", 0.05));
+        world = world
+            .with_light(sky.sun())
+            .with_background(Background::Sky(sky));
+    }
+
+    if !document["environment"].is_null() {
+        let entry = &document["environment"];
+        let path = entry["path"]
+            .as_str()
+            .ok_or_else(|| invalid_data("environment without path"))?;
+        let environment = Environment::load(&base.join(path))?
+            .with_rotation(number(entry, "rotation", 0.0))
+            .with_intensity(number(entry, "intensity", 1.0));
+        world = world.with_background(Background::Environment(Arc::new(environment)));
+    }
+
+    let (camera, camera_path) = match document["camera"].as_object() {
+        Some(entry) => {
+            let (camera, camera_path) = parse_camera(entry)?;
+            (Some(camera), camera_path)
+        }
+        None => (None, None),
+    };
+    Ok(Scene {
+        world,
+        camera,
+        camera_path,
+    })
+}
+
+fn parse_material(entry: &Value) -> io::Result<MaterialType> {
+    let kind = entry["type"]
+        .as_str()
+        .ok_or_else(|| invalid_data("material without type"))?;
+    let albedo = color(&entry["albedo"], Color::new(0.8, 0.8, 0.8))?;
+    let roughness = number(entry, "roughness", 0.0);
+    if let Some(keyframes) = entry["keyframes"].as_array() {
+        let kind = match kind {
+            "lambertian" => AnimatedKind::Lambertian,
+            "metal" => AnimatedKind::Metal,
+            "dielectric" => AnimatedKind::Dielectric,
+            _ => return Err(invalid_data(&format!("`{kind}` can't be animated"))),
+        };
+        let mut material = Animated::new(kind, interpolation(entry)?);
+        // Parameters without any key keep the value given with the material.
+        let keyed = |name: &str| keyframes.iter().any(|key| !key[name].is_null());
+        if !keyed("albedo") {
+            material = material.with_albedo(0.0, albedo);
+        }
+        if !keyed("fuzz") {
+            material = material.with_fuzz(0.0, number(entry, "fuzz", 0.0));
+        }
+        if !keyed("ir") {
+            material = material.with_ir(0.0, number(entry, "ir", 1.5));
+        }
+        for key in keyframes {
+            let time = time(key)?;
+            if !key["albedo"].is_null() {
+                material = material.with_albedo(time, color(&key["albedo"], albedo)?);
+            }
+            if let Some(fuzz) = key["fuzz"].as_f64() {
+                material = material.with_fuzz(time, fuzz as f32);
+            }
+            if let Some(ir) = key["ir"].as_f64() {
+                material = material.with_ir(time, ir as f32);
+            }
+        }
+        return Ok(MaterialType::Animated(Box::new(material)));
+    }
+
+    Ok(match kind {
+        "lambertian" => MaterialType::Lambertian(Lambertian::new(albedo)),
+        "metal" => {
+            let mut material = Metal::new(albedo, number(entry, "fuzz", 0.0));
+            if let Some(film) = thin_film(entry) {
+                material = material.with_thin_film(film);
+            }
+            MaterialType::Metal(material)
+        }
+        "dielectric" => {
+            let mut material = Dielectric::new(ior(&entry["ir"])?)
+                .with_priority(entry["priority"].as_i64().unwrap_or(0) as i32);
+            let absorption = &entry["absorption"];
+            if !absorption.is_null() {
+                material = material.with_absorption(
+                    color(&absorption["color"], Color::new(1.0, 1.0, 1.0))?,
+                    number(absorption, "distance", 1.0),
+                );
+            }
+            if let Some(film) = thin_film(entry) {
+                material = material.with_thin_film(film);
+            }
+            MaterialType::Dielectric(material)
+        }
+        "rough-dielectric" => {
+            MaterialType::RoughDielectric(RoughDielectric::new(number(entry, "ir", 1.5), roughness))
+        }
+        "gold" => MaterialType::Conductor(Conductor::gold(roughness)),
+        "copper" => MaterialType::Conductor(Conductor::copper(roughness)),
+        "aluminum" => MaterialType::Conductor(Conductor::aluminum(roughness)),
+        "silver" => MaterialType::Conductor(Conductor::silver(roughness)),
+        "principled" => MaterialType::Principled(Box::new(
+            Principled::new(albedo)
+                .with_metallic(number(entry, "metallic", 0.0))
+                .with_roughness(number(entry, "roughness", 0.5))
+                .with_transmission(number(entry, "transmission", 0.0), number(entry, "ir", 1.5)),
+        )),
+        "coated" => {
+            if entry["base"].is_null() {
+                return Err(invalid_data("coated material without base"));
+            }
+            MaterialType::Coated(Box::new(
+                Coated::new(parse_material(&entry["base"])?)
+                    .with_ior(number(entry, "ir", 1.5))
+                    .with_roughness(roughness),
+            ))
+        }
+        _ => return Err(invalid_data(&format!("unknown material type `{kind}`"))),
+    })
+}
+
+fn parse_object(
+    entry: &Value,
+    material: Option<Arc<MaterialType>>,
+    base: &Path,
+) -> io::Result<Arc<dyn Hittable>> {
+    let kind = entry["type"]
+        .as_str()
+        .ok_or_else(|| invalid_data("object without type"))?;
+    let path = || {
+        entry["path"]
+            .as_str()
+            .map(|path| base.join(path))
+            .ok_or_else(|| invalid_data("mesh without path"))
+    };
+    let object: Arc<dyn Hittable> = match kind {
+        "sphere" => {
+            let material = material.unwrap_or_else(|| {
+                Arc::new(MaterialType::Lambertian(Lambertian::new(Color::new(
+                    0.5, 0.5, 0.5,
+                ))))
+            });
+            Arc::new(Sphere::new(
+                point(&entry["center"], Point3::default())?,
+                number(entry, "radius", 1.0),
+                material,
+            ))
+        }
+        "mesh" => {
+            let path = path()?;
+            let extension = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            let mesh = match extension.as_str() {
+                "ply" => ply::parse(&fs::read(&path)?)?,
+                "stl" => stl::parse(&fs::read(&path)?)?,
+                _ => return Err(invalid_data(&format!("unknown mesh format `{extension}`"))),
+            };
+            let material = material.unwrap_or_else(|| Arc::new(mesh.default_material()));
+            Arc::new(TriangleMesh::new(mesh, material))
+        }
+        "gltf" => {
+            let scene = gltf::load(&path()?)?;
+            Arc::new(Bvh::new(scene.world.objects().to_vec()))
+        }
+        _ => return Err(invalid_data(&format!("unknown object type `{kind}`"))),
+    };
+
+    let mut instance = Instance::new(object, interpolation(entry)?);
+    if !entry["translation"].is_null() {
+        instance = instance.with_translation(0.0, point(&entry["translation"], Vec3::default())?);
+    }
+    if !entry["rotation"].is_null() {
+        instance = instance.with_rotation(0.0, point(&entry["rotation"], Vec3::default())?);
+    }
+    if !entry["scale"].is_null() {
+        instance = instance.with_scale(0.0, scale(&entry["scale"])?);
+    }
+    if let Some(keyframes) = entry["keyframes"].as_array() {
+        for key in keyframes {
+            let time = time(key)?;
+            if !key["translation"].is_null() {
+                instance =
+                    instance.with_translation(time, point(&key["translation"], Vec3::default())?);
+            }
+            if !key["rotation"].is_null() {
+                instance = instance.with_rotation(time, point(&key["rotation"], Vec3::default())?);
+            }
+            if !key["scale"].is_null() {
+                instance = instance.with_scale(time, scale(&key["scale"])?);
+            }
+        }
+    }
+    Ok(Arc::new(instance))
+}
+
+fn parse_light(entry: &Value) -> io::Result<LightType> {
+    let kind = entry["type"]
+        .as_str()
+        .ok_or_else(|| invalid_data("light without type"))?;
+    let position = point(&entry["position"], Point3::default())?;
+    // Spots point at a target or along a direction, straight down by default.
+    let direction = |default: Vec3| -> io::Result<Vec3> {
+        if entry["look_at"].is_null() {
+            point(&entry["direction"], default)
+        } else {
+            Ok(point(&entry["look_at"], Point3::default())? - position)
+        }
+    };
+    let range = entry["range"].as_f64().map(|range| range as f32);
+    Ok(match kind {
+        "point" => {
+            let light = PointLight::new(
+                position,
+                color(&entry["intensity"], Color::new(1.0, 1.0, 1.0))?,
+            );
+            LightType::Point(match range {
+                Some(range) => light.with_range(range),
+                None => light,
+            })
+        }
+        "spot" => {
+            let light = SpotLight::new(
+                position,
+                direction(Vec3::new(0.0, -1.0, 0.0))?,
+                color(&entry["intensity"], Color::new(1.0, 1.0, 1.0))?,
+                number(entry, "inner_angle", 20.0),
+                number(entry, "outer_angle", 30.0),
+            );
+            LightType::Spot(match range {
+                Some(range) => light.with_range(range),
+                None => light,
+            })
+        }
+        "directional" => LightType::Directional(
+            DirectionalLight::new(
+                direction(Vec3::new(0.0, -1.0, 0.0))?,
+                color(&entry["irradiance"], Color::new(1.0, 1.0, 1.0))?,
+            )
+            .with_angular_diameter(number(entry, "angular_diameter", 0.0)),
+        ),
+        _ => return Err(invalid_data(&format!("unknown light type `{kind}`"))),
+    })
+}
+
+fn parse_camera(entry: &Map<String, Value>) -> io::Result<(GltfCamera, Option<CameraPath>)> {
+    let value = |name: &str| entry.get(name).unwrap_or(&Value::Null);
+    let number = |name: &str, default: f32| value(name).as_f64().map_or(default, |x| x as f32);
+    let transform = Xform::new(
+        point(value("look_from"), Point3::new(13.0, 2.0, 3.0))?,
+        point(value("look_at"), Point3::default())?,
+        point(value("vup"), Vec3::new(0.0, 1.0, 0.0))?,
+    );
+    let settings = CameraSettings::new(
+        number("aspect_ratio", DEFAULT_ASPECT_RATIO),
+        number("vfov", 20.0),
+        number("defocus_angle", 0.0),
+        number(
+            "focus_distance",
+            (transform.look_from - transform.look_at).length(),
+        ),
+    );
+    let camera_path = match entry.get("keyframes") {
+        Some(_) => Some(CameraPath::from_json(
+            &Value::Object(entry.clone()),
+            &settings,
+        )?),
+        None => None,
+    };
+    Ok((
+        GltfCamera {
+            transform,
+            settings,
+        },
+        camera_path,
+    ))
+}
+
+fn interpolation(entry: &Value) -> io::Result<Interpolation> {
+    match entry["interpolation"].as_str() {
+        Some(name) => name.parse().map_err(|err: String| invalid_data(&err)),
+        None => Ok(Interpolation::default()),
+    }
+}
+
+fn time(key: &Value) -> io::Result<f32> {
+    key["time"]
+        .as_f64()
+        .map(|time| time as f32)
+        .ok_or_else(|| invalid_data("keyframe without time"))
+}
+
+fn thin_film(entry: &Value) -> Option<ThinFilm> {
+    let film = &entry["film"];
+    (!film.is_null())
+        .then(|| ThinFilm::new(number(film, "thickness", 400.0), number(film, "ir", 1.33)))
+}
+
+fn number(entry: &Value, name: &str, default: f32) -> f32 {
+    entry[name].as_f64().map_or(default, |x| x as f32)
+}
+
+// A number, or the coefficients of a dispersion formula.
+fn ior(value: &Value) -> io::Result<Ior> {
+    if value.is_null() {
+        return Ok(Ior::Constant(1.5));
+    }
+    if let Some(ir) = value.as_f64() {
+        return Ok(Ior::Constant(ir as f32));
+    }
+    if let Some(name) = value.as_str() {
+        return Ior::named(name).ok_or_else(|| invalid_data(&format!("unknown glass `{name}`")));
+    }
+    let numbers = |value: &Value| -> Option<Vec<f32>> {
+        value
+            .as_array()?
+            .iter()
+            .map(|x| x.as_f64().map(|x| x as f32))
+            .collect()
+    };
+    if !value["abbe"].is_null() {
+        return match numbers(&value["abbe"]).as_deref() {
+            Some(&[nd, vd]) => Ok(Ior::abbe(nd, vd)),
+            _ => Err(invalid_data("expected the index and the Abbe number")),
+        };
+    }
+    if !value["cauchy"].is_null() {
+        return match numbers(&value["cauchy"]).as_deref() {
+            Some(&[a, b]) => Ok(Ior::Cauchy { a, b }),
+            _ => Err(invalid_data("expected the two Cauchy coefficients")),
+        };
+    }
+    let sellmeier = &value["sellmeier"];
+    match (
+        numbers(&sellmeier["b"]).as_deref(),
+        numbers(&sellmeier["c"]).as_deref(),
+    ) {
+        (Some(&[b1, b2, b3]), Some(&[c1, c2, c3])) => Ok(Ior::Sellmeier {
+            b: [b1, b2, b3],
+            c: [c1, c2, c3],
+        }),
+        _ => Err(invalid_data(
+            "expected a number, a glass, abbe, cauchy or sellmeier coefficients",
+        )),
+    }
+}
+
+// Three numbers, or `default` when the value is missing.
+fn point(value: &Value, default: Vec3) -> io::Result<Vec3> {
+    if value.is_null() {
+        return Ok(default);
+    }
+    json_vec3(value).ok_or_else(|| invalid_data("expected three numbers"))
+}
+
+fn color(value: &Value, default: Color) -> io::Result<Color> {
+    match value.as_f64() {
+        Some(gray) => Ok(Color::new(gray as f32, gray as f32, gray as f32)),
+        None => point(value, default),
+    }
+}
+
+// A single number scales uniformly.
+fn scale(value: &Value) -> io::Result<Vec3> {
+    match value.as_f64() {
+        Some(s) => Ok(Vec3::new(s as f32, s as f32, s as f32)),
+        None => point(value, Vec3::new(1.0, 1.0, 1.0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::HitRecord;
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+
+    #[test]
+    fn test_load() {
+        // Apart from the files of other test runs at the same time.
+        let path =
+            std::env::temp_dir().join(format!("sansara_test_scene_{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{
+                "camera": {"look_from": [0, 0, 10], "look_at": [0, 0, 0], "vfov": 30,
+                    "keyframes": [{"time": 0, "look_from": [0, 0, 10], "look_at": [0, 0, 0]},
+                        {"time": 1, "look_from": [0, 0, 20], "look_at": [0, 0, 0]}]},
+                "materials": {"pulse": {"type": "lambertian", "interpolation": "linear",
+                    "keyframes": [{"time": 0, "albedo": [1, 0, 0]}, {"time": 1, "albedo": [0, 0, 1]}]}},
+                "objects": [{"type": "sphere", "material": "pulse", "interpolation": "linear",
+                    "keyframes": [{"time": 0, "translation": [0, 0, 0]},
+                        {"time": 1, "translation": [4, 0, 0]}]}]
+            }"#,
+        )
+        .unwrap();
+        let scene = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let camera = scene.camera.unwrap();
+        assert_eq!(camera.settings.vfov, 30.0);
+        assert_eq!(camera.settings.focus_distance, 10.0);
+        assert_eq!(scene.camera_path.unwrap().duration(), (0.0, 1.0));
+
+        let sphere = &scene.world.objects()[0];
+        let ray = Ray::new(Point3::new(4.0, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        let ray_t = Interval::new(0.001, f32::INFINITY);
+        let mut rec = HitRecord::new();
+        assert!(!sphere.hit(&ray, ray_t, &mut rec));
+        assert!(sphere.hit(&ray.with_time(1.0), ray_t, &mut rec));
+        let Some(MaterialType::Animated(material)) = rec.material.as_deref() else {
+            panic!("the material isn't animated");
+        };
+        assert!(matches!(material.at(0.5), MaterialType::Lambertian(_)));
+
+        let unknown = std::env::temp_dir().join(format!(
+            "sansara_test_unknown_material_{}.json",
+            std::process::id()
+        ));
+        fs::write(
+            &unknown,
+            r#"{"objects": [{"type": "sphere", "material": "x"}]}"#,
+        )
+        .unwrap();
+        assert!(load(&unknown).is_err());
+        fs::remove_file(&unknown).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+// Analytic daylight, the sky model of Preetham et al., "A Practical Analytic Model for Daylight",
+// with the sun as a directional light of matching color and strength.
+
+use crate::color::Color;
+use crate::light::{DirectionalLight, LightType};
+use crate::vec3::{self, Vec3};
+
+use std::f32::consts::{FRAC_PI_2, PI};
+use std::hash::Hasher;
+
+// Angular diameter of the sun, in degrees.
+const SUN_DIAMETER: f32 = 0.53;
+// Illuminance of the sun above the atmosphere, in kilolux.
+const SOLAR_ILLUMINANCE: f32 = 128.0;
+
+// Clear sky over a +y up world, in kcd/m² times the intensity.
+#[derive(Debug, Clone)]
+pub struct Sky {
+    // Unit direction towards the sun, kept above the horizon.
+    sun_direction: Vec3,
+    turbidity: f32,
+    intensity: f32,
+    // Zenith xyY and the Perez coefficients A to E of each of x, y and Y.
+    zenith: [f32; 3],
+    perez: [[f32; 5]; 3],
+    // Perez function towards the zenith, the sky at the zenith divided by it.
+    zenith_perez: [f32; 3],
+}
+
+impl Sky {
+    // The sun `elevation` degrees above the horizon, turned `azimuth` degrees from +z towards +x.
+    // The turbidity goes from 2 for a very clear sky to about 10 for haze.
+    pub fn new(elevation: f32, azimuth: f32, turbidity: f32) -> Self {
+        let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+        let sun_direction = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            elevation.cos() * azimuth.cos(),
+        );
+        let t = turbidity.clamp(1.7, 10.0);
+        // The model isn't made for a sun below the horizon.
+        let theta_s = (FRAC_PI_2 - elevation).clamp(0.0, FRAC_PI_2 - 0.01);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let chromaticity = |m: [[f32; 4]; 3]| {
+            let theta = [theta_s.powi(3), theta_s * theta_s, theta_s, 1.0];
+            let row = |r: [f32; 4]| r.iter().zip(theta).map(|(a, b)| a * b).sum::<f32>();
+            t * t * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let perez = [
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+        ];
+        let zenith_perez = perez.map(|coefficients| perez_function(coefficients, 0.0, theta_s));
+        Self {
+            sun_direction,
+            turbidity: t,
+            // One unit is 20 kcd/m², which leaves a white surface in the noon sun close to 1.
+            intensity: 0.05,
+            zenith: [zenith_x, zenith_y, zenith_luminance],
+            perez,
+            zenith_perez,
+        }
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn fingerprint(&self, state: &mut dyn Hasher) {
+        vec3::fingerprint(&self.sun_direction, state);
+        state.write_u32(self.turbidity.to_bits());
+        state.write_u32(self.intensity.to_bits());
+    }
+
+    #[inline]
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun_direction
+    }
+
+    // Radiance of the sky without the sun, the horizon continues below it.
+    pub fn radiance(&self, direction: Vec3) -> Color {
+        let mut direction = vec3::unit_vector(direction);
+        if direction[1] < 0.0 {
+            let horizon = Vec3::new(direction[0], 0.0, direction[2]);
+            direction = if horizon.near_zero() {
+                Vec3::new(0.0, 0.0, 1.0)
+            } else {
+                vec3::unit_vector(horizon)
+            };
+        }
+        let theta = direction[1].clamp(0.0, 1.0).acos();
+        let gamma = vec3::dot(direction, self.sun_direction)
+            .clamp(-1.0, 1.0)
+            .acos();
+        let [x, y, luminance] = [0, 1, 2].map(|i| {
+            self.zenith[i] * perez_function(self.perez[i], theta, gamma) / self.zenith_perez[i]
+        });
+        xyy_to_rgb(x, y, luminance) * self.intensity
+    }
+
+    // The sun, reddened by the air it shines through.
+    pub fn sun(&self) -> LightType {
+        let elevation = self.sun_direction[1].asin();
+        let irradiance = if elevation <= 0.0 {
+            Color::default()
+        } else {
+            // Relative optical mass of the air towards the sun (Kasten), with Rayleigh and
+            // aerosol extinction at the wavelengths of red, green and blue in micrometers.
+            let zenith_angle = (FRAC_PI_2 - elevation).to_degrees();
+            let mass = 1.0 / (elevation.sin() + 0.15 * (93.885 - zenith_angle).powf(-1.253));
+            let beta = 0.04608 * self.turbidity - 0.04586;
+            let transmittance = |lambda: f32| {
+                let rayleigh = 0.008735 * lambda.powf(-4.08);
+                let aerosol = beta * lambda.powf(-1.3);
+                (-(rayleigh + aerosol) * mass).exp()
+            };
+            Color::new(
+                transmittance(0.68),
+                transmittance(0.55),
+                transmittance(0.44),
+            ) * (SOLAR_ILLUMINANCE * self.intensity)
+        };
+        LightType::Directional(
+            DirectionalLight::new(-self.sun_direction, irradiance)
+                .with_angular_diameter(SUN_DIAMETER),
+        )
+    }
+}
+
+// Relative brightness of the sky at `theta` from the zenith and `gamma` from the sun.
+fn perez_function(coefficients: [f32; 5], theta: f32, gamma: f32) -> f32 {
+    let [a, b, c, d, e] = coefficients;
+    // Directions at the horizon are kept just above it.
+    let cos_theta = theta.cos().max(0.01);
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+// CIE xyY to linear sRGB.
+fn xyy_to_rgb(x: f32, y: f32, luminance: f32) -> Color {
+    if y <= 0.0 {
+        return Color::default();
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Color::new(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::Light;
+    use crate::vec3::Point3;
+
+    #[test]
+    fn test_sky() {
+        let sky = Sky::new(30.0, 90.0, 3.0);
+        assert!((sky.sun_direction() - Vec3::new(0.866, 0.5, 0.0)).length() < 1e-3);
+
+        // Blue overhead, brighter around the sun than away from it.
+        let zenith = sky.radiance(Vec3::new(0.0, 1.0, 0.0));
+        assert!(zenith[2] > zenith[0]);
+        let near_sun = sky.radiance(Vec3::new(0.8, 0.55, 0.0));
+        let away = sky.radiance(Vec3::new(-0.8, 0.55, 0.0));
+        assert!(near_sun[1] > 2.0 * away[1]);
+        // Below the horizon the sky looks like at the horizon.
+        assert_eq!(
+            sky.radiance(Vec3::new(0.0, -1.0, 1.0)),
+            sky.radiance(Vec3::new(0.0, 0.0, 1.0))
+        );
+
+        // A low sun is dimmer and redder than a high one.
+        let sun = |elevation: f32| {
+            Sky::new(elevation, 0.0, 3.0)
+                .sun()
+                .sample_li(Point3::default(), (0.5, 0.5))
+                .unwrap()
+                .li
+        };
+        let (noon, evening) = (sun(80.0), sun(5.0));
+        assert!(noon[1] > evening[1]);
+        assert!(evening[0] / evening[2] > noon[0] / noon[2]);
+        assert_eq!(sun(-10.0), Color::default());
+    }
+}
@@ -0,0 +1,236 @@
+// Spectral rendering with hero wavelength sampling (Wilkie et al., "Hero Wavelength Spectral
+// Sampling"). Every camera ray carries a few wavelengths, the values of `Color` along its path
+// are the radiance at those wavelengths instead of red, green and blue.
+
+use crate::color::Color;
+
+// Range of the sampled wavelengths, in nanometers.
+pub const WAVELENGTH_MIN: f32 = 360.0;
+pub const WAVELENGTH_MAX: f32 = 830.0;
+// Wavelengths standing in for red, green and blue.
+pub const RGB_WAVELENGTHS: [f32; 3] = [650.0, 550.0, 450.0];
+// Wavelengths per ray, one for each component of a `Color`.
+const SAMPLES: usize = 3;
+// Integral of the luminance matching function over the range.
+const CIE_Y_INTEGRAL: f32 = 106.922;
+
+// Smits, "An RGB to Spectrum Conversion for Reflectances": spectra of white and the primary and
+// secondary colors in ten bins from 380 to 720nm, smooth enough to mix into any color.
+const SMITS_WHITE: [f32; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [f32; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [f32; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [f32; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [f32; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f32; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_BLUE: [f32; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+// Wavelengths a camera ray is traced at. The first one is the hero, the others are spread evenly
+// from it, each distributed like the hero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampledWavelengths {
+    lambda: [f32; SAMPLES],
+    pdf: [f32; SAMPLES],
+    // Only the hero is left, after something that sends each wavelength its own way.
+    secondary_terminated: bool,
+}
+
+impl SampledWavelengths {
+    // Picks the wavelengths with the density of `visible_pdf`, which follows the sensitivity of
+    // the eye.
+    pub fn sample_visible(u: f32) -> Self {
+        let mut lambda = [0.0; SAMPLES];
+        let mut pdf = [0.0; SAMPLES];
+        for i in 0..SAMPLES {
+            let u = (u + i as f32 / SAMPLES as f32).fract();
+            lambda[i] = sample_visible_wavelength(u);
+            pdf[i] = visible_pdf(lambda[i]);
+        }
+        Self {
+            lambda,
+            pdf,
+            secondary_terminated: false,
+        }
+    }
+
+    #[inline]
+    pub fn hero(&self) -> f32 {
+        self.lambda[0]
+    }
+
+    #[inline]
+    pub fn lambda(&self) -> [f32; SAMPLES] {
+        self.lambda
+    }
+
+    #[inline]
+    pub fn secondary_terminated(&self) -> bool {
+        self.secondary_terminated
+    }
+
+    // Keeps only the hero wavelength. Returns the weight for the rest of the path, which counts
+    // the hero for all of them.
+    pub fn terminate_secondary(&mut self) -> Color {
+        self.secondary_terminated = true;
+        Color::new(SAMPLES as f32, 0.0, 0.0)
+    }
+
+    // Values of an RGB reflectance, or radiance, at the wavelengths.
+    pub fn lift(&self, rgb: Color) -> Color {
+        Color::new(
+            rgb_to_spectrum(rgb, self.lambda[0]),
+            rgb_to_spectrum(rgb, self.lambda[1]),
+            rgb_to_spectrum(rgb, self.lambda[2]),
+        )
+    }
+
+    // Linear sRGB of the radiance `values` at the wavelengths. The equal energy spectrum comes
+    // out white.
+    pub fn to_rgb(&self, values: Color) -> Color {
+        let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+        for i in 0..SAMPLES {
+            if self.pdf[i] == 0.0 {
+                continue;
+            }
+            let weight = values[i] / self.pdf[i];
+            x += weight * cie_x(self.lambda[i]);
+            y += weight * cie_y(self.lambda[i]);
+            z += weight * cie_z(self.lambda[i]);
+        }
+        let scale = 1.0 / (SAMPLES as f32 * CIE_Y_INTEGRAL);
+        let (x, y, z) = (x * scale, y * scale, z * scale);
+        // XYZ to linear sRGB, with the rows scaled to white balance the equal energy white.
+        Color::new(
+            2.6999 * x - 1.2807 * y - 0.4154 * z,
+            -1.0201 * x + 1.9749 * y + 0.0437 * z,
+            0.0613 * x - 0.2246 * y + 1.1639 * z,
+        )
+    }
+}
+
+// Density of the visible wavelengths over the range (Radziszewski et al.), close to the
+// luminance matching function.
+pub fn visible_pdf(lambda: f32) -> f32 {
+    if !(WAVELENGTH_MIN..=WAVELENGTH_MAX).contains(&lambda) {
+        return 0.0;
+    }
+    0.003939804 / (0.0072 * (lambda - 538.0)).cosh().powi(2)
+}
+
+fn sample_visible_wavelength(u: f32) -> f32 {
+    538.0 - 138.88889 * (0.85691062 - 1.827502 * u).atanh()
+}
+
+// Piecewise Gaussian fits of the CIE 1931 color matching functions, from Wyman et al., "Simple
+// Analytic Approximations to the CIE XYZ Color Matching Functions".
+fn gaussian(lambda: f32, mu: f32, sigma_below: f32, sigma_above: f32) -> f32 {
+    let sigma = if lambda < mu {
+        sigma_below
+    } else {
+        sigma_above
+    };
+    let t = (lambda - mu) / sigma;
+    (-0.5 * t * t).exp()
+}
+
+pub fn cie_x(lambda: f32) -> f32 {
+    1.056 * gaussian(lambda, 599.8, 37.9, 31.0) + 0.362 * gaussian(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * gaussian(lambda, 501.1, 20.4, 26.2)
+}
+
+pub fn cie_y(lambda: f32) -> f32 {
+    0.821 * gaussian(lambda, 568.8, 46.9, 40.5) + 0.286 * gaussian(lambda, 530.9, 16.3, 31.1)
+}
+
+pub fn cie_z(lambda: f32) -> f32 {
+    1.217 * gaussian(lambda, 437.0, 11.8, 36.0) + 0.681 * gaussian(lambda, 459.0, 26.0, 13.8)
+}
+
+// The Smits spectrum of `rgb` at `lambda`, linear between the centers of the bins and constant
+// past the first and the last one.
+pub fn rgb_to_spectrum(rgb: Color, lambda: f32) -> f32 {
+    let (r, g, b) = (rgb[0].max(0.0), rgb[1].max(0.0), rgb[2].max(0.0));
+    let at = |spectrum: &[f32; 10]| {
+        let x = ((lambda - 380.0) / 34.0 - 0.5).clamp(0.0, 9.0);
+        let i = (x as usize).min(8);
+        let t = x - i as f32;
+        spectrum[i] * (1.0 - t) + spectrum[i + 1] * t
+    };
+    // White for the smallest component, the secondary color for what the two larger ones share
+    // and the primary color for the rest of the largest.
+    if r <= g && r <= b {
+        let white = r * at(&SMITS_WHITE);
+        if g <= b {
+            white + (g - r) * at(&SMITS_CYAN) + (b - g) * at(&SMITS_BLUE)
+        } else {
+            white + (b - r) * at(&SMITS_CYAN) + (g - b) * at(&SMITS_GREEN)
+        }
+    } else if g <= r && g <= b {
+        let white = g * at(&SMITS_WHITE);
+        if r <= b {
+            white + (r - g) * at(&SMITS_MAGENTA) + (b - r) * at(&SMITS_BLUE)
+        } else {
+            white + (b - g) * at(&SMITS_MAGENTA) + (r - b) * at(&SMITS_RED)
+        }
+    } else {
+        let white = b * at(&SMITS_WHITE);
+        if r <= g {
+            white + (r - b) * at(&SMITS_YELLOW) + (g - r) * at(&SMITS_GREEN)
+        } else {
+            white + (g - b) * at(&SMITS_YELLOW) + (r - g) * at(&SMITS_RED)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Mean over many wavelength samples of a lifted color, back in RGB.
+    fn round_trip(rgb: Color) -> Color {
+        let n = 4096;
+        let mut sum = Color::default();
+        for k in 0..n {
+            let wavelengths = SampledWavelengths::sample_visible((k as f32 + 0.5) / n as f32);
+            sum += wavelengths.to_rgb(wavelengths.lift(rgb));
+        }
+        sum / n as f32
+    }
+
+    #[test]
+    fn test_spectral_round_trip() {
+        let wavelengths = SampledWavelengths::sample_visible(0.3);
+        for i in 0..SAMPLES {
+            assert!((WAVELENGTH_MIN..=WAVELENGTH_MAX).contains(&wavelengths.lambda[i]));
+        }
+
+        // The density of the wavelengths integrates to one.
+        let steps = 4700;
+        let width = (WAVELENGTH_MAX - WAVELENGTH_MIN) / steps as f32;
+        let integral: f32 = (0..steps)
+            .map(|i| visible_pdf(WAVELENGTH_MIN + (i as f32 + 0.5) * width) * width)
+            .sum();
+        assert!((integral - 1.0).abs() < 0.01);
+
+        let white = round_trip(Color::new(1.0, 1.0, 1.0));
+        assert!((white - Color::new(1.0, 1.0, 1.0)).length() < 0.02);
+        // Colors keep their hue, even if not exactly their saturation.
+        let red = round_trip(Color::new(0.8, 0.1, 0.1));
+        assert!(red[0] > 3.0 * red[1] && red[0] > 3.0 * red[2]);
+        let blue = round_trip(Color::new(0.1, 0.2, 0.8));
+        assert!(blue[2] > 2.0 * blue[0] && blue[2] > blue[1]);
+    }
+}
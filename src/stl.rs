@@ -0,0 +1,129 @@
+// Reader for STL meshes, both ASCII and binary. STL stores every triangle with its own three
+// vertices and no further attributes, the facet normals are ignored in favor of the winding.
+
+use crate::mesh::{Mesh, TriangleMesh};
+use crate::rtweekend::invalid_data;
+use crate::vec3::Point3;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Header and triangle count of binary files, followed by 50 bytes for every triangle.
+const HEADER_SIZE: usize = 84;
+const TRIANGLE_SIZE: usize = 50;
+
+// Loads a mesh with the default material of `Mesh`.
+pub fn load(path: &Path) -> io::Result<TriangleMesh> {
+    let mesh = parse(&fs::read(path)?)?;
+    let material = mesh.default_material();
+    Ok(TriangleMesh::new(mesh, material))
+}
+
+pub fn parse(bytes: &[u8]) -> io::Result<Mesh> {
+    let positions = if is_binary(bytes) {
+        parse_binary(bytes)
+    } else {
+        parse_ascii(bytes)?
+    };
+    let indices = (0..positions.len() as u32 / 3)
+        .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+        .collect();
+    Ok(Mesh::new(positions, indices))
+}
+
+// Binary files may start with "solid" as well, so they are told apart by their size.
+fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.len() < HEADER_SIZE {
+        return false;
+    }
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    bytes.len() == HEADER_SIZE + count * TRIANGLE_SIZE
+        || !bytes.trim_ascii_start().starts_with(b"solid")
+}
+
+fn parse_binary(bytes: &[u8]) -> Vec<Point3> {
+    let mut positions = vec![];
+    for triangle in bytes[HEADER_SIZE..].chunks_exact(TRIANGLE_SIZE) {
+        let float = |i: usize| {
+            let b = &triangle[4 * i..4 * i + 4];
+            f32::from_le_bytes([b[0], b[1], b[2], b[3]])
+        };
+        // The normal comes first.
+        for vertex in 1..4 {
+            positions.push(Point3::new(
+                float(3 * vertex),
+                float(3 * vertex + 1),
+                float(3 * vertex + 2),
+            ));
+        }
+    }
+    positions
+}
+
+fn parse_ascii(bytes: &[u8]) -> io::Result<Vec<Point3>> {
+    let text =
+        std::str::from_utf8(bytes).map_err(|_| invalid_data("ASCII STL isn't valid UTF-8"))?;
+    let mut words = text.split_ascii_whitespace();
+    let mut positions = vec![];
+    while let Some(word) = words.next() {
+        if word != "vertex" {
+            continue;
+        }
+        let mut coordinate = || -> io::Result<f32> {
+            let word = words
+                .next()
+                .ok_or_else(|| invalid_data("unexpected end of STL data"))?;
+            word.parse()
+                .map_err(|_| invalid_data(&format!("invalid STL coordinate `{word}`")))
+        };
+        positions.push(Point3::new(coordinate()?, coordinate()?, coordinate()?));
+    }
+    if positions.len() % 3 != 0 {
+        return Err(invalid_data("STL facet without three vertices"));
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIANGLE: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+    #[test]
+    fn test_ascii() {
+        let text = "solid test
+            facet normal 0 0 1
+                outer loop
+                    vertex 0 0 0
+                    vertex 1 0 0
+                    vertex 0 1 0
+                endloop
+            endfacet
+        endsolid test";
+        let mesh = parse(text.as_bytes()).unwrap();
+        assert_eq!(mesh.triangle_count(), 1);
+        assert_eq!(mesh.positions()[1], Point3::new(1.0, 0.0, 0.0));
+        assert!(parse(b"solid test facet normal 0 0 1 outer loop vertex 0 0").is_err());
+    }
+
+    #[test]
+    fn test_binary() {
+        // Header starting with "solid" like many exporters write it.
+        let mut bytes = b"solid binary".to_vec();
+        bytes.resize(80, 0);
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        for _ in 0..2 {
+            for value in [0.0f32, 0.0, 1.0].iter().chain(TRIANGLE.iter().flatten()) {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.extend_from_slice(&0u16.to_le_bytes());
+        }
+        assert!(is_binary(&bytes));
+        let mesh = parse(&bytes).unwrap();
+        assert_eq!(mesh.triangle_count(), 2);
+        assert_eq!(mesh.indices()[1], [3, 4, 5]);
+        assert_eq!(mesh.positions()[5], Point3::new(0.0, 1.0, 0.0));
+    }
+}
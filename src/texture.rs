@@ -0,0 +1,236 @@
+use crate::color::Color;
+use crate::vec3::{self, Point3};
+
+use std::hash::Hasher;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f32, v: f32, p: Point3) -> Color;
+}
+
+pub enum TextureType {
+    Solid(Color),
+    Checker(Checker),
+    Image(ImageTexture),
+}
+
+// 3D checker pattern, alternates between two textures every `scale` units along each axis.
+pub struct Checker {
+    scale: f32,
+    even: Box<TextureType>,
+    odd: Box<TextureType>,
+}
+
+impl Checker {
+    pub fn new(scale: f32, even: impl Into<TextureType>, odd: impl Into<TextureType>) -> Self {
+        Self {
+            scale,
+            even: Box::new(even.into()),
+            odd: Box::new(odd.into()),
+        }
+    }
+}
+
+// Bilinearly filtered image in linear RGB, repeated outside of [0, 1]. The first row is the top of
+// the image, at v = 1.
+#[derive(Clone)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Arc<Vec<Color>>,
+}
+
+impl ImageTexture {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        assert_eq!(pixels.len(), width * height);
+        Self {
+            width,
+            height,
+            pixels: Arc::new(pixels),
+        }
+    }
+
+    // Decodes an 8 or 16 bit image, color textures are usually sRGB encoded while data like
+    // roughness is stored linearly.
+    pub fn decode(bytes: &[u8], srgb: bool) -> io::Result<Self> {
+        let image = image::load_from_memory(bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+            .into_rgb32f();
+        let decode = |c: f32| if srgb { srgb_to_linear(c) } else { c };
+        let pixels = image
+            .pixels()
+            .map(|pixel| Color::new(decode(pixel[0]), decode(pixel[1]), decode(pixel[2])))
+            .collect();
+        Ok(Self::new(
+            image.width() as usize,
+            image.height() as usize,
+            pixels,
+        ))
+    }
+
+    pub fn load(path: &Path, srgb: bool) -> io::Result<Self> {
+        Self::decode(&std::fs::read(path)?, srgb)
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Row major pixels, the top row first.
+    #[inline]
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    // Gray image of a single channel, e.g. roughness packed into the green channel.
+    pub fn channel(&self, channel: usize) -> Self {
+        self.map(|color| Color::new(color[channel], color[channel], color[channel]))
+    }
+
+    // Multiplies every pixel by `factor`.
+    pub fn scale(&self, factor: Color) -> Self {
+        self.map(|color| color * factor)
+    }
+
+    fn map(&self, f: impl Fn(Color) -> Color) -> Self {
+        Self::new(
+            self.width,
+            self.height,
+            self.pixels.iter().map(|color| f(*color)).collect(),
+        )
+    }
+
+    fn texel(&self, i: i64, j: i64) -> Color {
+        let i = i.rem_euclid(self.width as i64) as usize;
+        let j = j.rem_euclid(self.height as i64) as usize;
+        self.pixels[j * self.width + i]
+    }
+
+    fn sample(&self, u: f32, v: f32) -> Color {
+        if self.pixels.is_empty() {
+            return Color::default();
+        }
+        // Texel centers are at half integer coordinates.
+        let x = u * self.width as f32 - 0.5;
+        let y = (1.0 - v) * self.height as f32 - 0.5;
+        let (i, j) = (x.floor(), y.floor());
+        let (s, t) = (x - i, y - j);
+        let (i, j) = (i as i64, j as i64);
+        (1.0 - t) * ((1.0 - s) * self.texel(i, j) + s * self.texel(i + 1, j))
+            + t * ((1.0 - s) * self.texel(i, j + 1) + s * self.texel(i + 1, j + 1))
+    }
+}
+
+#[inline]
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+impl From<Color> for TextureType {
+    fn from(color: Color) -> Self {
+        TextureType::Solid(color)
+    }
+}
+
+// Scalar parameters are textures as well, gray with the value in every channel.
+impl From<f32> for TextureType {
+    fn from(value: f32) -> Self {
+        TextureType::Solid(Color::new(value, value, value))
+    }
+}
+
+impl TextureType {
+    // Scalar value of the texture, taken from its first channel.
+    #[inline]
+    pub fn scalar(&self, u: f32, v: f32, p: Point3) -> f32 {
+        self.value(u, v, p)[0]
+    }
+
+    pub fn fingerprint(&self, state: &mut dyn Hasher) {
+        match self {
+            TextureType::Solid(color) => {
+                state.write(b"solid");
+                vec3::fingerprint(color, state);
+            }
+            TextureType::Checker(checker) => {
+                state.write(b"checker");
+                state.write_u32(checker.scale.to_bits());
+                checker.even.fingerprint(state);
+                checker.odd.fingerprint(state);
+            }
+            TextureType::Image(image) => {
+                state.write(b"image");
+                state.write_usize(image.width);
+                state.write_usize(image.height);
+                for color in image.pixels.iter() {
+                    vec3::fingerprint(color, state);
+                }
+            }
+        }
+    }
+}
+
+impl Texture for TextureType {
+    fn value(&self, u: f32, v: f32, p: Point3) -> Color {
+        match self {
+            TextureType::Solid(color) => *color,
+            TextureType::Checker(checker) => {
+                let cell = |x: f32| (x / checker.scale).floor() as i64;
+                if (cell(p[0]) + cell(p[1]) + cell(p[2])) % 2 == 0 {
+                    checker.even.value(u, v, p)
+                } else {
+                    checker.odd.value(u, v, p)
+                }
+            }
+            TextureType::Image(image) => image.sample(u, v),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checker() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let black = Color::new(0.0, 0.0, 0.0);
+        let checker = TextureType::Checker(Checker::new(0.5, white, black));
+        assert_eq!(checker.value(0.0, 0.0, Point3::new(0.1, 0.1, 0.1)), white);
+        assert_eq!(checker.value(0.0, 0.0, Point3::new(0.6, 0.1, 0.1)), black);
+        assert_eq!(checker.value(0.0, 0.0, Point3::new(-0.1, 0.1, 0.1)), black);
+        assert_eq!(checker.value(0.0, 0.0, Point3::new(-0.6, 0.1, 0.1)), white);
+        assert_eq!(
+            TextureType::from(0.25).scalar(0.5, 0.5, Point3::default()),
+            0.25
+        );
+    }
+
+    #[test]
+    fn test_image() {
+        // 2x1 image, black on the left and orange on the right.
+        let image = ImageTexture::new(2, 1, vec![Color::default(), Color::new(1.0, 0.5, 0.0)]);
+        assert_eq!(image.sample(0.25, 0.5), Color::default());
+        assert_eq!(image.sample(0.75, 0.5), Color::new(1.0, 0.5, 0.0));
+        assert_eq!(image.sample(0.5, 0.5), Color::new(0.5, 0.25, 0.0));
+        // Repeats, the left edge blends with the right one.
+        assert_eq!(image.sample(0.0, 0.5), Color::new(0.5, 0.25, 0.0));
+        assert_eq!(
+            image.channel(1).sample(0.75, 0.1),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert!((srgb_to_linear(0.5) - 0.21404).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::vec3::{self, Point3, Vec3};
+
+use std::ops::Mul;
+
+// Affine transform as a row major 4x4 matrix, applied to column vectors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    m: [[f32; 4]; 4],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Self { m }
+    }
+
+    pub fn new(m: [[f32; 4]; 4]) -> Self {
+        Self { m }
+    }
+
+    // From the 16 values of a column major matrix, the layout glTF and OpenGL use.
+    pub fn from_columns(values: &[f32; 16]) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, value) in values.iter().enumerate() {
+            m[i % 4][i / 4] = *value;
+        }
+        Self { m }
+    }
+
+    pub fn translate(t: Vec3) -> Self {
+        let mut transform = Self::identity();
+        for axis in 0..3 {
+            transform.m[axis][3] = t[axis];
+        }
+        transform
+    }
+
+    pub fn scale(s: Vec3) -> Self {
+        let mut transform = Self::identity();
+        for axis in 0..3 {
+            transform.m[axis][axis] = s[axis];
+        }
+        transform
+    }
+
+    // Rotation by the unit quaternion `x i + y j + z k + w`.
+    pub fn rotate(q: [f32; 4]) -> Self {
+        let [x, y, z, w] = q;
+        Self::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - z * w),
+                2.0 * (x * z + y * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - x * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - y * w),
+                2.0 * (y * z + x * w),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // Rotation by Euler angles in degrees, around x first, then y and z last.
+    pub fn rotate_euler(degrees: Vec3) -> Self {
+        let axis_rotation = |axis: usize| {
+            let half = degrees[axis].to_radians() / 2.0;
+            let mut q = [0.0, 0.0, 0.0, half.cos()];
+            q[axis] = half.sin();
+            Self::rotate(q)
+        };
+        axis_rotation(2) * axis_rotation(1) * axis_rotation(0)
+    }
+
+    // Scales first, then rotates and translates last.
+    pub fn from_trs(t: Vec3, r: [f32; 4], s: Vec3) -> Self {
+        Self::translate(t) * Self::rotate(r) * Self::scale(s)
+    }
+
+    #[inline]
+    pub fn point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+        let row = |i: usize| m[i][0] * p[0] + m[i][1] * p[1] + m[i][2] * p[2] + m[i][3];
+        let w = row(3);
+        let p = Point3::new(row(0), row(1), row(2));
+        if w == 1.0 || w == 0.0 {
+            p
+        } else {
+            p / w
+        }
+    }
+
+    #[inline]
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        let row = |i: usize| m[i][0] * v[0] + m[i][1] * v[1] + m[i][2] * v[2];
+        Vec3::new(row(0), row(1), row(2))
+    }
+
+    // Normals transform with the inverse transpose, which is the cofactor matrix up to the
+    // scale of the determinant. The result is normalized.
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        let m = &self.m;
+        let cofactor = |i: usize, j: usize| {
+            let (i0, i1) = ((i + 1) % 3, (i + 2) % 3);
+            let (j0, j1) = ((j + 1) % 3, (j + 2) % 3);
+            m[i0][j0] * m[i1][j1] - m[i0][j1] * m[i1][j0]
+        };
+        let row = |i: usize| cofactor(i, 0) * n[0] + cofactor(i, 1) * n[1] + cofactor(i, 2) * n[2];
+        let sign = self.determinant().signum();
+        vec3::unit_vector(sign * Vec3::new(row(0), row(1), row(2)))
+    }
+
+    // Inverse of the affine transform, `None` when it is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.determinant();
+        if det == 0.0 {
+            return None;
+        }
+        let m = &self.m;
+        let cofactor = |i: usize, j: usize| {
+            let (i0, i1) = ((i + 1) % 3, (i + 2) % 3);
+            let (j0, j1) = ((j + 1) % 3, (j + 2) % 3);
+            m[i0][j0] * m[i1][j1] - m[i0][j1] * m[i1][j0]
+        };
+        // The inverse of the linear part is the transposed cofactor matrix over the determinant,
+        // the translation is undone after it.
+        let mut inverse = Self::identity();
+        for i in 0..3 {
+            for j in 0..3 {
+                inverse.m[i][j] = cofactor(j, i) / det;
+            }
+        }
+        for i in 0..3 {
+            inverse.m[i][3] = -(0..3).map(|k| inverse.m[i][k] * m[k][3]).sum::<f32>();
+        }
+        Some(inverse)
+    }
+
+    // Determinant of the linear part, negative when the transform mirrors.
+    pub fn determinant(&self) -> f32 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+    fn mul(self, rhs: Transform) -> Transform {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Transform { m }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trs() {
+        // Quarter turn around y.
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let transform = Transform::from_trs(
+            Vec3::new(1.0, 2.0, 3.0),
+            [0.0, half, 0.0, half],
+            Vec3::new(2.0, 2.0, 2.0),
+        );
+        let p = transform.point(Point3::new(1.0, 0.0, 0.0));
+        assert!((p - Point3::new(1.0, 2.0, 1.0)).length() < 1e-5);
+        let v = transform.vector(Vec3::new(0.0, 0.0, 1.0));
+        assert!((v - Vec3::new(2.0, 0.0, 0.0)).length() < 1e-5);
+        let inverse = transform.inverse().unwrap();
+        assert!((inverse.point(p) - Point3::new(1.0, 0.0, 0.0)).length() < 1e-5);
+        assert!(Transform::scale(Vec3::default()).inverse().is_none());
+
+        let euler = Transform::rotate_euler(Vec3::new(0.0, 90.0, 0.0));
+        let v = euler.vector(Vec3::new(0.0, 0.0, 1.0));
+        assert!((v - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-5);
+
+        let columns = [
+            1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 5.0, 6.0, 7.0, 1.0,
+        ];
+        assert_eq!(
+            Transform::from_columns(&columns),
+            Transform::translate(Vec3::new(5.0, 6.0, 7.0))
+        );
+    }
+
+    #[test]
+    fn test_normal() {
+        let transform = Transform::scale(Vec3::new(1.0, 4.0, 1.0))
+            * Transform::rotate([0.0, 0.0, 0.38268343, 0.9238795]);
+        let tangent = Vec3::new(1.0, -1.0, 0.0);
+        let n = transform.normal(Vec3::new(1.0, 1.0, 0.0));
+        assert!(vec3::dot(n, transform.vector(tangent)).abs() < 1e-5);
+        assert!((n.length() - 1.0).abs() < 1e-5);
+
+        let mirror = Transform::scale(Vec3::new(-1.0, 1.0, 1.0));
+        assert!(mirror.determinant() < 0.0);
+        assert_eq!(
+            mirror.normal(Vec3::new(1.0, 0.0, 0.0)),
+            Vec3::new(-1.0, 0.0, 0.0)
+        );
+    }
+}
@@ -1,6 +1,7 @@
 #![allow(clippy::approx_constant)]
 
 use std::fmt::Display;
+use std::hash::Hasher;
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub};
 use std::ops::{Index, IndexMut};
 
@@ -195,6 +196,13 @@ pub fn unit_vector(v: Vec3) -> Vec3 {
     v / v.length()
 }
 
+pub fn fingerprint(v: &Vec3, state: &mut dyn Hasher) {
+    // Hash the exact bit patterns, `Vec3` can't implement `Hash` itself because of `-0.0 == 0.0`.
+    state.write_u32(v.x.to_bits());
+    state.write_u32(v.y.to_bits());
+    state.write_u32(v.z.to_bits());
+}
+
 #[inline]
 pub fn random_in_unit_sphere() -> Vec3 {
     loop {
@@ -218,6 +226,42 @@ pub fn random_in_unit_disk() -> Vec3 {
     random_in_unit_disk()
 }
 
+// Concentric mapping of a uniform 2D sample onto the unit disk, keeps the stratification of
+// the sample unlike rejection sampling.
+#[inline]
+pub fn sample_unit_disk(u: (f32, f32)) -> Vec3 {
+    let (x, y) = (2.0 * u.0 - 1.0, 2.0 * u.1 - 1.0);
+    if x == 0.0 && y == 0.0 {
+        return Vec3::default();
+    }
+    let (r, theta) = if x.abs() > y.abs() {
+        (x, std::f32::consts::FRAC_PI_4 * (y / x))
+    } else {
+        (
+            y,
+            std::f32::consts::FRAC_PI_2 - std::f32::consts::FRAC_PI_4 * (x / y),
+        )
+    };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+// Maps a uniform 2D sample onto the unit sphere.
+#[inline]
+pub fn sample_unit_vector(u: (f32, f32)) -> Vec3 {
+    let z = 1.0 - 2.0 * u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * std::f32::consts::PI * u.1;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+// Cosine weighted direction on the hemisphere around +z.
+#[inline]
+pub fn sample_cosine_hemisphere(u: (f32, f32)) -> Vec3 {
+    let d = sample_unit_disk(u);
+    let z = (1.0 - d[0] * d[0] - d[1] * d[1]).max(0.0).sqrt();
+    Vec3::new(d[0], d[1], z)
+}
+
 #[inline]
 pub fn random_unit_vector() -> Vec3 {
     unit_vector(random_in_unit_sphere())
@@ -379,6 +423,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sample_mappings() {
+        for u in [
+            (0.0, 0.0),
+            (0.5, 0.5),
+            (0.99, 0.01),
+            (0.25, 0.75),
+            (0.1, 0.9),
+        ] {
+            assert!(sample_unit_disk(u).length_squared() <= 1.0 + 1e-6);
+            assert!((sample_unit_vector(u).length() - 1.0).abs() < 1e-6);
+            let w = sample_cosine_hemisphere(u);
+            assert!((w.length() - 1.0).abs() < 1e-6 && w[2] >= 0.0);
+        }
+        assert_eq!(sample_unit_disk((0.5, 0.5)), Vec3::default());
+        assert_eq!(sample_unit_vector((0.0, 0.0)), Vec3::new(0.0, 0.0, 1.0));
+    }
+
     #[test]
     fn test_refracted_vec() {
         let uv = Vec3::new(0.8, 0.2, 0.3);
@@ -0,0 +1,136 @@
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::environment::Background;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::light::LightType;
+use crate::ray::Ray;
+
+use std::hash::Hasher;
+use std::sync::Arc;
+
+// Everything a render looks at: the objects behind a bounding volume hierarchy, the analytic
+// lights gathered by shadow rays and what rays leaving the scene see.
+#[derive(Default)]
+pub struct World {
+    objects: Bvh,
+    lights: Vec<LightType>,
+    background: Option<Background>,
+}
+
+impl World {
+    pub fn new(objects: Vec<Arc<dyn Hittable>>) -> Self {
+        Self {
+            objects: Bvh::new(objects),
+            lights: vec![],
+            background: None,
+        }
+    }
+
+    pub fn with_light(mut self, light: LightType) -> Self {
+        self.lights.push(light);
+        self
+    }
+
+    // Replaces the one there was, the gradient of the book when there's none.
+    pub fn with_background(mut self, background: Background) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    #[inline]
+    pub fn objects(&self) -> &[Arc<dyn Hittable>] {
+        self.objects.objects()
+    }
+
+    #[inline]
+    pub fn lights(&self) -> &[LightType] {
+        &self.lights
+    }
+
+    #[inline]
+    pub fn background(&self) -> Option<&Background> {
+        self.background.as_ref()
+    }
+
+    #[inline]
+    pub fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.objects.hit(r, ray_t, rec)
+    }
+
+    #[inline]
+    pub fn bounding_box(&self) -> Aabb {
+        self.objects.bounding_box()
+    }
+
+    // Everything that affects the rendered image, see `Hittable::fingerprint`.
+    pub fn fingerprint(&self, state: &mut dyn Hasher) {
+        self.objects.fingerprint(state);
+        for light in &self.lights {
+            light.fingerprint(state);
+        }
+        if let Some(background) = &self.background {
+            background.fingerprint(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::Interpolation;
+    use crate::color::Color;
+    use crate::hittable::Sphere;
+    use crate::instance::Instance;
+    use crate::light::PointLight;
+    use crate::material::{Lambertian, MaterialType};
+    use crate::vec3::{Point3, Vec3};
+
+    use std::collections::hash_map::DefaultHasher;
+
+    #[test]
+    fn test_lights_and_background_change_the_fingerprint() {
+        let sphere = || -> Arc<dyn Hittable> {
+            Arc::new(Sphere::new(
+                Point3::new(0.0, 0.0, 0.0),
+                1.0,
+                MaterialType::Lambertian(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+            ))
+        };
+        let hash = |world: &World| {
+            let mut hasher = DefaultHasher::new();
+            world.fingerprint(&mut hasher);
+            hasher.finish()
+        };
+        let light = || {
+            LightType::Point(PointLight::new(
+                Point3::new(0.0, 2.0, 0.0),
+                Color::new(1.0, 1.0, 1.0),
+            ))
+        };
+
+        let plain = World::new(vec![sphere()]);
+        let lit = World::new(vec![sphere()]).with_light(light());
+        let gradient = World::new(vec![sphere()])
+            .with_light(light())
+            .with_background(Background::Gradient);
+        assert_eq!(hash(&plain), hash(&World::new(vec![sphere()])));
+        assert_ne!(hash(&plain), hash(&lit));
+        assert_ne!(hash(&lit), hash(&gradient));
+        assert_eq!(lit.lights().len(), 1);
+
+        // The same keys, only passed through differently.
+        let moving = |interpolation| -> Arc<dyn Hittable> {
+            Arc::new(
+                Instance::new(sphere(), interpolation)
+                    .with_translation(0.0, Vec3::new(0.0, 0.0, 0.0))
+                    .with_translation(0.5, Vec3::new(1.0, 0.0, 0.0))
+                    .with_translation(1.0, Vec3::new(1.0, 1.0, 0.0)),
+            )
+        };
+        let linear = World::new(vec![moving(Interpolation::Linear)]);
+        let spline = World::new(vec![moving(Interpolation::CatmullRom)]);
+        assert_ne!(hash(&linear), hash(&spline));
+        assert_eq!(plain.bounding_box(), sphere().bounding_box());
+    }
+}
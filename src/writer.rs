@@ -0,0 +1,108 @@
+use crate::color;
+use crate::framebuffer::Framebuffer;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// Plain text PPM, gamma corrected.
+pub fn write_ppm(f: &mut impl Write, framebuffer: &Framebuffer) -> io::Result<()> {
+    writeln!(
+        f,
+        "P3\n{} {}\n255",
+        framebuffer.width(),
+        framebuffer.height()
+    )?;
+    for j in 0..framebuffer.height() {
+        for i in 0..framebuffer.width() {
+            let [r, g, b] = color::to_rgb8(framebuffer.pixel(i, j));
+            writeln!(f, "{} {} {}", r, g, b)?;
+        }
+    }
+    Ok(())
+}
+
+// Binary PPM, gamma corrected.
+pub fn write_ppm_binary(f: &mut impl Write, framebuffer: &Framebuffer) -> io::Result<()> {
+    write!(
+        f,
+        "P6\n{} {}\n255\n",
+        framebuffer.width(),
+        framebuffer.height()
+    )?;
+    for j in 0..framebuffer.height() {
+        for i in 0..framebuffer.width() {
+            f.write_all(&color::to_rgb8(framebuffer.pixel(i, j)))?;
+        }
+    }
+    Ok(())
+}
+
+// Portable float map, keeps the linear radiance for compositing and tone mapping elsewhere.
+pub fn write_pfm(f: &mut impl Write, framebuffer: &Framebuffer) -> io::Result<()> {
+    // A negative scale marks little endian data, scanlines go from bottom to top.
+    write!(
+        f,
+        "PF\n{} {}\n-1.0\n",
+        framebuffer.width(),
+        framebuffer.height()
+    )?;
+    for j in (0..framebuffer.height()).rev() {
+        for i in 0..framebuffer.width() {
+            let pixel_color = framebuffer.pixel(i, j);
+            for c in 0..3 {
+                f.write_all(&pixel_color[c].to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Picks the encoding by the file extension, `.pfm` or `.ppm`.
+pub fn save(path: &Path, framebuffer: &Framebuffer) -> io::Result<()> {
+    let mut f = BufWriter::new(File::create(path)?);
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("pfm") => write_pfm(&mut f, framebuffer)?,
+        Some("ppm") => write_ppm_binary(&mut f, framebuffer)?,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown image format of {}", path.display()),
+            ))
+        }
+    }
+    f.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn test_write_ppm() {
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.add_samples(0, 0, Color::new(0.25, 1.0, 4.0), 0.0, 1);
+        let mut out = vec![];
+        write_ppm(&mut out, &framebuffer).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "P3\n2 1\n255\n128 255 255\n0 0 0\n"
+        );
+    }
+
+    #[test]
+    fn test_write_pfm() {
+        let mut framebuffer = Framebuffer::new(1, 2);
+        framebuffer.add_samples(0, 1, Color::new(0.5, 2.0, 4.0), 0.0, 2);
+        let mut out = vec![];
+        write_pfm(&mut out, &framebuffer).unwrap();
+
+        let header = b"PF\n1 2\n-1.0\n";
+        assert_eq!(&out[..header.len()], header);
+        // The bottom row comes first.
+        let first = f32::from_le_bytes(out[header.len()..header.len() + 4].try_into().unwrap());
+        assert_eq!(first, 0.25);
+        assert_eq!(out.len(), header.len() + 2 * 3 * 4);
+    }
+}